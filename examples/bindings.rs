@@ -0,0 +1,147 @@
+use bevy::{
+    app::{App, Startup, Update},
+    asset::Assets,
+    camera::Camera2d,
+    color::{Color, Srgba},
+    light::GlobalAmbientLight,
+    math::Vec3,
+    mesh::Mesh2d,
+    prelude::{
+        Commands, Component, Entity, OrthographicProjection, Projection, Query, ReflectComponent,
+        Res, ResMut, Resource, Transform,
+    },
+    reflect::Reflect,
+    sprite_render::{AlphaMode2d, ColorMaterial, MeshMaterial2d},
+    time::{Time, Virtual},
+    DefaultPlugins,
+};
+use bevy_rich_text3d::{
+    SegmentStyle, Text3d, Text3dBindings, Text3dBounds, Text3dPlugin, Text3dStyle, TextAlign,
+    TextAtlas,
+};
+
+#[derive(Debug, Component, Reflect)]
+#[reflect(Component)]
+pub struct Player {
+    health: f32,
+    gold: u32,
+}
+
+#[derive(Debug, Component)]
+pub struct Buff {
+    name: &'static str,
+    seconds: f32,
+}
+
+#[derive(Debug, Resource)]
+pub struct Elapsed(f32);
+
+pub fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(Text3dPlugin {
+            load_system_fonts: true,
+            ..Default::default()
+        })
+        .insert_resource(GlobalAmbientLight {
+            color: Color::WHITE,
+            brightness: 800.,
+            ..Default::default()
+        })
+        .register_type::<Player>()
+        .insert_resource(Elapsed(0.0))
+        .add_systems(Startup, setup)
+        .add_systems(Update, simulate)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut standard_materials: ResMut<Assets<ColorMaterial>>) {
+    let mat = standard_materials.add(ColorMaterial {
+        texture: Some(TextAtlas::DEFAULT_IMAGE.clone()),
+        alpha_mode: AlphaMode2d::Blend,
+        ..Default::default()
+    });
+
+    commands.spawn(Player {
+        health: 100.0,
+        gold: 0,
+    });
+
+    let bindings = Text3dBindings::new()
+        .with_path("hp", "Player.health")
+        .with_component("gold", |player: &Player| player.gold)
+        // Whole seconds, so the text is only rebuilt once per second.
+        .with_resource("time", |elapsed: &Elapsed| elapsed.0 as u32)
+        .with_component_list("buffs", |buff: &Buff| {
+            vec![
+                ("name", buff.name.to_owned()),
+                ("time", (buff.seconds.ceil() as u32).to_string()),
+            ]
+        })
+        .with_condition("low", |world| {
+            let mut query = world.try_query::<&Player>()?;
+            Some(query.iter(world).next()?.health < 30.0)
+        })
+        .with_style("hp_color", |world| {
+            let mut query = world.try_query::<&Player>()?;
+            let low = query.iter(world).next()?.health < 30.0;
+            Some(SegmentStyle {
+                fill_color: low.then_some(Srgba::new(1., 0., 0., 1.)),
+                ..Default::default()
+            })
+        });
+
+    let text = Text3d::parse(
+        "HP: {@hp_color: {hp:0.0}} {?low: {red:(Low!)}}{:else: {?hp >= 90: (Healthy)}}\nGold: {gold:,}\nTime: {time:mm:ss}\n{#each buffs: {name} {time:m:ss}\n}",
+        bindings.parser(&mut commands),
+    )
+    .unwrap();
+
+    commands.insert_resource(bindings);
+    commands.spawn((
+        text,
+        Text3dStyle {
+            size: 48.,
+            color: Srgba::new(0., 1., 1., 1.),
+            align: TextAlign::Left,
+            ..Default::default()
+        },
+        Text3dBounds { width: 600. },
+        Mesh2d::default(),
+        MeshMaterial2d(mat.clone()),
+    ));
+    commands.spawn((
+        Camera2d,
+        Projection::Orthographic(OrthographicProjection::default_3d()),
+        Transform::from_translation(Vec3::new(0., 0., 1.))
+            .looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
+    ));
+}
+
+fn simulate(
+    mut commands: Commands,
+    time: Res<Time<Virtual>>,
+    mut elapsed: ResMut<Elapsed>,
+    mut players: Query<&mut Player>,
+    mut buffs: Query<(Entity, &mut Buff)>,
+) {
+    let previous = elapsed.0 as u32;
+    elapsed.0 += time.delta_secs();
+    // A new buff every 3 seconds, expired buffs are removed from the list.
+    if elapsed.0 as u32 != previous && previous.is_multiple_of(3) {
+        commands.spawn(Buff {
+            name: ["Haste", "Regeneration", "Shield"][fastrand::usize(0..3)],
+            seconds: 5.0 + fastrand::f32() * 10.0,
+        });
+    }
+    for (entity, mut buff) in &mut buffs {
+        buff.seconds -= time.delta_secs();
+        if buff.seconds <= 0.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+    for mut player in &mut players {
+        player.health = (player.health - time.delta_secs() * 7.3).rem_euclid(100.0);
+        player.gold += fastrand::u32(0..50);
+    }
+}
@@ -0,0 +1,80 @@
+use bevy::{
+    app::{App, Startup},
+    asset::Assets,
+    camera::Camera2d,
+    color::Srgba,
+    math::Vec3,
+    mesh::Mesh2d,
+    prelude::{Commands, Res, ResMut, Transform},
+    sprite_render::MeshMaterial2d,
+    time::Time,
+    DefaultPlugins,
+};
+use bevy_rich_text3d::{
+    Text3d, Text3dPlugin, Text3dStyle, TextEffect, TextEffectMaterial2d, TextEffectPlugin,
+};
+
+pub fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(Text3dPlugin {
+            load_system_fonts: true,
+            ..Default::default()
+        })
+        .add_plugins(TextEffectPlugin)
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut materials: ResMut<Assets<TextEffectMaterial2d>>,
+) {
+    let effects = [
+        TextEffect::Typewriter {
+            since: time.elapsed_secs(),
+            speed: 4.,
+            softness: 1.,
+        },
+        TextEffect::Rainbow {
+            speed: 0.5,
+            frequency: 0.1,
+            saturation: 0.8,
+        },
+        TextEffect::Wave {
+            amplitude: 8.,
+            frequency: 0.5,
+            speed: 1.,
+        },
+        TextEffect::Shake {
+            intensity: 3.,
+            speed: 20.,
+        },
+        TextEffect::Dissolve {
+            progress: 0.3,
+            edge: 0.1,
+            edge_color: Srgba::new(1., 0.5, 0., 1.),
+        },
+        TextEffect::OutlineGlow {
+            color: Srgba::new(0., 1., 1., 1.),
+            intensity: 1.,
+            pulse: 1.,
+        },
+    ];
+
+    for (i, effect) in effects.into_iter().enumerate() {
+        commands.spawn((
+            Text3d::new(format!("{effect:?}").split_whitespace().next().unwrap()),
+            Text3dStyle {
+                size: 48.,
+                ..Text3dStyle::for_text_effects()
+            },
+            Mesh2d::default(),
+            MeshMaterial2d(materials.add(TextEffectMaterial2d::new(effect))),
+            Transform::from_translation(Vec3::new(0., 200. - i as f32 * 80., 0.)),
+        ));
+    }
+
+    commands.spawn(Camera2d);
+}
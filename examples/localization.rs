@@ -0,0 +1,88 @@
+use bevy::{
+    app::{App, Startup, Update},
+    asset::{AssetServer, Assets},
+    camera::Camera2d,
+    color::Srgba,
+    input::{keyboard::KeyCode, ButtonInput},
+    mesh::Mesh2d,
+    prelude::{Commands, Query, Res, ResMut, Transform},
+    sprite_render::{AlphaMode2d, ColorMaterial, MeshMaterial2d},
+    DefaultPlugins,
+};
+use bevy_rich_text3d::{
+    LocaleArg, Text3dBounds, Text3dLocale, Text3dLocalized, Text3dPlugin, Text3dStyle, TextAtlas,
+};
+
+pub fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(Text3dPlugin {
+            load_system_fonts: true,
+            ..Default::default()
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, (switch_locale, add_apples))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(
+        Text3dLocale::new("en-US")
+            .with_table("en", server.load("locale/en.rtext"))
+            .with_table("fr", server.load("locale/fr.rtext"))
+            .with_fallback("en"),
+    );
+
+    let mat = materials.add(ColorMaterial {
+        texture: Some(TextAtlas::DEFAULT_IMAGE.clone()),
+        alpha_mode: AlphaMode2d::Blend,
+        ..Default::default()
+    });
+
+    let texts = [
+        Text3dLocalized::new("greeting").with_arg("name", "Alice"),
+        Text3dLocalized::new("apples").with_arg("count", 0),
+        Text3dLocalized::new("hint"),
+    ];
+
+    for (i, text) in texts.into_iter().enumerate() {
+        commands.spawn((
+            text,
+            Text3dStyle {
+                size: 32.,
+                color: Srgba::WHITE,
+                ..Default::default()
+            },
+            Text3dBounds { width: 600. },
+            Mesh2d::default(),
+            MeshMaterial2d(mat.clone()),
+            Transform::from_xyz(0., 100. - i as f32 * 100., 0.),
+        ));
+    }
+
+    commands.spawn(Camera2d);
+}
+
+fn switch_locale(input: Res<ButtonInput<KeyCode>>, mut locale: ResMut<Text3dLocale>) {
+    if input.just_pressed(KeyCode::Space) {
+        locale.locale = if locale.locale == "fr-FR" {
+            "en-US".into()
+        } else {
+            "fr-FR".into()
+        };
+    }
+}
+
+fn add_apples(input: Res<ButtonInput<KeyCode>>, mut query: Query<&mut Text3dLocalized>) {
+    if input.just_pressed(KeyCode::ArrowUp) {
+        for mut text in &mut query {
+            if let Some(LocaleArg::Number(count)) = text.get_arg("count").cloned() {
+                text.set_arg("count", count + 1.);
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use bevy::{
+    app::{App, Startup},
+    asset::Assets,
+    camera::Camera2d,
+    color::Srgba,
+    math::Vec3,
+    mesh::Mesh2d,
+    prelude::{Commands, OrthographicProjection, Projection, ResMut, Transform},
+    sprite_render::{AlphaMode2d, ColorMaterial, MeshMaterial2d},
+    DefaultPlugins,
+};
+use bevy_rich_text3d::{
+    Fade, GlyphModifiers, Shake, SpanEffectModifier, Text3d, Text3dPlugin, Text3dStyle, TextAtlas,
+    Wave,
+};
+
+pub fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(Text3dPlugin {
+            load_system_fonts: true,
+            ..Default::default()
+        })
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let mat = materials.add(ColorMaterial {
+        texture: Some(TextAtlas::DEFAULT_IMAGE.clone()),
+        alpha_mode: AlphaMode2d::Blend,
+        ..Default::default()
+    });
+
+    commands.spawn((
+        Text3d::new("Waving without shaders!"),
+        Text3dStyle {
+            size: 64.,
+            color: Srgba::new(0., 1., 1., 1.),
+            ..Default::default()
+        },
+        GlyphModifiers::from(Wave::default()),
+        Mesh2d::default(),
+        MeshMaterial2d(mat.clone()),
+        Transform::from_translation(Vec3::new(0., 100., 0.)),
+    ));
+
+    commands.spawn((
+        Text3d::new("Shaking in fear!"),
+        Text3dStyle {
+            size: 64.,
+            color: Srgba::RED,
+            ..Default::default()
+        },
+        GlyphModifiers::from(Shake::default()),
+        Mesh2d::default(),
+        MeshMaterial2d(mat.clone()),
+    ));
+
+    commands.spawn((
+        Text3d::new("Fading in one by one..."),
+        Text3dStyle {
+            size: 64.,
+            ..Default::default()
+        },
+        GlyphModifiers::new()
+            .with(Fade {
+                start: 0.5,
+                speed: 8.0,
+            })
+            .with(Wave {
+                amplitude: 0.05,
+                ..Default::default()
+            }),
+        Mesh2d::default(),
+        MeshMaterial2d(mat.clone()),
+        Transform::from_translation(Vec3::new(0., -100., 0.)),
+    ));
+
+    commands.spawn((
+        Text3d::parse_raw("Markup {wave:waves}, {shake-2:shakes} and {wave, fade:both}!").unwrap(),
+        Text3dStyle {
+            size: 48.,
+            ..Default::default()
+        },
+        GlyphModifiers::from(SpanEffectModifier::default()),
+        Mesh2d::default(),
+        MeshMaterial2d(mat.clone()),
+        Transform::from_translation(Vec3::new(0., -200., 0.)),
+    ));
+
+    commands.spawn((
+        Camera2d,
+        Projection::Orthographic(OrthographicProjection::default_3d()),
+        Transform::from_translation(Vec3::new(0., 0., 1.))
+            .looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
+    ));
+}
@@ -0,0 +1,75 @@
+use bevy::{
+    app::{App, Startup, Update},
+    asset::Assets,
+    camera::Camera2d,
+    color::Srgba,
+    ecs::message::MessageReader,
+    input::{keyboard::KeyCode, ButtonInput},
+    log::info,
+    mesh::Mesh2d,
+    prelude::{Commands, Query, Res, ResMut},
+    sprite_render::{AlphaMode2d, ColorMaterial, MeshMaterial2d},
+    DefaultPlugins,
+};
+use bevy_rich_text3d::{
+    Text3d, Text3dBounds, Text3dPlugin, Text3dReveal, Text3dRevealMessage, Text3dStyle, TextAtlas,
+};
+
+pub fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(Text3dPlugin {
+            load_system_fonts: true,
+            ..Default::default()
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, (skip, play_sound))
+        .run();
+}
+
+fn setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let mat = materials.add(ColorMaterial {
+        texture: Some(TextAtlas::DEFAULT_IMAGE.clone()),
+        alpha_mode: AlphaMode2d::Blend,
+        ..Default::default()
+    });
+
+    commands.spawn((
+        Text3d::parse_raw(
+            "Hello, traveler. The road ahead is {red:dangerous}.{pause=1} \
+            {speed=0.25}Very{speed=1} dangerous. Press space to skip.",
+        )
+        .unwrap(),
+        Text3dStyle {
+            size: 32.,
+            color: Srgba::WHITE,
+            ..Default::default()
+        },
+        Text3dBounds { width: 600. },
+        Text3dReveal::new(20.),
+        Mesh2d::default(),
+        MeshMaterial2d(mat),
+    ));
+
+    commands.spawn(Camera2d);
+}
+
+fn skip(input: Res<ButtonInput<KeyCode>>, mut query: Query<&mut Text3dReveal>) {
+    if input.just_pressed(KeyCode::Space) {
+        for mut reveal in &mut query {
+            reveal.skip();
+        }
+    }
+}
+
+fn play_sound(mut messages: MessageReader<Text3dRevealMessage>) {
+    for message in messages.read() {
+        match message {
+            Text3dRevealMessage::Glyph { glyph, .. } if !glyph.char.is_whitespace() => {
+                info!("blip: {}", glyph.char)
+            }
+            Text3dRevealMessage::Finished { .. } => info!("finished!"),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Run with `--features bevy/file_watcher` and edit `assets/strings.rtext` to hot reload.
+use bevy::{
+    app::{App, Startup},
+    asset::{AssetServer, Assets},
+    camera::Camera2d,
+    color::Srgba,
+    mesh::Mesh2d,
+    prelude::{Commands, Res, ResMut, Transform},
+    sprite_render::{AlphaMode2d, ColorMaterial, MeshMaterial2d},
+    DefaultPlugins,
+};
+use bevy_rich_text3d::{
+    Text3dBounds, Text3dKey, Text3dPlugin, Text3dStrings, Text3dStyle, TextAtlas,
+};
+
+pub fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(Text3dPlugin {
+            load_system_fonts: true,
+            ..Default::default()
+        })
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mat = materials.add(ColorMaterial {
+        texture: Some(TextAtlas::DEFAULT_IMAGE.clone()),
+        alpha_mode: AlphaMode2d::Blend,
+        ..Default::default()
+    });
+
+    let strings = server.load::<Text3dStrings>("strings.rtext");
+
+    for (i, key) in ["title", "greeting", "intro"].into_iter().enumerate() {
+        commands.spawn((
+            Text3dKey::new(strings.clone(), key),
+            Text3dStyle {
+                size: 32.,
+                color: Srgba::WHITE,
+                ..Default::default()
+            },
+            Text3dBounds { width: 600. },
+            Mesh2d::default(),
+            MeshMaterial2d(mat.clone()),
+            Transform::from_xyz(0., 150. - i as f32 * 150., 0.),
+        ));
+    }
+
+    commands.spawn(Camera2d);
+}
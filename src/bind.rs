@@ -0,0 +1,794 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use bevy::ecs::{
+    change_detection::{DetectChanges, Ref, Tick},
+    component::Component,
+    entity::Entity,
+    hierarchy::ChildOf,
+    query::QueryState,
+    resource::Resource,
+    system::Commands,
+    world::{Mut, World},
+};
+#[cfg(feature = "reflect")]
+use bevy::{
+    ecs::{
+        archetype::Archetype,
+        component::ComponentId,
+        entity_disabling::DefaultQueryFilters,
+        reflect::{AppTypeRegistry, ReflectComponent},
+    },
+    reflect::{GetPath, PartialReflect, Reflect},
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    parse_util::{ConditionOutput, DefaultFn, ParseConditionFn, ParseValueFn},
+    FetchedCondition, FetchedItem, FetchedList, FetchedStyle, FetchedText, ParseBuilder,
+    ParseError, SegmentStyle, Text3dSegment, WhitespaceMode,
+};
+
+/// Last and current run of [`text_bindings_system`], bindings may skip sources not changed since the last run.
+///
+/// `None` if the source must be read, i.e. for newly bound entities.
+type Since = Option<(Tick, Tick)>;
+
+type ValueBinding = Arc<dyn Fn(&World, Since) -> Option<String> + Send + Sync>;
+type ConditionBinding = Arc<dyn Fn(&World, Since) -> Option<bool> + Send + Sync>;
+type StyleBinding = Arc<dyn Fn(&World, Since) -> Option<SegmentStyle> + Send + Sync>;
+type ListBinding = Arc<dyn Fn(&World, Since) -> Option<Vec<Vec<(String, String)>>> + Send + Sync>;
+
+/// Whether a component or resource last changed at `tick` needs to be read again.
+fn is_changed(tick: Tick, since: Since) -> bool {
+    since.is_none_or(|(last_run, this_run)| tick.is_newer_than(last_run, this_run))
+}
+
+/// Binds value, condition, dynamic style and list names to sources in the world.
+///
+/// Entities spawned by [`Text3dBindings::parser`] are updated before rendering,
+/// text is only rebuilt if the value changes.
+///
+/// Functions of the world are called every frame, bindings to components and resources
+/// are only read and formatted if they changed.
+///
+/// # Example
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_rich_text3d::*;
+/// #[derive(Component)]
+/// struct Player {
+///     health: f32,
+/// }
+///
+/// fn setup(mut commands: Commands) {
+///     let bindings = Text3dBindings::new()
+///         .with_component("hp", |player: &Player| player.health)
+///         .with_condition("low", |world| {
+///             let mut query = world.try_query::<&Player>()?;
+///             Some(query.iter(world).next()?.health < 10.0)
+///         });
+///     let text = Text3d::parse("HP: {hp:0.0}{?low: Low!}", bindings.parser(&mut commands));
+///     commands.spawn(text.unwrap());
+///     commands.insert_resource(bindings);
+/// }
+/// ```
+#[derive(Default, Resource)]
+pub struct Text3dBindings {
+    values: FxHashMap<String, ValueBinding>,
+    conditions: FxHashMap<String, ConditionBinding>,
+    styles: FxHashMap<String, StyleBinding>,
+    lists: FxHashMap<String, ListBinding>,
+}
+
+impl std::fmt::Debug for Text3dBindings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Text3dBindings")
+            .field("values", &self.values.keys())
+            .field("conditions", &self.conditions.keys())
+            .field("styles", &self.styles.keys())
+            .field("lists", &self.lists.keys())
+            .finish()
+    }
+}
+
+impl Text3dBindings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind a value to a function of the world, values are not updated if `None`.
+    pub fn with_value<T: ToString>(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&World) -> Option<T> + Send + Sync + 'static,
+    ) -> Self {
+        self.values.insert(
+            name.into(),
+            Arc::new(move |world, _| Some(f(world)?.to_string())),
+        );
+        self
+    }
+
+    /// Bind a value to a field of a resource.
+    pub fn with_resource<R: Resource, T: ToString>(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&R) -> T + Send + Sync + 'static,
+    ) -> Self {
+        self.values.insert(
+            name.into(),
+            Arc::new(move |world, since| {
+                let resource = world.get_resource_ref::<R>()?;
+                is_changed(resource.last_changed(), since).then(|| f(&resource).to_string())
+            }),
+        );
+        self
+    }
+
+    /// Bind a value to a field of a component on the first entity that has it.
+    pub fn with_component<C: Component, T: ToString>(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&C) -> T + Send + Sync + 'static,
+    ) -> Self {
+        // The query and the entity that was read.
+        let cache = Mutex::new((None::<QueryState<(Entity, Ref<'static, C>)>>, None));
+        self.values.insert(
+            name.into(),
+            Arc::new(move |world, since| {
+                let mut cache = cache.lock().ok()?;
+                let (query, first) = &mut *cache;
+                if query.is_none() {
+                    *query = world.try_query();
+                }
+                let (entity, component) = query.as_mut()?.iter(world).next()?;
+                let moved = first.replace(entity) != Some(entity);
+                (moved || is_changed(component.last_changed(), since))
+                    .then(|| f(&component).to_string())
+            }),
+        );
+        self
+    }
+
+    /// Bind a condition to a function of the world, conditions are not updated if `None`.
+    pub fn with_condition(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&World) -> Option<bool> + Send + Sync + 'static,
+    ) -> Self {
+        self.conditions
+            .insert(name.into(), Arc::new(move |world, _| f(world)));
+        self
+    }
+
+    /// Bind a dynamic style like `{@name: ...}` to a function of the world, styles are not updated if `None`.
+    pub fn with_style(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&World) -> Option<SegmentStyle> + Send + Sync + 'static,
+    ) -> Self {
+        self.styles
+            .insert(name.into(), Arc::new(move |world, _| f(world)));
+        self
+    }
+
+    /// Bind a list like `{#each name: ...}` to a function of the world, lists are not updated if `None`.
+    ///
+    /// Each item is a list of field names and values.
+    pub fn with_list(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&World) -> Option<Vec<Vec<(String, String)>>> + Send + Sync + 'static,
+    ) -> Self {
+        self.lists
+            .insert(name.into(), Arc::new(move |world, _| f(world)));
+        self
+    }
+
+    /// Bind a list to all entities with a component, `f` returns the fields of an item.
+    pub fn with_component_list<C: Component>(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&C) -> Vec<(&'static str, String)> + Send + Sync + 'static,
+    ) -> Self {
+        // The query and the number of items that were read.
+        let cache = Mutex::new((None::<QueryState<Ref<'static, C>>>, 0));
+        self.lists.insert(
+            name.into(),
+            Arc::new(move |world, since| {
+                let mut cache = cache.lock().ok()?;
+                let (query, len) = &mut *cache;
+                if query.is_none() {
+                    *query = world.try_query();
+                }
+                let query = query.as_mut()?;
+                let count = query.iter(world).count();
+                if count == *len
+                    && !query
+                        .iter(world)
+                        .any(|component| is_changed(component.last_changed(), since))
+                {
+                    return None;
+                }
+                *len = count;
+                Some(
+                    query
+                        .iter(world)
+                        .map(|component| {
+                            f(&component)
+                                .into_iter()
+                                .map(|(field, value)| (field.to_owned(), value))
+                                .collect()
+                        })
+                        .collect(),
+                )
+            }),
+        );
+        self
+    }
+
+    /// Bind a value and a condition to a reflect path like `Player.health`.
+    ///
+    /// The first segment is the short type path of a reflected component or resource,
+    /// components are read from the first entity that has it.
+    /// The value and the condition are only read if the component or resource changed.
+    /// Only strings, numbers and booleans are supported, booleans can be used as conditions.
+    #[cfg(feature = "reflect")]
+    pub fn with_path(mut self, name: impl Into<String>, path: impl Into<String>) -> Self {
+        let name = name.into();
+        let path = path.into();
+        let value_source = Mutex::new(PathSource::new(&path));
+        let condition_source = Mutex::new(PathSource::new(&path));
+        self.values.insert(
+            name.clone(),
+            Arc::new(move |world, since| {
+                value_source
+                    .lock()
+                    .ok()?
+                    .read(world, since, reflect_to_string)
+            }),
+        );
+        self.conditions.insert(
+            name,
+            Arc::new(move |world, since| {
+                condition_source.lock().ok()?.read(world, since, |value| {
+                    value.try_downcast_ref::<bool>().copied()
+                })
+            }),
+        );
+        self
+    }
+
+    /// Create a [`ParseBuilder`] that spawns bound values, conditions, dynamic styles and lists.
+    ///
+    /// Custom styles can be added with [`ParseBuilder::with_parse_style`].
+    pub fn parser<'a, 'w, 's>(
+        &'a self,
+        commands: &'a mut Commands<'w, 's>,
+    ) -> ParseBuilder<DefaultFn, BindingHook<'a, 'w, 's>, BindingHook<'a, 'w, 's>> {
+        let hook = BindingHook {
+            bindings: self,
+            commands: Rc::new(RefCell::new(commands)),
+        };
+        ParseBuilder {
+            parse_style: DefaultFn,
+            parse_value: hook.clone(),
+            parse_condition: hook,
+            strict: false,
+            whitespace: WhitespaceMode::PreserveNewlines,
+            markdown_blocks: false,
+        }
+    }
+}
+
+/// Parse function of [`Text3dBindings::parser`].
+#[derive(Clone)]
+pub struct BindingHook<'a, 'w, 's> {
+    bindings: &'a Text3dBindings,
+    commands: Rc<RefCell<&'a mut Commands<'w, 's>>>,
+}
+
+impl ParseValueFn for BindingHook<'_, '_, '_> {
+    fn call(
+        &mut self,
+        _index: usize,
+        s: &str,
+    ) -> Result<(Text3dSegment, SegmentStyle), ParseError> {
+        let mut commands = self.commands.borrow_mut();
+        let segment = if let Some(name) = s.strip_prefix('@') {
+            if !self.bindings.styles.contains_key(name) {
+                return Err(ParseError::Custom(format!("Unknown dynamic style {name}.")));
+            }
+            let style = commands.spawn(BoundStyle(name.to_owned())).id();
+            Text3dSegment::Style { style, offset: 0 }
+        } else if let Some(name) = s.strip_prefix("#each ") {
+            if !self.bindings.lists.contains_key(name) {
+                return Err(ParseError::Custom(format!("Unknown list {name}.")));
+            }
+            let list = commands.spawn(BoundList(name.to_owned())).id();
+            Text3dSegment::Each { list, offset: 0 }
+        } else {
+            if !self.bindings.values.contains_key(s) {
+                return Err(ParseError::Custom(format!("Unknown value {s}.")));
+            }
+            Text3dSegment::Extract(commands.spawn(BoundValue(s.to_owned())).id())
+        };
+        Ok((segment, SegmentStyle::default()))
+    }
+}
+
+impl ParseConditionFn for BindingHook<'_, '_, '_> {
+    fn call(&mut self, s: &str) -> Result<ConditionOutput, ParseError> {
+        if !self.bindings.conditions.contains_key(s) {
+            return Err(ParseError::Custom(format!("Unknown condition {s}.")));
+        }
+        let entity = self
+            .commands
+            .borrow_mut()
+            .spawn(BoundCondition(s.to_owned()))
+            .id();
+        Ok(ConditionOutput::Dynamic(entity))
+    }
+}
+
+/// A [`FetchedText`] updated by a value in [`Text3dBindings`].
+#[derive(Debug, Clone, Component)]
+#[require(FetchedText)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct BoundValue(pub String);
+
+/// A [`FetchedCondition`] updated by a condition in [`Text3dBindings`].
+#[derive(Debug, Clone, Component)]
+#[require(FetchedCondition)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct BoundCondition(pub String);
+
+/// A [`FetchedStyle`] updated by a dynamic style in [`Text3dBindings`].
+#[derive(Debug, Clone, Component)]
+#[require(FetchedStyle)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct BoundStyle(pub String);
+
+/// A [`FetchedList`] updated by a list in [`Text3dBindings`].
+///
+/// Items are spawned as children and reused when the list changes.
+#[derive(Debug, Clone, Component)]
+#[require(FetchedList)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct BoundList(pub String);
+
+/// Reuse item entities of a [`FetchedList`], only spawning or despawning the difference.
+fn update_list(world: &mut World, entity: Entity, items: &[Vec<(String, String)>]) {
+    let Some(list) = world.get::<FetchedList>(entity) else {
+        return;
+    };
+    let mut entities = list.0.clone();
+    for item in entities.drain(items.len().min(entities.len())..) {
+        world.despawn(item);
+    }
+    while entities.len() < items.len() {
+        entities.push(world.spawn((FetchedItem::default(), ChildOf(entity))).id());
+    }
+    for (item, fields) in entities.iter().zip(items) {
+        for (name, value) in fields {
+            let field = match world.get::<FetchedItem>(*item).and_then(|x| x.field(name)) {
+                Some(field) => field,
+                None => {
+                    let field = world.spawn((FetchedText::EMPTY, ChildOf(*item))).id();
+                    if let Some(mut fields) = world.get_mut::<FetchedItem>(*item) {
+                        fields.0.push((name.clone(), field));
+                    }
+                    field
+                }
+            };
+            if let Some(text) = world.get_mut::<FetchedText>(field) {
+                FetchedText::set_if_changed(text, value);
+            }
+        }
+    }
+    if let Some(list) = world.get_mut::<FetchedList>(entity) {
+        FetchedList::set_if_changed(list, &entities);
+    }
+}
+
+/// A reflected component or resource found by its short type path, the type and the entity that has it are cached.
+#[cfg(feature = "reflect")]
+struct PathSource {
+    name: String,
+    field: String,
+    component: Option<(ComponentId, ReflectComponent)>,
+    entity: Option<Entity>,
+}
+
+#[cfg(feature = "reflect")]
+impl PathSource {
+    fn new(path: &str) -> Self {
+        let (name, field) = path.split_once('.').unwrap_or((path, ""));
+        PathSource {
+            name: name.to_owned(),
+            field: field.to_owned(),
+            component: None,
+            entity: None,
+        }
+    }
+
+    /// Read a field if the component changed since the last run or moved to another entity.
+    fn read<T>(
+        &mut self,
+        world: &World,
+        since: Since,
+        f: impl FnOnce(&dyn PartialReflect) -> Option<T>,
+    ) -> Option<T> {
+        if self.component.is_none() {
+            let registry = world.get_resource::<AppTypeRegistry>()?.read();
+            let registration = registry
+                .get_with_short_type_path(&self.name)
+                .or_else(|| registry.get_with_type_path(&self.name))?;
+            // Resources are stored as components of their own entity.
+            let component = registration.data::<ReflectComponent>()?.clone();
+            let id = world.components().get_id(registration.type_id())?;
+            self.component = Some((id, component));
+        }
+        let (id, component) = self.component.as_ref()?;
+        // Skip disabled entities like queries do.
+        let disabling: Vec<ComponentId> = world
+            .get_resource::<DefaultQueryFilters>()
+            .map(|filters| filters.disabling_ids().collect())
+            .unwrap_or_default();
+        let enabled = |archetype: &Archetype| !disabling.iter().any(|id| archetype.contains(*id));
+        let ticks = self.entity.and_then(|entity| {
+            let entity = world.get_entity(entity).ok()?;
+            enabled(entity.archetype())
+                .then(|| entity.get_change_ticks_by_id(*id))
+                .flatten()
+        });
+        let entity = match ticks {
+            Some(ticks) if !is_changed(ticks.changed, since) => return None,
+            Some(_) => self.entity?,
+            // The cached entity no longer has the component, read the first entity that has it.
+            None => {
+                let entity = world
+                    .archetypes()
+                    .iter()
+                    .filter(|archetype| archetype.contains(*id) && enabled(archetype))
+                    .find_map(|archetype| archetype.entities().first())?
+                    .id();
+                *self.entity.insert(entity)
+            }
+        };
+        let value = component.reflect(world.entity(entity))?;
+        match self.field.as_str() {
+            "" => f(value.as_partial_reflect()),
+            field => f(value.reflect_path(field).ok()?),
+        }
+    }
+}
+
+#[cfg(feature = "reflect")]
+fn reflect_to_string(value: &dyn PartialReflect) -> Option<String> {
+    macro_rules! downcast {
+        ($($ty: ty),*) => {
+            $(if let Some(value) = value.try_downcast_ref::<$ty>() {
+                return Some(value.to_string());
+            })*
+        };
+    }
+    downcast!(String, bool, f32, f64, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+    None
+}
+
+/// Update [`BoundValue`], [`BoundCondition`], [`BoundStyle`] and [`BoundList`] entities from [`Text3dBindings`].
+pub fn text_bindings_system(
+    world: &mut World,
+    values: &mut QueryState<(Ref<'static, BoundValue>, &'static mut FetchedText)>,
+    conditions: &mut QueryState<(Ref<'static, BoundCondition>, &'static mut FetchedCondition)>,
+    styles: &mut QueryState<(Ref<'static, BoundStyle>, &'static mut FetchedStyle)>,
+    lists: &mut QueryState<(Entity, Ref<'static, BoundList>)>,
+) {
+    // Inside an exclusive system, the last change tick is the last run of the system.
+    let since = Some((world.last_change_tick(), world.read_change_tick()));
+    // Newly bound entities read their sources even if unchanged.
+    let new_values: FxHashSet<String> = values
+        .iter(world)
+        .filter(|(bound, _)| bound.is_added())
+        .map(|(bound, _)| bound.0.clone())
+        .collect();
+    let new_conditions: FxHashSet<String> = conditions
+        .iter(world)
+        .filter(|(bound, _)| bound.is_added())
+        .map(|(bound, _)| bound.0.clone())
+        .collect();
+    let new_styles: FxHashSet<String> = styles
+        .iter(world)
+        .filter(|(bound, _)| bound.is_added())
+        .map(|(bound, _)| bound.0.clone())
+        .collect();
+    let new_lists: FxHashSet<String> = lists
+        .iter(world)
+        .filter(|(_, bound)| bound.is_added())
+        .map(|(_, bound)| bound.0.clone())
+        .collect();
+    world.resource_scope(|world, bindings: Mut<Text3dBindings>| {
+        // Read each source once.
+        let current_values: FxHashMap<&str, String> = bindings
+            .values
+            .iter()
+            .filter_map(|(name, f)| {
+                let since = since.filter(|_| !new_values.contains(name));
+                Some((name.as_str(), f(world, since)?))
+            })
+            .collect();
+        let current_conditions: FxHashMap<&str, bool> = bindings
+            .conditions
+            .iter()
+            .filter_map(|(name, f)| {
+                let since = since.filter(|_| !new_conditions.contains(name));
+                Some((name.as_str(), f(world, since)?))
+            })
+            .collect();
+        let current_styles: FxHashMap<&str, SegmentStyle> = bindings
+            .styles
+            .iter()
+            .filter_map(|(name, f)| {
+                let since = since.filter(|_| !new_styles.contains(name));
+                Some((name.as_str(), f(world, since)?))
+            })
+            .collect();
+        let current_lists: FxHashMap<&str, Vec<Vec<(String, String)>>> = bindings
+            .lists
+            .iter()
+            .filter_map(|(name, f)| {
+                let since = since.filter(|_| !new_lists.contains(name));
+                Some((name.as_str(), f(world, since)?))
+            })
+            .collect();
+        if !current_values.is_empty() {
+            for (bound, text) in values.iter_mut(world) {
+                if let Some(value) = current_values.get(bound.0.as_str()) {
+                    FetchedText::set_if_changed(text, value);
+                }
+            }
+        }
+        for (bound, condition) in conditions.iter_mut(world) {
+            if let Some(value) = current_conditions.get(bound.0.as_str()) {
+                FetchedCondition::set_if_changed(condition, *value);
+            }
+        }
+        for (bound, style) in styles.iter_mut(world) {
+            if let Some(value) = current_styles.get(bound.0.as_str()) {
+                FetchedStyle::set_if_changed(style, value.clone());
+            }
+        }
+        if current_lists.is_empty() {
+            return;
+        }
+        let bound_lists: Vec<(Entity, &Vec<Vec<(String, String)>>)> = lists
+            .iter(world)
+            .filter_map(|(entity, bound)| Some((entity, current_lists.get(bound.0.as_str())?)))
+            .collect();
+        for (entity, items) in bound_lists {
+            update_list(world, entity, items);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[cfg(feature = "reflect")]
+    use bevy::ecs::{change_detection::DetectChangesMut, reflect::ReflectComponent};
+    use bevy::ecs::{component::Component, resource::Resource, world::World};
+
+    use super::text_bindings_system;
+    use crate::{
+        BoundCondition, BoundStyle, BoundValue, FetchedCondition, FetchedStyle, FetchedText,
+        SegmentStyle, Text3dBindings,
+    };
+
+    #[derive(Resource)]
+    struct Score(u32);
+
+    #[derive(Component)]
+    #[cfg_attr(feature = "reflect", derive(bevy::reflect::Reflect))]
+    #[cfg_attr(feature = "reflect", reflect(Component))]
+    struct Player {
+        health: f32,
+        alive: bool,
+    }
+
+    /// A world with `bindings` and a system that runs [`text_bindings_system`] once.
+    fn world(bindings: Text3dBindings) -> (World, impl FnMut(&mut World)) {
+        let mut world = World::new();
+        world.insert_resource(bindings);
+        let system = world.register_system(text_bindings_system);
+        (world, move |world: &mut World| {
+            world.run_system(system).unwrap();
+        })
+    }
+
+    fn text(world: &World, entity: bevy::ecs::entity::Entity) -> &str {
+        &world.get::<FetchedText>(entity).unwrap().0
+    }
+
+    #[test]
+    fn resource_binding() {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let counter = reads.clone();
+        let (mut world, mut run) = world(Text3dBindings::new().with_resource(
+            "score",
+            move |score: &Score| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                score.0
+            },
+        ));
+        world.insert_resource(Score(1));
+        let value = world.spawn(BoundValue("score".into())).id();
+        run(&mut world);
+        assert_eq!(text(&world, value), "1");
+        // Unchanged resources are not read again.
+        run(&mut world);
+        run(&mut world);
+        assert_eq!(reads.load(Ordering::Relaxed), 1);
+        world.resource_mut::<Score>().0 = 2;
+        run(&mut world);
+        assert_eq!(text(&world, value), "2");
+        assert_eq!(reads.load(Ordering::Relaxed), 2);
+        // A newly bound value reads the unchanged resource.
+        let late = world.spawn(BoundValue("score".into())).id();
+        run(&mut world);
+        assert_eq!(text(&world, late), "2");
+    }
+
+    #[test]
+    fn component_binding() {
+        let (mut world, mut run) = world(
+            Text3dBindings::new()
+                .with_component("hp", |player: &Player| player.health)
+                .with_component("alive", |player: &Player| player.alive),
+        );
+        let value = world.spawn(BoundValue("hp".into())).id();
+        let alive = world.spawn(BoundValue("alive".into())).id();
+        run(&mut world);
+        assert_eq!(text(&world, value), "");
+        let player = world
+            .spawn(Player {
+                health: 5.0,
+                alive: true,
+            })
+            .id();
+        run(&mut world);
+        assert_eq!(text(&world, value), "5");
+        assert_eq!(text(&world, alive), "true");
+        world.get_mut::<Player>(player).unwrap().health = 3.0;
+        run(&mut world);
+        assert_eq!(text(&world, value), "3");
+        // The binding moves to another entity if the first one is removed,
+        // even if that component has not changed.
+        let other = world
+            .spawn(Player {
+                health: 7.0,
+                alive: true,
+            })
+            .id();
+        run(&mut world);
+        assert_eq!(text(&world, value), "3");
+        world.despawn(player);
+        run(&mut world);
+        assert_eq!(text(&world, value), "7");
+        world.despawn(other);
+    }
+
+    #[test]
+    fn condition_and_style_bindings() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let (mut world, mut run) = world(
+            Text3dBindings::new()
+                .with_condition("high", |world| Some(world.get_resource::<Score>()?.0 > 5))
+                .with_style("score", move |world| {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    let score = world.get_resource::<Score>()?;
+                    Some(SegmentStyle {
+                        magic_number: Some(score.0 as f32),
+                        ..Default::default()
+                    })
+                }),
+        );
+        world.insert_resource(Score(1));
+        let condition = world.spawn(BoundCondition("high".into())).id();
+        let style = world.spawn(BoundStyle("score".into())).id();
+        run(&mut world);
+        assert!(!world.get::<FetchedCondition>(condition).unwrap().0);
+        assert_eq!(
+            world.get::<FetchedStyle>(style).unwrap().0.magic_number,
+            Some(1.0)
+        );
+        // Functions of the world are called every frame, but unchanged results do not
+        // trigger change detection.
+        let tick = world.change_tick();
+        run(&mut world);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        let changed = |world: &World| {
+            let entity = world.entity(style);
+            entity
+                .get_change_ticks::<FetchedStyle>()
+                .unwrap()
+                .is_changed(tick, world.read_change_tick())
+        };
+        assert!(!changed(&world));
+        world.resource_mut::<Score>().0 = 9;
+        run(&mut world);
+        assert!(changed(&world));
+        assert!(world.get::<FetchedCondition>(condition).unwrap().0);
+        assert_eq!(
+            world.get::<FetchedStyle>(style).unwrap().0.magic_number,
+            Some(9.0)
+        );
+    }
+
+    #[cfg(feature = "reflect")]
+    #[test]
+    fn reflect_path_binding() {
+        use bevy::ecs::{entity_disabling::Disabled, reflect::AppTypeRegistry};
+
+        let (mut world, mut run) = world(
+            Text3dBindings::new()
+                .with_path("hp", "Player.health")
+                .with_path("alive", "Player.alive"),
+        );
+        world.init_resource::<AppTypeRegistry>();
+        world
+            .resource::<AppTypeRegistry>()
+            .write()
+            .register::<Player>();
+        // Disabled entities are skipped like in queries.
+        world.spawn((
+            Player {
+                health: 1.0,
+                alive: false,
+            },
+            Disabled,
+        ));
+        let player = world
+            .spawn(Player {
+                health: 5.0,
+                alive: true,
+            })
+            .id();
+        let value = world.spawn(BoundValue("hp".into())).id();
+        let condition = world.spawn(BoundCondition("alive".into())).id();
+        run(&mut world);
+        assert_eq!(text(&world, value), "5");
+        assert!(world.get::<FetchedCondition>(condition).unwrap().0);
+        // Values and conditions are only read if the component changed.
+        world
+            .get_mut::<Player>(player)
+            .unwrap()
+            .bypass_change_detection()
+            .alive = false;
+        run(&mut world);
+        assert!(world.get::<FetchedCondition>(condition).unwrap().0);
+        world.get_mut::<Player>(player).unwrap().health = 2.0;
+        run(&mut world);
+        assert_eq!(text(&world, value), "2");
+        assert!(!world.get::<FetchedCondition>(condition).unwrap().0);
+        // A newly bound condition reads the unchanged component.
+        let late = world.spawn(BoundCondition("alive".into())).id();
+        world.get_mut::<FetchedCondition>(late).unwrap().0 = true;
+        run(&mut world);
+        assert!(!world.get::<FetchedCondition>(late).unwrap().0);
+    }
+}
@@ -0,0 +1,307 @@
+use std::fmt::Display;
+
+use bevy::ecs::entity::Entity;
+#[cfg(feature = "reflect")]
+use bevy::prelude::Reflect;
+
+use crate::{
+    parse_util::{ConditionOutput, ParseConditionFn, ParseValueFn},
+    ParseError, Text3dSegment,
+};
+
+/// Comparison operator of [`ConditionTerm::Compare`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub enum CompareOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl CompareOp {
+    /// Operators ordered so that `<=` is matched before `<`.
+    const ALL: [(&'static str, CompareOp); 7] = [
+        ("<=", CompareOp::Le),
+        (">=", CompareOp::Ge),
+        ("==", CompareOp::Eq),
+        ("!=", CompareOp::Ne),
+        ("<", CompareOp::Lt),
+        (">", CompareOp::Gt),
+        ("=", CompareOp::Eq),
+    ];
+
+    pub fn compare(&self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            CompareOp::Lt => lhs < rhs,
+            CompareOp::Le => lhs <= rhs,
+            CompareOp::Gt => lhs > rhs,
+            CompareOp::Ge => lhs >= rhs,
+            CompareOp::Eq => lhs == rhs,
+            CompareOp::Ne => lhs != rhs,
+        }
+    }
+}
+
+impl Display for CompareOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+        })
+    }
+}
+
+/// A term of a [`ConditionExpr`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub enum ConditionTerm {
+    /// [`FetchedCondition`](crate::FetchedCondition) on an entity, flipped if `negate`.
+    Condition { condition: Entity, negate: bool },
+    /// [`FetchedText`](crate::FetchedText) on an entity as a number, compared with `rhs`, flipped if `negate`.
+    ///
+    /// Only `!=` is true if the text is not a number, so `!hp < 10` is true but `hp >= 10` is not.
+    Compare {
+        value: Entity,
+        op: CompareOp,
+        rhs: f64,
+        negate: bool,
+    },
+}
+
+/// A condition like `a && hp < 10 || !b`, `&&` binds tighter than `||`.
+///
+/// Stored as groups of terms, true if all terms of any group are true.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct ConditionExpr(pub Vec<Vec<ConditionTerm>>);
+
+impl ConditionExpr {
+    /// Evaluate with values of [`FetchedCondition`](crate::FetchedCondition) and [`FetchedText`](crate::FetchedText),
+    /// returns `None` if an entity is missing.
+    pub fn evaluate(
+        &self,
+        mut condition: impl FnMut(Entity) -> Option<bool>,
+        mut value: impl FnMut(Entity) -> Option<f64>,
+    ) -> Option<bool> {
+        let mut result = false;
+        for group in &self.0 {
+            let mut all = true;
+            for term in group {
+                all &= match term {
+                    ConditionTerm::Condition {
+                        condition: e,
+                        negate,
+                    } => condition(*e)? != *negate,
+                    ConditionTerm::Compare {
+                        value: e,
+                        op,
+                        rhs,
+                        negate,
+                    } => op.compare(value(*e)?, *rhs) != *negate,
+                };
+            }
+            result |= all;
+        }
+        Some(result)
+    }
+
+    /// Entities referenced by this condition.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().flatten().map(|term| match term {
+            ConditionTerm::Condition { condition, .. } => *condition,
+            ConditionTerm::Compare { value, .. } => *value,
+        })
+    }
+
+    pub(crate) fn entities_mut(&mut self) -> impl Iterator<Item = &mut Entity> + '_ {
+        self.0.iter_mut().flatten().map(|term| match term {
+            ConditionTerm::Condition { condition, .. } => condition,
+            ConditionTerm::Compare { value, .. } => value,
+        })
+    }
+
+    /// Convert to a segment that skips the next `offset` segments if the condition equals `skip_if`.
+    ///
+    /// A single [`ConditionTerm::Condition`] is converted to a [`Text3dSegment::SkipIf`].
+    pub(crate) fn into_segment(self, skip_if: bool, offset: usize) -> Text3dSegment {
+        if let [group] = self.0.as_slice() {
+            if let [ConditionTerm::Condition { condition, negate }] = group.as_slice() {
+                return Text3dSegment::SkipIf {
+                    condition: *condition,
+                    skip_if: skip_if != *negate,
+                    offset,
+                };
+            }
+        }
+        Text3dSegment::SkipIfExpr {
+            condition: self,
+            skip_if,
+            offset,
+        }
+    }
+}
+
+/// Parse a condition expression, constant terms are evaluated immediately.
+///
+/// Names are passed to `parse_condition`, the left hand side of comparisons to `parse_value`.
+/// Returns `Err` with the value if the whole expression is constant.
+pub(crate) fn parse_condition_expr(
+    s: &str,
+    index: usize,
+    parse_condition: &mut impl ParseConditionFn,
+    parse_value: &mut impl ParseValueFn,
+) -> Result<Result<ConditionExpr, bool>, ParseError> {
+    let mut groups = Vec::new();
+    let mut always_true = false;
+    for group in s.split("||") {
+        let mut terms = Vec::new();
+        // Terms are still parsed to report errors.
+        let mut always_false = false;
+        for term in group.split("&&") {
+            let mut term = term.trim();
+            let mut negate = false;
+            while let Some(rest) = term.strip_prefix('!') {
+                negate = !negate;
+                term = rest.trim_start();
+            }
+            if term.is_empty() {
+                return Err(ParseError::BadCommand(format!("?{}", s.trim())));
+            }
+            let term = match parse_compare(term) {
+                Some((lhs, op, rhs)) => {
+                    let rhs = rhs
+                        .parse::<f64>()
+                        .map_err(|_| ParseError::BadCommand(format!("?{term}")))?;
+                    match parse_value.call(index, lhs)?.0 {
+                        Text3dSegment::String(value) => {
+                            Err(op.compare(value.trim().parse().unwrap_or(f64::NAN), rhs) != negate)
+                        }
+                        Text3dSegment::Extract(value) => Ok(ConditionTerm::Compare {
+                            value,
+                            op,
+                            rhs,
+                            negate,
+                        }),
+                        _ => return Err(ParseError::NotSupported("comparison with this value")),
+                    }
+                }
+                None => match parse_condition.call(term)? {
+                    ConditionOutput::Constant(b) => Err(b != negate),
+                    ConditionOutput::Dynamic(condition) => {
+                        Ok(ConditionTerm::Condition { condition, negate })
+                    }
+                },
+            };
+            match term {
+                Ok(term) => terms.push(term),
+                Err(true) => (),
+                Err(false) => always_false = true,
+            }
+        }
+        match (always_false, terms.is_empty()) {
+            (true, _) => (),
+            (false, true) => always_true = true,
+            (false, false) => groups.push(terms),
+        }
+    }
+    if always_true || groups.is_empty() {
+        return Ok(Err(always_true));
+    }
+    Ok(Ok(ConditionExpr(groups)))
+}
+
+/// Split a comparison like `hp < 10`.
+fn parse_compare(term: &str) -> Option<(&str, CompareOp, &str)> {
+    let start = term.find(['<', '>', '=', '!'])?;
+    let rest = &term[start..];
+    let (symbol, op) = CompareOp::ALL
+        .iter()
+        .find(|(symbol, _)| rest.starts_with(symbol))?;
+    Some((term[..start].trim(), *op, rest[symbol.len()..].trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::entity::Entity;
+
+    use super::{parse_condition_expr, ConditionExpr};
+    use crate::{parse_util::ConditionOutput, ParseError, SegmentStyle, Text3dSegment};
+
+    fn entity(index: u32) -> Entity {
+        Entity::from_raw_u32(index).unwrap()
+    }
+
+    /// `a` and `b` are dynamic conditions, `hp` is a dynamic value,
+    /// `yes`, `no`, `five` and `name` are constants.
+    fn parse(s: &str) -> Result<Result<ConditionExpr, bool>, ParseError> {
+        parse_condition_expr(
+            s,
+            0,
+            &mut |s: &str| match s {
+                "a" => Ok(ConditionOutput::Dynamic(entity(0))),
+                "b" => Ok(ConditionOutput::Dynamic(entity(1))),
+                "yes" => Ok(ConditionOutput::Constant(true)),
+                "no" => Ok(ConditionOutput::Constant(false)),
+                _ => Err(ParseError::Custom(format!("Unknown condition {s}."))),
+            },
+            &mut |s: &str| match s {
+                "hp" => Ok((Text3dSegment::Extract(entity(2)), SegmentStyle::default())),
+                "five" => Ok((Text3dSegment::String("5".into()), SegmentStyle::default())),
+                "name" => Ok((Text3dSegment::String("Bob".into()), SegmentStyle::default())),
+                _ => Err(ParseError::Custom(format!("Unknown value {s}."))),
+            },
+        )
+    }
+
+    fn evaluate(s: &str, a: bool, b: bool, hp: f64) -> Option<bool> {
+        parse(s).unwrap().unwrap().evaluate(
+            |e| [a, b].get(e.index_u32() as usize).copied(),
+            |e| (e == entity(2)).then_some(hp),
+        )
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(parse("a || b && !a").unwrap().unwrap().0.len(), 2);
+        assert_eq!(evaluate("a || b && !a", false, true, 0.0), Some(true));
+        assert_eq!(evaluate("a || b && !a", true, false, 0.0), Some(true));
+        assert_eq!(evaluate("a || b && !a", false, false, 0.0), Some(false));
+        assert_eq!(evaluate("a && b || hp > 3", true, false, 5.0), Some(true));
+        assert_eq!(evaluate("a && b || hp > 3", true, false, 1.0), Some(false));
+        assert_eq!(evaluate("!!a", true, false, 0.0), Some(true));
+    }
+
+    #[test]
+    fn constant_folding() {
+        assert_eq!(parse("yes && a").unwrap().unwrap().0.len(), 1);
+        assert_eq!(parse("no && a || b").unwrap().unwrap().0.len(), 1);
+        assert_eq!(evaluate("no && a || b", true, false, 0.0), Some(false));
+        assert_eq!(parse("no || !yes").unwrap(), Err(false));
+        assert_eq!(parse("a || yes").unwrap(), Err(true));
+        assert_eq!(parse("five > 3").unwrap(), Err(true));
+        assert_eq!(parse("!five > 3").unwrap(), Err(false));
+        assert_eq!(parse("five = 5 && !no").unwrap(), Err(true));
+        assert!(parse("a &&").is_err());
+        assert!(parse("five > x").is_err());
+        assert!(parse("unknown || yes").is_err());
+    }
+
+    #[test]
+    fn negated_comparison_of_text() {
+        assert_eq!(parse("name < 10").unwrap(), Err(false));
+        assert_eq!(parse("!name < 10").unwrap(), Err(true));
+        assert_eq!(parse("name >= 10").unwrap(), Err(false));
+        assert_eq!(parse("name != 10").unwrap(), Err(true));
+        assert_eq!(evaluate("!hp < 10", false, false, f64::NAN), Some(true));
+        assert_eq!(evaluate("hp >= 10", false, false, f64::NAN), Some(false));
+        assert_eq!(evaluate("!hp < 10", false, false, 5.0), Some(false));
+        assert_eq!(evaluate("!hp < 10", false, false, 12.0), Some(true));
+    }
+}
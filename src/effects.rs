@@ -0,0 +1,417 @@
+use bevy::{
+    app::{App, Plugin},
+    asset::{embedded_asset, embedded_path, Asset, AssetPath},
+    color::{ColorToComponents, LinearRgba, Srgba},
+    math::Vec4,
+    reflect::TypePath,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{AsBindGroup, AsBindGroupShaderType, ShaderType},
+        texture::GpuImage,
+    },
+    shader::{load_shader_library, ShaderRef},
+};
+
+#[cfg(feature = "pbr")]
+use bevy::pbr::{ExtendedMaterial, MaterialExtension, MaterialPlugin, StandardMaterial};
+#[cfg(feature = "sprite")]
+use bevy::{
+    asset::Handle,
+    image::Image,
+    mesh::{Mesh, MeshVertexBufferLayoutRef},
+    render::render_resource::{RenderPipelineDescriptor, SpecializedMeshPipelineError},
+    sprite_render::{AlphaMode2d, Material2d, Material2dKey, Material2dPlugin},
+};
+
+use crate::{GlyphMeta, MeshExport, Text3dStyle, TextAtlas};
+
+/// A built-in shader effect, used by [`TextEffectExtension`] and [`TextEffectMaterial2d`].
+///
+/// Requires [`TextEffect::EXPORT`] as the [`MeshExport`] of the text,
+/// see [`Text3dStyle::for_text_effects`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TextEffect {
+    /// No effect.
+    #[default]
+    None,
+    /// Reveals glyphs from left to right.
+    Typewriter {
+        /// Time in seconds when the reveal starts, usually `Time::elapsed_secs`.
+        since: f32,
+        /// Number of `em` revealed per second.
+        speed: f32,
+        /// Width of the fade in `em`, `1.0` is a per glyph fade.
+        softness: f32,
+    },
+    /// Cycles hue along the text.
+    Rainbow {
+        /// Number of cycles per second.
+        speed: f32,
+        /// Number of cycles per `em`.
+        frequency: f32,
+        /// Saturation of the colors in `0..=1`.
+        saturation: f32,
+    },
+    /// Moves glyphs up and down in a sine wave.
+    Wave {
+        /// Height of the wave in mesh units.
+        amplitude: f32,
+        /// Number of waves per `em`.
+        frequency: f32,
+        /// Number of cycles per second.
+        speed: f32,
+    },
+    /// Randomly offsets glyphs.
+    Shake {
+        /// Maximum offset in mesh units.
+        intensity: f32,
+        /// Number of times the offset changes per second.
+        speed: f32,
+    },
+    /// Dissolves text in a noise pattern.
+    Dissolve {
+        /// Portion of the text dissolved in `0..=1`.
+        progress: f32,
+        /// Width of the colored edge in `0..=1`.
+        edge: f32,
+        /// Color of the dissolving edge.
+        edge_color: Srgba,
+    },
+    /// Lights up the edges of glyphs.
+    OutlineGlow {
+        /// Color of the glow.
+        color: Srgba,
+        /// Strength of the glow, usually `1.0`.
+        intensity: f32,
+        /// Number of pulses per second, `0.0` disables pulsing.
+        pulse: f32,
+    },
+}
+
+/// Uniform of [`TextEffect`], must match `text_effect.wgsl`.
+#[derive(Debug, Clone, Copy, Default, ShaderType)]
+pub struct TextEffectUniform {
+    pub color: Vec4,
+    pub params: Vec4,
+    pub kind: u32,
+}
+
+impl TextEffect {
+    /// [`MeshExport`] required by built-in text effects.
+    pub const EXPORT: MeshExport =
+        MeshExport::Uv1(GlyphMeta::PerGlyphAdvance, GlyphMeta::RandomPerGlyph);
+
+    pub fn as_uniform(&self) -> TextEffectUniform {
+        let (kind, params, color) = match *self {
+            TextEffect::None => (0, Vec4::ZERO, Srgba::NONE),
+            TextEffect::Typewriter {
+                since,
+                speed,
+                softness,
+            } => (1, Vec4::new(since, speed, softness, 0.), Srgba::NONE),
+            TextEffect::Rainbow {
+                speed,
+                frequency,
+                saturation,
+            } => (2, Vec4::new(speed, frequency, saturation, 0.), Srgba::NONE),
+            TextEffect::Wave {
+                amplitude,
+                frequency,
+                speed,
+            } => (3, Vec4::new(amplitude, frequency, speed, 0.), Srgba::NONE),
+            TextEffect::Shake { intensity, speed } => {
+                (4, Vec4::new(intensity, speed, 0., 0.), Srgba::NONE)
+            }
+            TextEffect::Dissolve {
+                progress,
+                edge,
+                edge_color,
+            } => (5, Vec4::new(progress, edge, 0., 0.), edge_color),
+            TextEffect::OutlineGlow {
+                color,
+                intensity,
+                pulse,
+            } => (6, Vec4::new(intensity, pulse, 0., 0.), color),
+        };
+        TextEffectUniform {
+            color: LinearRgba::from(color).to_vec4(),
+            params,
+            kind,
+        }
+    }
+}
+
+impl Text3dStyle {
+    /// Create a [`Text3dStyle`] that exports data required by built-in text effects.
+    pub fn for_text_effects() -> Self {
+        Text3dStyle {
+            export: TextEffect::EXPORT,
+            ..Default::default()
+        }
+    }
+}
+
+/// Adds shaders and materials of built-in text effects.
+///
+/// * With feature `pbr`, registers [`TextEffectMaterial3d`] for `Mesh3d`.
+/// * With feature `sprite`, registers [`TextEffectMaterial2d`] for `Mesh2d`.
+#[derive(Debug, Default)]
+pub struct TextEffectPlugin;
+
+impl Plugin for TextEffectPlugin {
+    fn build(&self, app: &mut App) {
+        load_shader_library!(app, "shaders/text_effect.wgsl");
+        #[cfg(feature = "pbr")]
+        {
+            embedded_asset!(app, "shaders/text_effect_3d.wgsl");
+            app.add_plugins(MaterialPlugin::<TextEffectMaterial3d>::default());
+        }
+        #[cfg(feature = "sprite")]
+        {
+            embedded_asset!(app, "shaders/text_effect_2d.wgsl");
+            app.add_plugins(Material2dPlugin::<TextEffectMaterial2d>::default());
+        }
+    }
+}
+
+/// A [`MaterialExtension`] of [`StandardMaterial`] that renders a [`TextEffect`].
+///
+/// The effect is applied to the base color before lighting,
+/// which follows [`StandardMaterial::unlit`] like the unextended material.
+#[cfg(feature = "pbr")]
+#[derive(Debug, Clone, Default, Asset, TypePath, AsBindGroup)]
+#[uniform(100, TextEffectUniform)]
+pub struct TextEffectExtension {
+    pub effect: TextEffect,
+}
+
+#[cfg(feature = "pbr")]
+impl AsBindGroupShaderType<TextEffectUniform> for TextEffectExtension {
+    fn as_bind_group_shader_type(&self, _: &RenderAssets<GpuImage>) -> TextEffectUniform {
+        self.effect.as_uniform()
+    }
+}
+
+#[cfg(feature = "pbr")]
+impl MaterialExtension for TextEffectExtension {
+    fn vertex_shader() -> ShaderRef {
+        ShaderRef::Path(
+            AssetPath::from_path_buf(embedded_path!("shaders/text_effect_3d.wgsl"))
+                .with_source("embedded"),
+        )
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        ShaderRef::Path(
+            AssetPath::from_path_buf(embedded_path!("shaders/text_effect_3d.wgsl"))
+                .with_source("embedded"),
+        )
+    }
+}
+
+/// A 3d text material with a built-in [`TextEffect`].
+#[cfg(feature = "pbr")]
+pub type TextEffectMaterial3d = ExtendedMaterial<StandardMaterial, TextEffectExtension>;
+
+#[cfg(feature = "pbr")]
+impl TextEffectExtension {
+    /// Create an unlit, alpha blended material using [`TextAtlas::DEFAULT_IMAGE`].
+    pub fn material(effect: TextEffect) -> TextEffectMaterial3d {
+        ExtendedMaterial {
+            base: StandardMaterial {
+                base_color_texture: Some(TextAtlas::DEFAULT_IMAGE.clone()),
+                alpha_mode: bevy::prelude::AlphaMode::Blend,
+                unlit: true,
+                ..Default::default()
+            },
+            extension: TextEffectExtension { effect },
+        }
+    }
+}
+
+/// A 2d text material with a built-in [`TextEffect`].
+///
+/// Unlike `ColorMaterial` this requires the mesh to have `uv1`,
+/// see [`Text3dStyle::for_text_effects`].
+#[cfg(feature = "sprite")]
+#[derive(Debug, Clone, Asset, TypePath, AsBindGroup)]
+#[uniform(0, TextEffectUniform)]
+pub struct TextEffectMaterial2d {
+    pub effect: TextEffect,
+    pub alpha_mode: AlphaMode2d,
+    /// The text atlas image, by default [`TextAtlas::DEFAULT_IMAGE`].
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Handle<Image>,
+}
+
+#[cfg(feature = "sprite")]
+impl TextEffectMaterial2d {
+    pub fn new(effect: TextEffect) -> Self {
+        TextEffectMaterial2d {
+            effect,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "sprite")]
+impl Default for TextEffectMaterial2d {
+    fn default() -> Self {
+        Self {
+            effect: TextEffect::None,
+            alpha_mode: AlphaMode2d::Blend,
+            texture: TextAtlas::DEFAULT_IMAGE.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "sprite")]
+impl AsBindGroupShaderType<TextEffectUniform> for TextEffectMaterial2d {
+    fn as_bind_group_shader_type(&self, _: &RenderAssets<GpuImage>) -> TextEffectUniform {
+        self.effect.as_uniform()
+    }
+}
+
+#[cfg(feature = "sprite")]
+impl Material2d for TextEffectMaterial2d {
+    fn vertex_shader() -> ShaderRef {
+        ShaderRef::Path(
+            AssetPath::from_path_buf(embedded_path!("shaders/text_effect_2d.wgsl"))
+                .with_source("embedded"),
+        )
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        ShaderRef::Path(
+            AssetPath::from_path_buf(embedded_path!("shaders/text_effect_2d.wgsl"))
+                .with_source("embedded"),
+        )
+    }
+
+    fn alpha_mode(&self) -> AlphaMode2d {
+        self.alpha_mode
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.0.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            Mesh::ATTRIBUTE_UV_0.at_shader_location(2),
+            Mesh::ATTRIBUTE_COLOR.at_shader_location(4),
+            Mesh::ATTRIBUTE_UV_1.at_shader_location(5),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{color::Srgba, math::Vec4};
+
+    use super::TextEffect;
+
+    /// Returns the value of `const EFFECT_*: u32` in `text_effect.wgsl`.
+    fn shader_kind(name: &str) -> u32 {
+        let source = include_str!("shaders/text_effect.wgsl");
+        let prefix = format!("const EFFECT_{name}: u32 = ");
+        let line = source
+            .lines()
+            .find_map(|line| line.strip_prefix(&prefix))
+            .unwrap();
+        line.trim_end_matches("u;").parse().unwrap()
+    }
+
+    #[test]
+    fn kinds_match_shader() {
+        let edge_color = Srgba::RED;
+        let cases = [
+            (
+                "TYPEWRITER",
+                TextEffect::Typewriter {
+                    since: 1.0,
+                    speed: 2.0,
+                    softness: 3.0,
+                },
+            ),
+            (
+                "RAINBOW",
+                TextEffect::Rainbow {
+                    speed: 1.0,
+                    frequency: 2.0,
+                    saturation: 3.0,
+                },
+            ),
+            (
+                "WAVE",
+                TextEffect::Wave {
+                    amplitude: 1.0,
+                    frequency: 2.0,
+                    speed: 3.0,
+                },
+            ),
+            (
+                "SHAKE",
+                TextEffect::Shake {
+                    intensity: 1.0,
+                    speed: 2.0,
+                },
+            ),
+            (
+                "DISSOLVE",
+                TextEffect::Dissolve {
+                    progress: 1.0,
+                    edge: 2.0,
+                    edge_color,
+                },
+            ),
+            (
+                "OUTLINE_GLOW",
+                TextEffect::OutlineGlow {
+                    color: edge_color,
+                    intensity: 1.0,
+                    pulse: 2.0,
+                },
+            ),
+        ];
+        assert_eq!(TextEffect::None.as_uniform().kind, 0);
+        for (name, effect) in cases {
+            assert_eq!(effect.as_uniform().kind, shader_kind(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn params_are_in_declaration_order() {
+        let uniform = TextEffect::Typewriter {
+            since: 1.0,
+            speed: 2.0,
+            softness: 3.0,
+        }
+        .as_uniform();
+        assert_eq!(uniform.params, Vec4::new(1.0, 2.0, 3.0, 0.0));
+        assert_eq!(uniform.color, Vec4::ZERO);
+
+        let uniform = TextEffect::Shake {
+            intensity: 0.5,
+            speed: 10.0,
+        }
+        .as_uniform();
+        assert_eq!(uniform.params, Vec4::new(0.5, 10.0, 0.0, 0.0));
+
+        let uniform = TextEffect::OutlineGlow {
+            color: Srgba::new(0.5, 1.0, 0.0, 0.25),
+            intensity: 2.0,
+            pulse: 0.0,
+        }
+        .as_uniform();
+        assert_eq!(uniform.params, Vec4::new(2.0, 0.0, 0.0, 0.0));
+        // Colors are linear in the uniform.
+        assert!((uniform.color.x - 0.214).abs() < 1e-3);
+        assert_eq!(uniform.color.y, 1.0);
+        assert_eq!(uniform.color.w, 0.25);
+    }
+}
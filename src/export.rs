@@ -1,8 +1,25 @@
-use bevy::mesh::{Mesh, MeshVertexAttribute, VertexAttributeValues};
+use std::{
+    fmt::Debug,
+    sync::{LazyLock, Mutex, PoisonError},
+};
+
+use bevy::mesh::{
+    Mesh, MeshVertexAttribute, MeshVertexAttributeId, VertexAttributeValues, VertexFormat,
+};
+#[cfg(feature = "reflect")]
+use bevy::reflect::Reflect;
+#[cfg(all(feature = "reflect", feature = "serde"))]
+use bevy::reflect::{ReflectDeserialize, ReflectSerialize};
+use rustc_hash::FxHashSet;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::SpanEffect;
 
 /// Determines what kind of data each field in [`MeshExport`] carry.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[non_exhaustive]
 pub enum GlyphMeta {
     /// Left to right count of the glyph, `0`, `1`, etc.
@@ -28,10 +45,59 @@ pub enum GlyphMeta {
     Category,
     /// The [`SegmentStyle::magic_number`](crate::SegmentStyle::magic_number) field
     MagicNumber,
+    /// The x component of [`SegmentStyle::params`](crate::SegmentStyle::params).
+    ParamX,
+    /// The y component of [`SegmentStyle::params`](crate::SegmentStyle::params).
+    ParamY,
+    /// The z component of [`SegmentStyle::params`](crate::SegmentStyle::params).
+    ParamZ,
+    /// The w component of [`SegmentStyle::params`](crate::SegmentStyle::params).
+    ParamW,
+    /// A value in [`SegmentStyle::named_params`](crate::SegmentStyle::named_params), `0` if not found.
+    NamedParam(ParamName),
+    /// Index of the line after wrapping, `0`, `1`, etc.
+    LineIndex,
+    /// Index of the word starting from `0`, words are separated by whitespaces or line breaks.
+    ///
+    /// Whitespaces share the index of the word before them, a word broken by wrapping keeps its index.
+    WordIndex,
+    /// Index of the segment in [`Text3d::segments`](crate::Text3d::segments).
+    SegmentIndex,
+    /// Byte offset of the glyph in the source string of [`Text3d::parse`](crate::Text3d::parse),
+    /// see [`Text3d::source_offsets`](crate::Text3d::source_offsets).
+    ///
+    /// Glyphs of dynamic values, list items and images return the offset of their placeholder.
+    /// Offsets inside a segment count its parsed text, so escapes and collapsed whitespaces
+    /// before the glyph in the same segment shift the offset.
+    ///
+    /// If the text is not parsed, returns the offset in the concatenation of rendered segments.
+    CharIndex,
+    /// Number of glyphs in the glyph's line.
+    GlyphCountInLine,
+    /// Number of lines after wrapping.
+    LineCount,
+    /// [`GlyphMeta::Index`] divided by the number of glyphs, in `0..1`.
+    IndexFraction,
+    /// x position of the center of the glyph's quad in local mesh units.
+    GlyphCenterX,
+    /// y position of the center of the glyph's quad in local mesh units.
+    GlyphCenterY,
+    /// Width of the glyph's quad in local mesh units.
+    GlyphWidth,
+    /// Height of the glyph's quad in local mesh units.
+    GlyphHeight,
+    /// y position of the glyph's baseline in local mesh units.
+    Baseline,
+    /// Bitset of [`SpanEffect::bit`] in [`SegmentStyle::effects`](crate::SegmentStyle::effects).
+    EffectMask,
+    /// Strength of a [`SpanEffect`] in [`SegmentStyle::effects`](crate::SegmentStyle::effects), `0` if not set.
+    EffectStrength(SpanEffect),
 }
 
 /// Determines what data to export as a part of the mesh.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum MeshExport {
     /// Export nothing.
     #[default]
@@ -59,8 +125,10 @@ impl MeshExport {
         match self {
             MeshExport::None => Vec::new(),
             MeshExport::Uv1(x, y) => vec![MeshExportCache {
+                attribute: Mesh::ATTRIBUTE_UV_1,
                 entry: MeshExportEntry {
-                    id: Mesh::ATTRIBUTE_UV_1,
+                    name: AttributeName(Mesh::ATTRIBUTE_UV_1.name),
+                    id: Mesh::ATTRIBUTE_UV_1.id,
                     len: 2,
                     meta: [*x, *y, *x, *y],
                 },
@@ -68,23 +136,36 @@ impl MeshExport {
             }],
             MeshExport::Custom(items) => items
                 .iter()
-                .map(|x| MeshExportCache {
-                    entry: *x,
-                    data: match x.len {
-                        1 => MeshExportCacheData::F1(reuse!(x.id, Float32)),
-                        2 => MeshExportCacheData::F2(reuse!(x.id, Float32x2)),
-                        3 => MeshExportCacheData::F3(reuse!(x.id, Float32x3)),
-                        _ => MeshExportCacheData::F4(reuse!(x.id, Float32x4)),
-                    },
+                .map(|x| {
+                    let attribute = x.attribute();
+                    MeshExportCache {
+                        attribute,
+                        entry: *x,
+                        data: match x.len {
+                            1 => MeshExportCacheData::F1(reuse!(attribute, Float32)),
+                            2 => MeshExportCacheData::F2(reuse!(attribute, Float32x2)),
+                            3 => MeshExportCacheData::F3(reuse!(attribute, Float32x3)),
+                            _ => MeshExportCacheData::F4(reuse!(attribute, Float32x4)),
+                        },
+                    }
                 })
                 .collect(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A custom vertex attribute in [`MeshExport::Custom`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(opaque, Debug, PartialEq, Clone))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    all(feature = "reflect", feature = "serde"),
+    reflect(Serialize, Deserialize)
+)]
 pub struct MeshExportEntry {
-    pub(crate) id: MeshVertexAttribute,
+    pub(crate) name: AttributeName,
+    pub(crate) id: MeshVertexAttributeId,
     pub(crate) len: usize,
     pub(crate) meta: [GlyphMeta; 4],
 }
@@ -96,27 +177,142 @@ impl MeshExportEntry {
     ///
     /// If attribute is not a float vector or if number of elements mismatches.
     pub fn new(entry: MeshVertexAttribute, meta: &[GlyphMeta]) -> Self {
-        use bevy::mesh::VertexFormat::*;
         let len = meta.len().min(4);
         match entry.format {
-            Float32 => assert_eq!(len, 1, "Expected 1 item."),
-            Float32x2 => assert_eq!(len, 2, "Expected 2 items."),
-            Float32x3 => assert_eq!(len, 3, "Expected 3 items."),
-            Float32x4 => assert_eq!(len, 4, "Expected 4 items."),
+            VertexFormat::Float32 => assert_eq!(len, 1, "Expected 1 item."),
+            VertexFormat::Float32x2 => assert_eq!(len, 2, "Expected 2 items."),
+            VertexFormat::Float32x3 => assert_eq!(len, 3, "Expected 3 items."),
+            VertexFormat::Float32x4 => assert_eq!(len, 4, "Expected 4 items."),
             _ => panic!("Expected float vector."),
         };
         MeshExportEntry {
-            id: entry,
-            len: meta.len().min(4),
+            name: AttributeName(entry.name),
+            id: entry.id,
+            len,
+            meta: std::array::from_fn(|i| meta.get(i).copied().unwrap_or_default()),
+        }
+    }
+
+    /// Create a new export attribute, equivalent to `MeshVertexAttribute::new(name, id, format)`
+    /// where `format` is a float vector with the same length as `meta`.
+    ///
+    /// # Panics
+    ///
+    /// If `meta` is empty or has more than 4 items.
+    pub fn from_name(name: &'static str, id: u64, meta: &[GlyphMeta]) -> Self {
+        assert!(
+            (1..=4).contains(&meta.len()),
+            "Expected 1 to 4 items, found {}.",
+            meta.len()
+        );
+        MeshExportEntry {
+            name: AttributeName(name),
+            id: MeshVertexAttribute::new(name, id, VertexFormat::Float32).id,
+            len: meta.len(),
             meta: std::array::from_fn(|i| meta.get(i).copied().unwrap_or_default()),
         }
     }
 
+    /// Returns the [`MeshVertexAttribute`] this entry writes to.
+    pub fn attribute(&self) -> MeshVertexAttribute {
+        MeshVertexAttribute {
+            name: self.name.0,
+            id: self.id,
+            format: match self.len {
+                1 => VertexFormat::Float32,
+                2 => VertexFormat::Float32x2,
+                3 => VertexFormat::Float32x3,
+                _ => VertexFormat::Float32x4,
+            },
+        }
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (usize, GlyphMeta)> + '_ {
         self.meta.iter().copied().enumerate().take(self.len)
     }
 }
+
+/// Returns a `'static` copy of `name`, each unique name is leaked once.
+fn intern(name: &str) -> &'static str {
+    static NAMES: LazyLock<Mutex<FxHashSet<&'static str>>> = LazyLock::new(Default::default);
+    let mut names = NAMES.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(interned) = names.get(name) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(name.into());
+    names.insert(interned);
+    interned
+}
+
+/// Name of a value in [`SegmentStyle::named_params`](crate::SegmentStyle::named_params),
+/// used by [`GlyphMeta::NamedParam`].
+///
+/// Names are interned so [`GlyphMeta`] stays `Copy`, each unique name is leaked once.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(opaque, Debug, PartialEq, Clone, Hash))]
+#[cfg_attr(
+    all(feature = "reflect", feature = "serde"),
+    reflect(Serialize, Deserialize)
+)]
+pub struct ParamName(&'static str);
+
+impl ParamName {
+    pub fn new(name: &str) -> Self {
+        ParamName(intern(name))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl Debug for ParamName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<&str> for ParamName {
+    fn from(name: &str) -> Self {
+        ParamName::new(name)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ParamName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ParamName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(ParamName::new(&String::deserialize(deserializer)?))
+    }
+}
+
+/// Name of a [`MeshVertexAttribute`], deserialized names are leaked once per unique name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct AttributeName(pub(crate) &'static str);
+
+#[cfg(feature = "serde")]
+impl Serialize for AttributeName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AttributeName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(AttributeName(intern(&String::deserialize(deserializer)?)))
+    }
+}
+
 pub struct MeshExportCache {
+    pub(crate) attribute: MeshVertexAttribute,
     pub(crate) entry: MeshExportEntry,
     pub(crate) data: MeshExportCacheData,
 }
@@ -249,3 +445,30 @@ impl TextMeshFaceCategory {
         (*self) as u8 as f32
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{GlyphMeta, MeshExportEntry, ParamName};
+
+    #[test]
+    fn param_names_are_interned() {
+        let glow = String::from("glow");
+        assert_eq!(ParamName::new("glow"), ParamName::new(&glow));
+        assert_eq!(
+            ParamName::new("glow").as_str().as_ptr(),
+            ParamName::new(&glow).as_str().as_ptr()
+        );
+        assert_ne!(ParamName::new("glow"), ParamName::new("blur"));
+    }
+
+    #[test]
+    fn exports_are_copy() {
+        fn copy<T: Copy>(value: &T) -> T {
+            *value
+        }
+        let meta = GlyphMeta::NamedParam("glow".into());
+        let entry = MeshExportEntry::from_name("Vertex_Glow", 9000, &[meta, GlyphMeta::Index]);
+        assert_eq!(copy(&entry), entry);
+        assert_eq!(copy(&meta), meta);
+    }
+}
@@ -1,10 +1,13 @@
 use std::str::FromStr;
 
-use bevy::ecs::{component::Component, world::Mut};
+use bevy::ecs::{component::Component, entity::Entity, world::Mut};
+
 #[cfg(feature = "reflect")]
 use bevy::prelude::{Reflect, ReflectComponent, ReflectDefault};
 
-/// If alongside a [`FetchedText`] or [`FetchedCondition`], prevent [`Text3d`](crate::Text3d) from despawning the entity on remove.
+use crate::SegmentStyle;
+
+/// If alongside a [`FetchedText`], [`FetchedCondition`], [`FetchedStyle`] or [`FetchedList`], prevent [`Text3d`](crate::Text3d) from despawning the entity on remove.
 #[derive(Debug, Component, Default)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Component, Default))]
@@ -15,6 +18,9 @@ pub struct SharedSegment;
 /// By default [`Text3d`](crate::Text3d) removes all linked [`FetchedText`] on remove,
 /// add [`SharedSegment`] to prevent this behavior.
 ///
+/// Numbers written by [`FetchedText::write_if_changed`] are formatted by the [`ValueFormat`](crate::ValueFormat)
+/// of the placeholder, i.e. `{hp:0.0}`.
+///
 /// # Change Detection
 ///
 /// As long as change detection is triggered on this component, associated text will be rebuilt.
@@ -76,3 +82,75 @@ impl FetchedCondition {
         }
     }
 }
+
+/// A style on an [`Entity`](bevy::ecs::entity::Entity) that can be referenced by a [`Text3d`](crate::Text3d)
+/// as `{@name: ...}`, overriding the styles of outer scopes.
+///
+/// By default [`Text3d`](crate::Text3d) removes all linked [`FetchedStyle`] on remove,
+/// add [`SharedSegment`] to prevent this behavior.
+///
+/// # Change Detection
+///
+/// As long as change detection is triggered on this component, associated text will be rebuilt.
+#[derive(Debug, Component, Default)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component, Default))]
+pub struct FetchedStyle(pub SegmentStyle);
+
+impl FetchedStyle {
+    pub const fn get(&self) -> &SegmentStyle {
+        &self.0
+    }
+
+    /// Set and trigger change detection if style is changed.
+    pub fn set_if_changed(mut this: Mut<Self>, style: SegmentStyle) {
+        if this.0 != style {
+            this.0 = style
+        }
+    }
+}
+
+/// A list of item entities on an [`Entity`](bevy::ecs::entity::Entity) that can be referenced by a [`Text3d`](crate::Text3d)
+/// as `{#each name: ...}`, the scope is repeated for each item with a [`FetchedItem`].
+///
+/// By default [`Text3d`](crate::Text3d) removes all linked [`FetchedList`] on remove,
+/// spawn items as children of this entity so they are removed with it.
+///
+/// # Change Detection
+///
+/// As long as change detection is triggered on this component, associated text will be rebuilt.
+/// Changes to [`FetchedItem`] and the [`FetchedText`] of their fields also rebuild the text.
+#[derive(Debug, Component, Default)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component, Default))]
+pub struct FetchedList(pub Vec<Entity>);
+
+impl FetchedList {
+    pub fn get(&self) -> &[Entity] {
+        &self.0
+    }
+
+    /// Set and trigger change detection if items are changed.
+    pub fn set_if_changed(mut this: Mut<Self>, items: &[Entity]) {
+        if this.0 != items {
+            this.0.clear();
+            this.0.extend_from_slice(items);
+        }
+    }
+}
+
+/// Fields of an item in a [`FetchedList`], `{name}` in `{#each}` reads the [`FetchedText`] on the entity of field `name`.
+#[derive(Debug, Component, Default)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component, Default))]
+pub struct FetchedItem(pub Vec<(String, Entity)>);
+
+impl FetchedItem {
+    /// Entity of a field, with a [`FetchedText`].
+    pub fn field(&self, name: &str) -> Option<Entity> {
+        self.0
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, entity)| *entity)
+    }
+}
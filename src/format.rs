@@ -0,0 +1,272 @@
+use std::{fmt::Display, str::FromStr};
+
+#[cfg(feature = "reflect")]
+use bevy::prelude::Reflect;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::ParseError;
+
+/// Format of a dynamic value like `{hp:0.0}`, applied if the [`FetchedText`](crate::FetchedText) is a number.
+///
+/// | Format | Value | Output (`en`) |
+/// | --- | --- | --- |
+/// | `0`, `0.00` | `1234.567` | `1235`, `1234.57` |
+/// | `,`, `,0.0` | `1234.567` | `1,234.567`, `1,234.6` |
+/// | `%`, `0.0%` | `0.1234` | `12%`, `12.3%` |
+/// | `mm:ss`, `m:ss` | `65` | `01:05`, `1:05` |
+/// | `hh:mm:ss`, `h:mm:ss` | `3725` | `01:02:05`, `1:02:05` |
+///
+/// Decimal and thousands separators depend on the locale, i.e. `1.234,6` in `de`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ValueFormat {
+    /// A number with a fixed number of decimals and optional thousands separators.
+    Number {
+        decimals: Option<u8>,
+        grouping: bool,
+    },
+    /// A fraction as a percentage, without decimals by default.
+    Percent { decimals: Option<u8> },
+    /// A number of seconds as a clock.
+    Time { hours: bool, pad: bool },
+}
+
+/// Decimal and thousands separators of a locale.
+struct Separators {
+    decimal: char,
+    group: char,
+    percent: &'static str,
+}
+
+impl Separators {
+    fn new(locale: &str) -> Self {
+        let mut parts = locale.split(['-', '_']);
+        let language = parts.next().unwrap_or(locale);
+        if parts.any(|region| region.eq_ignore_ascii_case("CH")) {
+            return Separators {
+                decimal: '.',
+                group: '\u{2019}',
+                percent: "%",
+            };
+        }
+        match language {
+            "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "el" | "ro" | "hr" | "sl"
+            | "sr" | "vi" | "ca" => Separators {
+                decimal: ',',
+                group: '.',
+                percent: if language == "de" { "\u{A0}%" } else { "%" },
+            },
+            "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "nb" | "no" | "nn" | "fi" | "uk" | "bg"
+            | "hu" | "lt" | "lv" | "et" => Separators {
+                decimal: ',',
+                group: '\u{A0}',
+                percent: "\u{A0}%",
+            },
+            _ => Separators {
+                decimal: '.',
+                group: ',',
+                percent: "%",
+            },
+        }
+    }
+}
+
+/// Write a non-negative number with `.` replaced and the integer part grouped.
+fn write_number(number: &str, grouping: bool, separators: &Separators, out: &mut String) {
+    let (integer, fraction) = match number.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (number, None),
+    };
+    for (i, c) in integer.chars().enumerate() {
+        if grouping && i > 0 && (integer.len() - i) % 3 == 0 {
+            out.push(separators.group);
+        }
+        out.push(c);
+    }
+    if let Some(fraction) = fraction {
+        out.push(separators.decimal);
+        out.push_str(fraction);
+    }
+}
+
+impl ValueFormat {
+    /// Format a number in a locale like `en-US`.
+    pub fn format(&self, value: f64, locale: &str) -> String {
+        let separators = Separators::new(locale);
+        let mut result = String::new();
+        let (value, decimals) = match *self {
+            ValueFormat::Number { decimals, .. } => (value, decimals),
+            ValueFormat::Percent { decimals } => (value * 100.0, decimals.or(Some(0))),
+            ValueFormat::Time { hours, pad } => {
+                if value < 0.0 {
+                    result.push('-');
+                }
+                let seconds = value.abs().floor() as u64;
+                let (h, m, s) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+                match (hours, pad) {
+                    (true, true) => result.push_str(&format!("{h:02}:{m:02}:{s:02}")),
+                    (true, false) => result.push_str(&format!("{h}:{m:02}:{s:02}")),
+                    (false, true) => result.push_str(&format!("{:02}:{s:02}", seconds / 60)),
+                    (false, false) => result.push_str(&format!("{}:{s:02}", seconds / 60)),
+                }
+                return result;
+            }
+        };
+        let number = match decimals {
+            Some(decimals) => format!("{:.*}", decimals as usize, value.abs()),
+            None => value.abs().to_string(),
+        };
+        // Avoid `-0` after rounding.
+        if value < 0.0 && number.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+            result.push('-');
+        }
+        let grouping = matches!(self, ValueFormat::Number { grouping: true, .. });
+        write_number(&number, grouping, &separators, &mut result);
+        if let ValueFormat::Percent { .. } = self {
+            result.push_str(separators.percent);
+        }
+        result
+    }
+
+    /// Format a string if it is a number, otherwise returns it unchanged.
+    pub fn format_str(&self, value: &str, locale: &str) -> String {
+        match value.trim().parse::<f64>() {
+            Ok(number) if number.is_finite() => self.format(number, locale),
+            _ => value.to_string(),
+        }
+    }
+}
+
+impl FromStr for ValueFormat {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let time = |hours, pad| Ok(ValueFormat::Time { hours, pad });
+        match s {
+            "mm:ss" => return time(false, true),
+            "m:ss" => return time(false, false),
+            "hh:mm:ss" => return time(true, true),
+            "h:mm:ss" => return time(true, false),
+            _ => (),
+        }
+        let err = || ParseError::BadCommand(format!("format {s}"));
+        let (rest, percent) = match s.strip_suffix('%') {
+            Some(rest) => (rest, true),
+            None => (s, false),
+        };
+        let (rest, grouping) = match rest.strip_prefix(',') {
+            Some(rest) => (rest, true),
+            None => (rest, false),
+        };
+        let decimals = match rest {
+            "" => None,
+            "0" => Some(0),
+            _ => {
+                let zeros = rest.strip_prefix("0.").ok_or_else(err)?;
+                if zeros.is_empty() || zeros.len() > 16 || zeros.bytes().any(|b| b != b'0') {
+                    return Err(err());
+                }
+                Some(zeros.len() as u8)
+            }
+        };
+        match (percent, grouping) {
+            (true, false) => Ok(ValueFormat::Percent { decimals }),
+            (false, true) => Ok(ValueFormat::Number { decimals, grouping }),
+            (false, false) if decimals.is_some() => Ok(ValueFormat::Number { decimals, grouping }),
+            _ => Err(err()),
+        }
+    }
+}
+
+impl Display for ValueFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (decimals, prefix, suffix) = match *self {
+            ValueFormat::Number { decimals, grouping } => {
+                (decimals, if grouping { "," } else { "" }, "")
+            }
+            ValueFormat::Percent { decimals } => (decimals, "", "%"),
+            ValueFormat::Time { hours, pad } => {
+                return match (hours, pad) {
+                    (true, true) => f.write_str("hh:mm:ss"),
+                    (true, false) => f.write_str("h:mm:ss"),
+                    (false, true) => f.write_str("mm:ss"),
+                    (false, false) => f.write_str("m:ss"),
+                };
+            }
+        };
+        f.write_str(prefix)?;
+        match decimals {
+            Some(0) => f.write_str("0")?,
+            Some(n) => write!(f, "0.{}", "0".repeat(n as usize))?,
+            None => (),
+        }
+        f.write_str(suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValueFormat;
+
+    fn format(format: &str, value: f64, locale: &str) -> String {
+        format.parse::<ValueFormat>().unwrap().format(value, locale)
+    }
+
+    #[test]
+    fn parse_formats() {
+        let number = |decimals, grouping| ValueFormat::Number { decimals, grouping };
+        assert_eq!("0".parse::<ValueFormat>().unwrap(), number(Some(0), false));
+        assert_eq!(
+            "0.00".parse::<ValueFormat>().unwrap(),
+            number(Some(2), false)
+        );
+        assert_eq!(",".parse::<ValueFormat>().unwrap(), number(None, true));
+        assert_eq!(
+            ",0.0".parse::<ValueFormat>().unwrap(),
+            number(Some(1), true)
+        );
+        assert_eq!(
+            "0.0%".parse::<ValueFormat>().unwrap(),
+            ValueFormat::Percent { decimals: Some(1) }
+        );
+        assert_eq!(
+            "m:ss".parse::<ValueFormat>().unwrap(),
+            ValueFormat::Time {
+                hours: false,
+                pad: false
+            }
+        );
+        for bad in ["", "1", "0.", "0.01", ",%", "abc", "mm:s"] {
+            assert!(bad.parse::<ValueFormat>().is_err(), "{bad}");
+        }
+        for format in ["0", "0.00", ",", ",0.0", "%", "0.0%", "mm:ss", "h:mm:ss"] {
+            assert_eq!(format.parse::<ValueFormat>().unwrap().to_string(), format);
+        }
+    }
+
+    #[test]
+    fn format_numbers() {
+        assert_eq!(format("0", 1234.567, "en"), "1235");
+        assert_eq!(format("0.00", 1234.567, "en"), "1234.57");
+        assert_eq!(format(",", 1234.567, "en"), "1,234.567");
+        assert_eq!(format(",0.0", 1234567.89, "en"), "1,234,567.9");
+        assert_eq!(format(",0.0", 1234.567, "de"), "1.234,6");
+        assert_eq!(format("0.0", 0.26, "fr"), "0,3");
+        assert_eq!(format("%", 0.1234, "en"), "12%");
+        assert_eq!(format("0.0%", 0.1234, "de"), "12,3\u{A0}%");
+        assert_eq!(format("mm:ss", 65.9, "en"), "01:05");
+        assert_eq!(format("h:mm:ss", 3725.0, "en"), "1:02:05");
+    }
+
+    #[test]
+    fn format_negative_numbers() {
+        assert_eq!(format("0.0", -1.26, "en"), "-1.3");
+        assert_eq!(format(",", -1234.0, "en"), "-1,234");
+        assert_eq!(format("0", -0.4, "en"), "0");
+        assert_eq!(format("0.00", -0.001, "en"), "0.00");
+        assert_eq!(format("0", -0.0, "en"), "0");
+        assert_eq!(format("m:ss", -65.0, "en"), "-1:05");
+    }
+}
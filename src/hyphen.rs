@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use bevy::ecs::resource::Resource;
+use rustc_hash::FxHashMap;
+
+/// Soft hyphen, an invisible line break opportunity rendered as `-` at the end of a line.
+pub const SOFT_HYPHEN: char = '\u{AD}';
+
+/// Hyphenation patterns of a language, using Liang's algorithm as in TeX.
+///
+/// Patterns are available for most languages in the `hyph-*.pat.txt` files of the `hyph-utf8` project.
+#[derive(Debug, Clone)]
+pub struct HyphenationDictionary {
+    patterns: FxHashMap<String, Vec<u8>>,
+    exceptions: FxHashMap<String, Vec<usize>>,
+    max_pattern: usize,
+    left_min: usize,
+    right_min: usize,
+}
+
+impl HyphenationDictionary {
+    /// Create a dictionary from whitespace separated patterns like `.hy3p he2n`, `%` starts a comment.
+    pub fn from_patterns(patterns: &str) -> Self {
+        let mut result = HyphenationDictionary {
+            patterns: FxHashMap::default(),
+            exceptions: FxHashMap::default(),
+            max_pattern: 0,
+            left_min: 2,
+            right_min: 3,
+        };
+        for pattern in patterns
+            .lines()
+            .flat_map(|line| line.split('%').next().unwrap_or("").split_whitespace())
+        {
+            let mut letters = String::new();
+            let mut values = vec![0];
+            for c in pattern.chars() {
+                match c.to_digit(10) {
+                    Some(digit) => {
+                        if let Some(last) = values.last_mut() {
+                            *last = digit as u8;
+                        }
+                    }
+                    None => {
+                        letters.extend(c.to_lowercase());
+                        values.push(0);
+                    }
+                }
+            }
+            result.max_pattern = result.max_pattern.max(letters.chars().count());
+            result.patterns.insert(letters, values);
+        }
+        result
+    }
+
+    /// Add whitespace separated words with explicit hyphens like `ta-ble`.
+    pub fn with_exceptions(mut self, exceptions: &str) -> Self {
+        for word in exceptions.split_whitespace() {
+            let mut points = Vec::new();
+            let mut letters = String::new();
+            for c in word.chars() {
+                if c == '-' {
+                    points.push(letters.chars().count());
+                } else {
+                    letters.extend(c.to_lowercase());
+                }
+            }
+            self.exceptions.insert(letters, points);
+        }
+        self
+    }
+
+    /// Set the minimum number of characters before and after a hyphen, by default `2` and `3`.
+    pub fn with_min(mut self, left: usize, right: usize) -> Self {
+        self.left_min = left.max(1);
+        self.right_min = right.max(1);
+        self
+    }
+
+    /// Returns character indices in `word` where a hyphen can be inserted.
+    pub fn hyphenate(&self, word: &str) -> Vec<usize> {
+        let lower: String = word.chars().flat_map(char::to_lowercase).collect();
+        let len = lower.chars().count();
+        if len != word.chars().count() || len < self.left_min + self.right_min {
+            return Vec::new();
+        }
+        if let Some(points) = self.exceptions.get(&lower) {
+            return points.clone();
+        }
+        let chars: Vec<char> = ['.']
+            .into_iter()
+            .chain(lower.chars())
+            .chain(['.'])
+            .collect();
+        let mut points = vec![0u8; chars.len() + 1];
+        let mut key = String::new();
+        for start in 0..chars.len() {
+            for end in start + 1..=(start + self.max_pattern).min(chars.len()) {
+                key.clear();
+                key.extend(&chars[start..end]);
+                if let Some(values) = self.patterns.get(&key) {
+                    for (point, value) in points[start..].iter_mut().zip(values) {
+                        *point = (*point).max(*value);
+                    }
+                }
+            }
+        }
+        // `points[i + 1]` is the point before the `i`th character in word.
+        (self.left_min..=len - self.right_min)
+            .filter(|i| points[i + 1] % 2 == 1)
+            .collect()
+    }
+
+    /// Write `text` to `out` with soft hyphens inserted into words.
+    ///
+    /// Words joined by non-breaking spaces, i.e. in `{nobr: ...}`, and words that already contain
+    /// soft hyphens are not hyphenated.
+    pub fn insert_soft_hyphens(&self, text: &str, out: &mut String) {
+        let mut rest = text;
+        while !rest.is_empty() {
+            let start = rest.find(|c: char| c.is_alphabetic()).unwrap_or(rest.len());
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest
+                .find(|c: char| !c.is_alphabetic() && c != '\u{A0}' && c != SOFT_HYPHEN)
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            rest = &rest[end..];
+            if word.contains(['\u{A0}', SOFT_HYPHEN]) {
+                out.push_str(word);
+                continue;
+            }
+            let points = self.hyphenate(word);
+            for (i, c) in word.chars().enumerate() {
+                if points.contains(&i) {
+                    out.push(SOFT_HYPHEN);
+                }
+                out.push(c);
+            }
+        }
+    }
+}
+
+/// Hyphenation dictionaries by language, used by [`Text3dStyle::hyphenate`](crate::Text3dStyle::hyphenate).
+#[derive(Debug, Clone, Default, Resource)]
+pub struct Text3dHyphenation {
+    pub dictionaries: FxHashMap<String, Arc<HyphenationDictionary>>,
+}
+
+impl Text3dHyphenation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a dictionary for a language like `en` or `en-US`.
+    pub fn with_dictionary(
+        mut self,
+        language: impl Into<String>,
+        dictionary: HyphenationDictionary,
+    ) -> Self {
+        self.dictionaries
+            .insert(language.into(), Arc::new(dictionary));
+        self
+    }
+
+    /// Find the dictionary of a language, `en-US` falls back to `en`.
+    pub fn get(&self, language: &str) -> Option<&HyphenationDictionary> {
+        self.dictionaries
+            .get(language)
+            .or_else(|| {
+                let (language, _) = language.split_once(['-', '_'])?;
+                self.dictionaries.get(language)
+            })
+            .map(Arc::as_ref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HyphenationDictionary, SOFT_HYPHEN};
+
+    // Patterns matching `hyphenation` from appendix H of The TeXbook.
+    const PATTERNS: &str = "hy3ph he2n hena4 hen5at 1na n2at 1tio 2io o2n % comment";
+
+    #[test]
+    fn hyphenate() {
+        let dictionary = HyphenationDictionary::from_patterns(PATTERNS).with_exceptions("ta-ble");
+        assert_eq!(dictionary.hyphenate("hyphenation"), vec![2, 6]);
+        assert_eq!(dictionary.hyphenate("Hyphenation"), vec![2, 6]);
+        assert_eq!(dictionary.hyphenate("table"), vec![2]);
+        assert_eq!(dictionary.hyphenate("Table"), vec![2]);
+        assert_eq!(dictionary.hyphenate("hy"), Vec::<usize>::new());
+
+        let mut out = String::new();
+        dictionary.insert_soft_hyphens("Hyphenation, table.", &mut out);
+        assert_eq!(
+            out,
+            format!("Hy{SOFT_HYPHEN}phen{SOFT_HYPHEN}ation, ta{SOFT_HYPHEN}ble.")
+        );
+    }
+
+    #[test]
+    fn hyphenate_min() {
+        let dictionary = HyphenationDictionary::from_patterns(PATTERNS);
+        assert_eq!(
+            dictionary.clone().with_min(3, 3).hyphenate("hyphenation"),
+            vec![6]
+        );
+        assert_eq!(
+            dictionary.clone().with_min(2, 6).hyphenate("hyphenation"),
+            vec![2]
+        );
+        assert_eq!(
+            dictionary.with_min(3, 6).hyphenate("hyphenation"),
+            Vec::<usize>::new()
+        );
+    }
+}
@@ -3,20 +3,32 @@
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::collapsible_if)]
 mod atlas;
+mod bind;
 mod color_table;
+mod condition;
+#[cfg(any(feature = "pbr", feature = "sprite"))]
+mod effects;
 mod emoji;
 mod export;
 mod fetch;
+mod format;
+mod hyphen;
 mod layers;
 mod line;
 mod loading;
+mod localize;
+mod markup;
 mod mesh_util;
 mod misc;
+mod modifier;
 mod parse;
 mod parse_util;
 mod prepare;
 mod render;
+mod reveal;
+mod strings;
 mod styling;
+mod tags;
 mod tess;
 mod text3d;
 
@@ -25,6 +37,8 @@ pub use prepare::{DrawStyle, FontSystemGuard, TextProgressReportCallback, TextRe
 pub use atlas::{TextAtlas, TextAtlasHandle};
 #[cfg(feature = "reflect")]
 use bevy::prelude::{Reflect, ReflectDefault, ReflectResource};
+#[cfg(all(feature = "reflect", feature = "serde"))]
+use bevy::reflect::{ReflectDeserialize, ReflectSerialize};
 use bevy::{
     app::{App, First, Plugin, PostUpdate},
     asset::{AssetApp, AssetId, Assets},
@@ -40,19 +54,41 @@ use bevy::{
     transform::TransformSystems,
     window::{PrimaryWindow, Window},
 };
+pub use bind::{BoundCondition, BoundList, BoundStyle, BoundValue, Text3dBindings};
+pub use condition::{CompareOp, ConditionExpr, ConditionTerm};
+#[cfg(any(feature = "pbr", feature = "sprite"))]
+pub use effects::*;
 
-pub use export::{GlyphMeta, MeshExport, MeshExportEntry};
-pub use fetch::{FetchedCondition, FetchedText, SharedSegment};
+pub use export::{GlyphMeta, MeshExport, MeshExportEntry, ParamName};
+pub use fetch::{
+    FetchedCondition, FetchedItem, FetchedList, FetchedStyle, FetchedText, SharedSegment,
+};
+pub use format::ValueFormat;
+pub use hyphen::{HyphenationDictionary, Text3dHyphenation, SOFT_HYPHEN};
 use loading::{load_cosmic_fonts_system, LoadCosmicFonts};
+pub use localize::{LocaleArg, PluralCategory, Text3dLocale, Text3dLocalized};
+pub use markup::MarkupBuilder;
 pub use misc::*;
-pub use parse_util::{ConditionOutput, ParseBuilder, ParseError};
-pub use styling::{SegmentSize, SegmentStyle, Text3dStyle};
+pub use modifier::{
+    Fade, GlyphModifier, GlyphModifiers, GlyphQuad, Shake, SpanEffectModifier, Wave,
+};
+pub use parse_util::{
+    ConditionOutput, ParseBuilder, ParseError, ParseWarning, SpannedParseError, WhitespaceMode,
+};
+pub use reveal::{RevealCommand, RevealGlyph, Text3dReveal, Text3dRevealMessage};
+pub use strings::{
+    Text3dKey, Text3dStrings, Text3dStringsError, Text3dStringsLoader, Text3dTemplate,
+};
+pub use styling::{ParagraphStyle, SegmentParagraphStyle, SegmentSize, SegmentStyle, Text3dStyle};
+pub use tags::TagSyntax;
 #[allow(deprecated)]
 pub type Text3dStyling = Text3dStyle;
 #[allow(deprecated)]
 pub type SharedTextSegment = SharedSegment;
 #[allow(deprecated)]
 pub type FetchedTextSegment = FetchedText;
+#[cfg(all(feature = "reflect", feature = "serde"))]
+use std::sync::Arc;
 pub use text3d::{Text3d, Text3dSegment};
 
 fn synchronize_scale_factor(
@@ -73,6 +109,49 @@ fn synchronize_scale_factor(
     }
 }
 
+/// Register all reflected types, [`Arc<str>`] is registered with serde data so styles can be saved in a `DynamicWorld`.
+#[cfg(feature = "reflect")]
+fn register_types(app: &mut App) {
+    app.register_type::<Text3d>()
+        .register_type::<Text3dStyle>()
+        .register_type::<Text3dSegment>()
+        .register_type::<SegmentStyle>()
+        .register_type::<SegmentSize>()
+        .register_type::<ParagraphStyle>()
+        .register_type::<SegmentParagraphStyle>()
+        .register_type::<ValueFormat>()
+        .register_type::<ConditionExpr>()
+        .register_type::<MeshExport>()
+        .register_type::<MeshExportEntry>()
+        .register_type::<GlyphMeta>()
+        .register_type::<ParamName>()
+        .register_type::<Text3dBounds>()
+        .register_type::<TextWrap>()
+        .register_type::<TabStop>()
+        .register_type::<TabAlign>()
+        .register_type::<Text3dDimensionOut>()
+        .register_type::<TextAtlasHandle>()
+        .register_type::<SharedSegment>()
+        .register_type::<FetchedText>()
+        .register_type::<FetchedCondition>()
+        .register_type::<FetchedStyle>()
+        .register_type::<FetchedList>()
+        .register_type::<FetchedItem>()
+        .register_type::<BoundValue>()
+        .register_type::<BoundCondition>()
+        .register_type::<BoundStyle>()
+        .register_type::<BoundList>()
+        .register_type::<Text3dReveal>()
+        .register_type::<Text3dKey>()
+        .register_type::<Text3dLocalized>()
+        .register_type::<LocaleArg>()
+        .register_type::<Text3dPlugin>();
+    #[cfg(feature = "serde")]
+    app.register_type::<Arc<str>>()
+        .register_type_data::<Arc<str>, ReflectSerialize>()
+        .register_type_data::<Arc<str>, ReflectDeserialize>();
+}
+
 /// Text3d Plugin.
 #[derive(Debug, Resource, Clone)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
@@ -114,6 +193,13 @@ pub struct Text3dPlugin {
     /// Must add `TouchTextMaterial*dPlugin`s to non-standard materials, otherwise
     /// text drawn before font system is loaded will not be rendered.
     pub asynchronous_load: bool,
+    /// If not `0`, shape text on this many copies of the [`FontSystem`](cosmic_text::FontSystem)
+    /// in parallel on the [`ComputeTaskPool`](bevy::tasks::ComputeTaskPool), text is shaped
+    /// on a single thread if the pool is not initialized.
+    ///
+    /// Only shaping runs in parallel, glyphs are drawn to the atlas and meshes are built on a single thread.
+    /// Font files are shared by all copies, but each copy keeps its own cache of parsed fonts and shaped words.
+    pub layout_threads: usize,
     /// Family name for placeholder glyphs used for placing emojis.
     pub placeholder_family: String,
     /// Pre-defines all glyph widths used by emojis, this generates a placeholder font containing glyphs with various sizes.
@@ -173,6 +259,7 @@ impl Default for Text3dPlugin {
             sync_scale_factor_with_main_window: true,
             load_system_fonts: false,
             asynchronous_load: false,
+            layout_threads: 0,
             locale: None,
             serif_family: String::new(),
             sans_serif_family: String::new(),
@@ -217,6 +304,8 @@ pub struct Text3dSet;
 impl Plugin for Text3dPlugin {
     fn build(&self, app: &mut App) {
         app.init_asset::<TextAtlas>();
+        app.init_asset::<Text3dStrings>();
+        app.init_asset_loader::<Text3dStringsLoader>();
         app.init_resource::<LoadFonts>();
         let mut res = self.clone();
         res.placeholder_glyphs_generated = self
@@ -248,21 +337,23 @@ impl Plugin for Text3dPlugin {
             First,
             load_cosmic_fonts_system.run_if(resource_exists::<LoadCosmicFonts>),
         );
+        app.add_message::<Text3dRevealMessage>();
         app.add_systems(
             PostUpdate,
-            render::text_render
-                .run_if(resource_exists::<TextRenderer>)
+            (
+                bind::text_bindings_system.run_if(resource_exists::<Text3dBindings>),
+                strings::text_strings_system,
+                localize::text_localize_system.run_if(resource_exists::<Text3dLocale>),
+                reveal::text_reveal,
+                render::text_render.run_if(resource_exists::<TextRenderer>),
+            )
+                .chain()
                 .in_set(Text3dSet),
         );
         app.configure_sets(PostUpdate, Text3dSet.before(TransformSystems::Propagate));
 
         #[cfg(feature = "reflect")]
-        app.register_type::<Text3d>()
-            .register_type::<Text3dStyle>()
-            .register_type::<Text3dSegment>()
-            .register_type::<SharedSegment>()
-            .register_type::<FetchedText>()
-            .register_type::<Text3dPlugin>();
+        register_types(app);
     }
 
     fn cleanup(&self, app: &mut App) {
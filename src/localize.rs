@@ -0,0 +1,774 @@
+use std::{borrow::Cow, ops::Range};
+
+use bevy::{
+    asset::{AssetEvent, AssetId, Assets, Handle},
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        lifecycle::RemovedComponents,
+        message::MessageReader,
+        resource::Resource,
+        system::{Commands, Local, Query, Res, ResMut},
+        world::Ref,
+    },
+    log::error,
+};
+#[cfg(feature = "reflect")]
+use bevy::{ecs::reflect::ReflectComponent, reflect::Reflect};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    ParseBuilder, ParseError, SpannedParseError, Text3d, Text3dPlugin, Text3dStrings,
+    Text3dTemplate, TextRenderer,
+};
+
+/// CLDR plural category of a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralCategory {
+    /// Obtain the plural category of a number in a locale.
+    ///
+    /// Only cardinal rules of common languages are implemented, unknown languages use English rules.
+    pub fn new(locale: &str, n: f64) -> Self {
+        use PluralCategory::*;
+        let language = locale.split(['-', '_']).next().unwrap_or(locale);
+        let is_int = n.fract() == 0.0;
+        let i = n.abs().trunc() as u64;
+        match language {
+            "ja" | "zh" | "ko" | "th" | "vi" | "id" | "ms" | "lo" | "my" | "km" => Other,
+            "fr" | "hi" | "bn" | "fa" | "am" | "zu" => {
+                if i <= 1 {
+                    One
+                } else {
+                    Other
+                }
+            }
+            "ru" | "uk" | "be" | "pl" if !is_int => Other,
+            "ru" | "uk" | "be" => match (i % 10, i % 100) {
+                (1, m) if m != 11 => One,
+                (2..=4, m) if !(12..=14).contains(&m) => Few,
+                _ => Many,
+            },
+            "pl" => match (i, i % 10, i % 100) {
+                (1, _, _) => One,
+                (_, 2..=4, m) if !(12..=14).contains(&m) => Few,
+                _ => Many,
+            },
+            "cs" | "sk" => match i {
+                _ if !is_int => Many,
+                1 => One,
+                2..=4 => Few,
+                _ => Other,
+            },
+            "ar" if is_int => match (i, i % 100) {
+                (0, _) => Zero,
+                (1, _) => One,
+                (2, _) => Two,
+                (_, 3..=10) => Few,
+                (_, 11..=99) => Many,
+                _ => Other,
+            },
+            "he" if is_int => match i {
+                1 => One,
+                2 => Two,
+                _ => Other,
+            },
+            _ => {
+                if is_int && i == 1 {
+                    One
+                } else {
+                    Other
+                }
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluralCategory::Zero => "zero",
+            PluralCategory::One => "one",
+            PluralCategory::Two => "two",
+            PluralCategory::Few => "few",
+            PluralCategory::Many => "many",
+            PluralCategory::Other => "other",
+        }
+    }
+}
+
+/// An argument of [`Text3dLocalized`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub enum LocaleArg {
+    String(Cow<'static, str>),
+    Number(f64),
+}
+
+impl From<&'static str> for LocaleArg {
+    fn from(value: &'static str) -> Self {
+        LocaleArg::String(Cow::Borrowed(value))
+    }
+}
+
+impl From<String> for LocaleArg {
+    fn from(value: String) -> Self {
+        LocaleArg::String(Cow::Owned(value))
+    }
+}
+
+macro_rules! impl_number_arg {
+    ($($ty: ty),*) => {
+        $(impl From<$ty> for LocaleArg {
+            fn from(value: $ty) -> Self {
+                LocaleArg::Number(value as f64)
+            }
+        })*
+    };
+}
+
+impl_number_arg!(f32, f64, i32, i64, u32, u64, usize, isize);
+
+/// Active locale and per-locale [`Text3dStrings`] tables used by [`Text3dLocalized`].
+///
+/// Changing `locale` re-parses all [`Text3dLocalized`] and sets [`Text3dPlugin::locale`],
+/// which recreates the font system so font fallback matches the new language.
+/// A loaded or modified table only re-parses keys resolved from it.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct Text3dLocale {
+    /// Active locale, like `en-US`.
+    pub locale: String,
+    /// Locale used when a key is missing in the active locale.
+    pub fallback: Option<String>,
+    /// Tables of strings by locale, `en` matches `en-US` if `en-US` is not present.
+    pub tables: FxHashMap<String, Handle<Text3dStrings>>,
+}
+
+impl Text3dLocale {
+    pub fn new(locale: impl Into<String>) -> Self {
+        Self {
+            locale: locale.into(),
+            fallback: None,
+            tables: FxHashMap::default(),
+        }
+    }
+
+    /// Add a table of strings for a locale.
+    pub fn with_table(mut self, locale: impl Into<String>, strings: Handle<Text3dStrings>) -> Self {
+        self.tables.insert(locale.into(), strings);
+        self
+    }
+
+    /// Set the fallback locale.
+    pub fn with_fallback(mut self, locale: impl Into<String>) -> Self {
+        self.fallback = Some(locale.into());
+        self
+    }
+
+    /// Tables in the active locale, its language and the fallback locale, in lookup order.
+    fn tables(&self) -> impl Iterator<Item = &Handle<Text3dStrings>> {
+        let language = self.locale.split(['-', '_']).next().unwrap_or("");
+        [
+            Some(self.locale.as_str()),
+            Some(language),
+            self.fallback.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|locale| self.tables.get(locale))
+    }
+
+    /// Find a string in the active locale, then its language, then the fallback locale.
+    ///
+    /// Returns `None` if a table is not loaded or the key is missing.
+    pub fn find<'t>(
+        &self,
+        assets: &'t Assets<Text3dStrings>,
+        key: &str,
+    ) -> Option<(&'t Text3dStrings, &'t Text3dTemplate)> {
+        self.tables().find_map(|handle| {
+            let strings = assets.get(handle)?;
+            Some((strings, strings.get(key)?))
+        })
+    }
+}
+
+/// Sets [`Text3d`] to a localized string in [`Text3dLocale`], inserts [`Text3d`] if missing.
+///
+/// # Syntax
+///
+/// On top of [`Text3d::parse`], strings support a subset of [Fluent](https://projectfluent.org) placeables.
+///
+/// ```text
+/// greeting = Hello, {$name}!
+/// apples = You have {$count ->
+///     [0] no apples
+///     [one] {red:one} apple
+///    *[other] {red:{$count}} apples
+///     }.
+/// ```
+///
+/// * `{$name}` is replaced by an argument, special characters are escaped.
+/// * `{$name -> [key] value ... *[key] value}` selects a variant.
+///   Numbers match exact values first, then their [`PluralCategory`] in the active locale.
+///   The variant marked with `*` is used if nothing matches.
+///
+/// Only standard styles are supported.
+#[derive(Debug, Clone, Component, Default)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct Text3dLocalized {
+    /// Key of the string.
+    pub key: Cow<'static, str>,
+    /// Arguments referenced by `{$name}`.
+    pub args: Vec<(Cow<'static, str>, LocaleArg)>,
+}
+
+impl Text3dLocalized {
+    pub fn new(key: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            key: key.into(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Add or replace an argument.
+    pub fn with_arg(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        value: impl Into<LocaleArg>,
+    ) -> Self {
+        self.set_arg(name, value);
+        self
+    }
+
+    /// Add or replace an argument.
+    pub fn set_arg(&mut self, name: impl Into<Cow<'static, str>>, value: impl Into<LocaleArg>) {
+        let name = name.into();
+        let value = value.into();
+        match self.args.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.args.push((name, value)),
+        }
+    }
+
+    /// Obtain an argument by name.
+    pub fn get_arg(&self, name: &str) -> Option<&LocaleArg> {
+        self.args.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    /// Resolve placeables in a string, the result can be parsed by [`Text3d::parse`].
+    pub fn format(&self, locale: &str, source: &str) -> Result<String, SpannedParseError> {
+        self.format_in(locale, source, source)
+    }
+
+    /// Format `source`, a substring of `full`.
+    fn format_in(
+        &self,
+        locale: &str,
+        source: &str,
+        full: &str,
+    ) -> Result<String, SpannedParseError> {
+        let mut result = String::new();
+        let mut rest = source;
+        while let Some(position) = rest.find(['{', '\\']) {
+            result.push_str(&rest[..position]);
+            rest = &rest[position..];
+            if rest.starts_with('\\') {
+                let len = rest[1..].chars().next().map(char::len_utf8).unwrap_or(0) + 1;
+                result.push_str(&rest[..len]);
+                rest = &rest[len..];
+                continue;
+            }
+            if !is_placeable(rest) {
+                result.push('{');
+                rest = &rest[1..];
+                continue;
+            }
+            let start = rest.as_ptr() as usize - full.as_ptr() as usize;
+            let end = matching_bracket(rest).ok_or_else(|| {
+                SpannedParseError::new(full, ParseError::UnclosedBracket, start..start + 1)
+            })?;
+            self.format_placeable(locale, &rest[1..end], full, &mut result)
+                .map_err(|e| match e {
+                    PlaceableError::Parse(e) => {
+                        SpannedParseError::new(full, e, start..start + end + 1)
+                    }
+                    PlaceableError::Spanned(e) => e,
+                })?;
+            rest = &rest[end + 1..];
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    fn format_placeable(
+        &self,
+        locale: &str,
+        placeable: &str,
+        full: &str,
+        result: &mut String,
+    ) -> Result<(), PlaceableError> {
+        let (name, variants) = match placeable.split_once("->") {
+            Some((name, variants)) => (name, Some(variants)),
+            None => (placeable, None),
+        };
+        let name = name.trim().trim_start_matches('$').trim();
+        let arg = self
+            .get_arg(name)
+            .ok_or_else(|| ParseError::Custom(format!("Missing argument ${name}.")))?;
+        let Some(variants) = variants else {
+            match arg {
+                LocaleArg::String(s) => escape(s, result),
+                LocaleArg::Number(n) => escape(&format!("{n}"), result),
+            }
+            return Ok(());
+        };
+        let variants = split_variants(variants)?;
+        let selected = variants
+            .iter()
+            .find(|(_, key, _)| match arg {
+                LocaleArg::String(s) => s == key,
+                LocaleArg::Number(n) => key.parse::<f64>().is_ok_and(|k| k == *n),
+            })
+            .or_else(|| match arg {
+                LocaleArg::Number(n) => {
+                    let category = PluralCategory::new(locale, *n).as_str();
+                    variants.iter().find(|(_, key, _)| *key == category)
+                }
+                LocaleArg::String(_) => None,
+            })
+            .or_else(|| variants.iter().find(|(default, _, _)| *default))
+            .ok_or_else(|| ParseError::BadCommand(format!("No default variant for ${name}.")))?;
+        result.push_str(
+            &self
+                .format_in(locale, selected.2, full)
+                .map_err(PlaceableError::Spanned)?,
+        );
+        Ok(())
+    }
+}
+
+enum PlaceableError {
+    Parse(ParseError),
+    Spanned(SpannedParseError),
+}
+
+impl From<ParseError> for PlaceableError {
+    fn from(value: ParseError) -> Self {
+        PlaceableError::Parse(value)
+    }
+}
+
+fn escape(s: &str, result: &mut String) {
+    for c in s.chars() {
+        if matches!(c, '{' | '}' | '*' | '_' | '~' | '\\') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+}
+
+/// Returns true if `s` starts with a placeable like `{$name}`, `{$18: text}` is a style.
+fn is_placeable(s: &str) -> bool {
+    s.strip_prefix('{')
+        .and_then(|s| s.trim_start().strip_prefix('$'))
+        .and_then(|s| s.chars().next())
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+}
+
+/// Replace placeables with whitespace so the rest can be checked by [`Text3d::parse`],
+/// variants of selectors are kept in place.
+///
+/// Byte offsets and line breaks are unchanged.
+pub(crate) fn blank_placeables(source: &str) -> String {
+    let mut bytes = source.as_bytes().to_vec();
+    blank_placeables_in(source, source, &mut bytes);
+    String::from_utf8(bytes).unwrap_or_else(|_| source.to_owned())
+}
+
+/// Blank placeables in `s`, a substring of `full`.
+fn blank_placeables_in(s: &str, full: &str, bytes: &mut [u8]) {
+    let offset_of = |s: &str| s.as_ptr() as usize - full.as_ptr() as usize;
+    let blank = |range: Range<usize>, bytes: &mut [u8]| {
+        for byte in &mut bytes[range] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    };
+    let mut rest = s;
+    while let Some(position) = rest.find(['{', '\\']) {
+        rest = &rest[position..];
+        if rest.starts_with('\\') {
+            let len = rest[1..].chars().next().map(char::len_utf8).unwrap_or(0) + 1;
+            rest = &rest[len..];
+            continue;
+        }
+        if !is_placeable(rest) {
+            rest = &rest[1..];
+            continue;
+        }
+        let Some(end) = matching_bracket(rest) else {
+            return;
+        };
+        let start = offset_of(rest);
+        let variants = rest[1..end]
+            .split_once("->")
+            .and_then(|(_, variants)| split_variants(variants).ok());
+        let mut cursor = start;
+        for (_, _, body) in variants.into_iter().flatten() {
+            blank(cursor..offset_of(body), bytes);
+            blank_placeables_in(body, full, bytes);
+            cursor = offset_of(body) + body.len();
+        }
+        blank(cursor..start + end + 1, bytes);
+        rest = &rest[end + 1..];
+    }
+}
+
+/// Find the `}` matching the `{` at the start of `s`.
+fn matching_bracket(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Split `[key] value *[key] value` into `(is_default, key, value)`.
+fn split_variants(s: &str) -> Result<Vec<(bool, &str, &str)>, ParseError> {
+    let mut result: Vec<(bool, &str, &str)> = Vec::new();
+    let mut depth = 0;
+    let mut escaped = false;
+    let mut body_start = None;
+    let mut iter = s.char_indices().peekable();
+    while let Some((i, c)) = iter.next() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '*' | '['
+                if depth == 0 && (c == '[' || iter.peek().is_some_and(|(_, c)| *c == '[')) =>
+            {
+                let is_default = c == '*';
+                if is_default {
+                    iter.next();
+                }
+                let key_start = if is_default { i + 2 } else { i + 1 };
+                if let (Some(start), Some(last)) = (body_start, result.last_mut()) {
+                    last.2 = s[start..i].trim();
+                }
+                let key_end = s[key_start..]
+                    .find(']')
+                    .map(|x| x + key_start)
+                    .ok_or(ParseError::BracketMismatch)?;
+                result.push((is_default, s[key_start..key_end].trim(), ""));
+                while iter.peek().is_some_and(|(j, _)| *j <= key_end) {
+                    iter.next();
+                }
+                body_start = Some(key_end + 1);
+            }
+            c if depth == 0 && body_start.is_none() && !c.is_whitespace() => {
+                return Err(ParseError::BadCommand(s.trim().to_owned()));
+            }
+            _ => (),
+        }
+    }
+    match (body_start, result.last_mut()) {
+        (Some(start), Some(last)) => last.2 = s[start..].trim(),
+        _ => return Err(ParseError::BadCommand(s.trim().to_owned())),
+    }
+    Ok(result)
+}
+
+pub fn text_localize_system(
+    mut commands: Commands,
+    locale: Res<Text3dLocale>,
+    mut applied_locale: Local<String>,
+    mut parsed_from: Local<FxHashMap<Entity, AssetId<Text3dStrings>>>,
+    mut removed: RemovedComponents<Text3dLocalized>,
+    mut plugin: ResMut<Text3dPlugin>,
+    renderer: Option<ResMut<TextRenderer>>,
+    mut events: MessageReader<AssetEvent<Text3dStrings>>,
+    assets: Res<Assets<Text3dStrings>>,
+    mut query: Query<(Entity, Ref<Text3dLocalized>, Option<&mut Text3d>)>,
+) {
+    if let Some(mut renderer) = renderer {
+        if !locale.locale.is_empty() && *applied_locale != locale.locale {
+            renderer.set_locale(locale.locale.clone());
+            plugin.locale = Some(locale.locale.clone());
+            *applied_locale = locale.locale.clone();
+        }
+    }
+    for entity in removed.read() {
+        parsed_from.remove(&entity);
+    }
+    let changed_assets: FxHashSet<_> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    for (entity, localized, text) in &mut query {
+        if !locale.is_changed() && !localized.is_changed() && changed_assets.is_empty() {
+            continue;
+        }
+        let found = locale.tables().find_map(|handle| {
+            let strings = assets.get(handle)?;
+            Some((handle.id(), strings, strings.get(&localized.key)?))
+        });
+        // Only re-parse if the key resolves to a different or changed table.
+        let table = found.map(|(id, ..)| id);
+        let table_changed = table.is_some_and(|id| changed_assets.contains(&id))
+            || parsed_from.get(&entity) != table.as_ref();
+        if !locale.is_changed() && !localized.is_changed() && !table_changed {
+            continue;
+        }
+        let Some((id, strings, template)) = found else {
+            parsed_from.remove(&entity);
+            continue;
+        };
+        parsed_from.insert(entity, id);
+        let result = localized
+            .format(&locale.locale, &template.source)
+            .and_then(|source| {
+                Text3d::parse_with_warnings(&source, ParseBuilder::new()).map(|(text, _)| text)
+            });
+        match result {
+            Ok(parsed) => match text {
+                Some(mut text) => *text = parsed,
+                None => {
+                    commands.entity(entity).insert(parsed);
+                }
+            },
+            Err(e) => error!(
+                "{}:{}: In `{}`: {}",
+                strings.path(),
+                template.line,
+                localized.key,
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        blank_placeables, text_localize_system, PluralCategory, Text3dLocale, Text3dLocalized,
+    };
+    use crate::{
+        parse_util::ConditionOutput, strings::placeholder_value, ParseBuilder, SegmentStyle,
+        Text3d, Text3dPlugin, Text3dStrings, TextRenderer,
+    };
+    use bevy::{
+        app::{App, TaskPoolPlugin, Update},
+        asset::{AssetApp, AssetPlugin, Assets},
+        ecs::{change_detection::DetectChanges, entity::Entity},
+    };
+    use cosmic_text::{fontdb::Database, FontSystem};
+
+    #[test]
+    fn blank_keeps_offsets_and_variants() {
+        let source = "A {$n -> [one] {red:one}\n *[other] {$n}} {$18:b}";
+        let blanked = blank_placeables(source);
+        assert_eq!(blanked.len(), source.len());
+        assert_eq!(blanked.find('\n'), source.find('\n'));
+        assert!(blanked.contains("{red:one}"));
+        assert!(!blanked.contains("$n"));
+        assert!(blanked.contains("{$18:b}"));
+        assert!(!blanked.contains("[one]"));
+    }
+
+    #[test]
+    fn shipped_tables_have_no_warnings() {
+        for (path, source) in [
+            ("en.rtext", include_str!("../assets/locale/en.rtext")),
+            ("fr.rtext", include_str!("../assets/locale/fr.rtext")),
+        ] {
+            let strings = Text3dStrings::from_source(path, source).unwrap();
+            for (key, template) in strings.iter() {
+                let (_, warnings) = Text3d::parse_with_warnings(
+                    &blank_placeables(&template.source),
+                    ParseBuilder::new()
+                        .with_parse_style(|_| Ok(SegmentStyle::default()))
+                        .with_parse_value(placeholder_value)
+                        .with_parse_condition(|_| Ok(ConditionOutput::Constant(true))),
+                )
+                .unwrap();
+                assert!(warnings.is_empty(), "{path}: {key}: {warnings:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn plural_categories() {
+        use PluralCategory::*;
+        let categories = |locale: &str, numbers: &[f64]| {
+            numbers
+                .iter()
+                .map(|n| PluralCategory::new(locale, *n))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            categories("en", &[0.0, 1.0, 2.0, 1.5]),
+            [Other, One, Other, Other]
+        );
+        assert_eq!(categories("en-US", &[1.0]), [One]);
+        assert_eq!(
+            categories("ru", &[1.0, 2.0, 5.0, 11.0, 12.0, 21.0, 22.0, 25.0, 1.5]),
+            [One, Few, Many, Many, Many, One, Few, Many, Other]
+        );
+        assert_eq!(
+            categories(
+                "ar",
+                &[0.0, 1.0, 2.0, 3.0, 10.0, 11.0, 99.0, 100.0, 103.0, 0.5]
+            ),
+            [Zero, One, Two, Few, Few, Many, Many, Other, Few, Other]
+        );
+        assert_eq!(categories("xx", &[1.0, 2.0]), [One, Other]);
+    }
+
+    #[test]
+    fn format_selector() {
+        let source = "{$count ->
+            [0] no apples
+            [one] {red:one} apple
+           *[other] {$count} apples
+        } for {$name -> [a] A *[other] {$name}}";
+        let format = |count: f64, name: &'static str| {
+            Text3dLocalized::new("apples")
+                .with_arg("count", count)
+                .with_arg("name", name)
+                .format("en", source)
+                .unwrap()
+        };
+        assert_eq!(format(0.0, "a"), "no apples for A");
+        assert_eq!(format(1.0, "b"), "{red:one} apple for b");
+        assert_eq!(format(3.0, "c"), "3 apples for c");
+
+        let localized = Text3dLocalized::new("apples").with_arg("count", 2.0);
+        assert_eq!(
+            localized
+                .format("ar", "{$count -> [two] two *[other] many}")
+                .unwrap(),
+            "two"
+        );
+        assert_eq!(
+            localized
+                .format("en", "{$count -> [two] two *[other] many}")
+                .unwrap(),
+            "many"
+        );
+        assert!(localized.format("en", "{$count -> [one] one}").is_err());
+        assert!(localized.format("en", "{$missing}").is_err());
+    }
+
+    #[test]
+    fn only_reparse_changed_tables() {
+        let mut app = App::new();
+        app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+            .init_asset::<Text3dStrings>()
+            .init_resource::<Text3dPlugin>()
+            .add_systems(Update, text_localize_system);
+        let mut assets = app.world_mut().resource_mut::<Assets<Text3dStrings>>();
+        let fr = assets.add(Text3dStrings::from_source("fr.rtext", "a = A fr").unwrap());
+        let en = assets.add(Text3dStrings::from_source("en.rtext", "b = B en").unwrap());
+        app.insert_resource(
+            Text3dLocale::new("fr")
+                .with_table("fr", fr.clone())
+                .with_table("en", en)
+                .with_fallback("en"),
+        );
+        let a = app.world_mut().spawn(Text3dLocalized::new("a")).id();
+        let b = app.world_mut().spawn(Text3dLocalized::new("b")).id();
+        app.update();
+        let text = |app: &App, entity| {
+            app.world()
+                .get::<Text3d>(entity)
+                .unwrap()
+                .get_single()
+                .map(str::to_owned)
+        };
+        assert_eq!(text(&app, a).as_deref(), Some("A fr"));
+        assert_eq!(text(&app, b).as_deref(), Some("B en"));
+        app.update();
+
+        let tick = |app: &App, entity: Entity| {
+            app.world()
+                .entity(entity)
+                .get_ref::<Text3d>()
+                .unwrap()
+                .last_changed()
+        };
+        let before = [tick(&app, a), tick(&app, b)];
+        app.world_mut()
+            .resource_mut::<Assets<Text3dStrings>>()
+            .insert(
+                fr.id(),
+                Text3dStrings::from_source("fr.rtext", "a = A2 fr").unwrap(),
+            )
+            .unwrap();
+        app.update();
+        app.update();
+        assert_eq!(text(&app, a).as_deref(), Some("A2 fr"));
+        assert_ne!(tick(&app, a), before[0]);
+        assert_eq!(tick(&app, b), before[1]);
+
+        app.world_mut()
+            .resource_mut::<Assets<Text3dStrings>>()
+            .insert(
+                fr.id(),
+                Text3dStrings::from_source("fr.rtext", "b = B fr").unwrap(),
+            )
+            .unwrap();
+        app.update();
+        app.update();
+        assert_eq!(text(&app, a).as_deref(), Some("A2 fr"));
+        assert_eq!(text(&app, b).as_deref(), Some("B fr"));
+    }
+
+    #[test]
+    fn set_same_locale_keeps_font_system() {
+        let mut renderer = TextRenderer::new(FontSystem::new_with_locale_and_db(
+            "en".into(),
+            Database::new(),
+        ));
+        renderer
+            .0
+            .lock()
+            .unwrap()
+            .shards
+            .push(FontSystem::new_with_locale_and_db(
+                "en".into(),
+                Database::new(),
+            ));
+        renderer.set_locale("en");
+        assert_eq!(renderer.0.lock().unwrap().shards.len(), 1);
+        renderer.set_locale("fr");
+        assert_eq!(renderer.lock().locale(), "fr");
+        assert!(renderer.0.lock().unwrap().shards.is_empty());
+    }
+}
@@ -0,0 +1,740 @@
+use std::fmt::Write;
+
+use bevy::{
+    color::{ColorToPacked, Srgba},
+    ecs::entity::Entity,
+};
+
+use crate::{
+    misc::{Style, Weight},
+    ConditionTerm, ParseError, RevealCommand, SegmentSize, SegmentStyle, SpanEffect, SpanEffects,
+    Text3d, Text3dSegment,
+};
+
+/// Builder pattern input for [`Text3d::to_markup_with`], the reverse of [`ParseBuilder`](crate::ParseBuilder).
+#[derive(Default)]
+pub struct MarkupBuilder<'t> {
+    styles: Vec<(&'t str, SegmentStyle)>,
+    value: Option<Box<dyn FnMut(&Text3dSegment) -> Option<String> + 't>>,
+    condition: Option<Box<dyn FnMut(Entity) -> Option<String> + 't>>,
+    dynamic_style: Option<Box<dyn FnMut(Entity) -> Option<String> + 't>>,
+    list: Option<Box<dyn FnMut(Entity) -> Option<String> + 't>>,
+}
+
+impl<'t> MarkupBuilder<'t> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a named style, segments containing all fields of the style are written as `name`.
+    ///
+    /// Styles are matched in insertion order, the parser's stylesheet should map `name` back to `style`.
+    pub fn with_style(mut self, name: &'t str, style: SegmentStyle) -> Self {
+        self.styles.push((name, style));
+        self
+    }
+
+    /// Map [`Text3dSegment::Extract`] and [`Text3dSegment::Image`] to value names written as `{name}`.
+    pub fn with_value(mut self, f: impl FnMut(&Text3dSegment) -> Option<String> + 't) -> Self {
+        self.value = Some(Box::new(f));
+        self
+    }
+
+    /// Map the condition entities of [`Text3dSegment::SkipIf`] and [`Text3dSegment::SkipIfExpr`]
+    /// to condition names written as `{?name: ...}`.
+    pub fn with_condition(mut self, f: impl FnMut(Entity) -> Option<String> + 't) -> Self {
+        self.condition = Some(Box::new(f));
+        self
+    }
+
+    /// Map the style entity of [`Text3dSegment::Style`] to dynamic style names written as `{@name: ...}`.
+    pub fn with_dynamic_style(mut self, f: impl FnMut(Entity) -> Option<String> + 't) -> Self {
+        self.dynamic_style = Some(Box::new(f));
+        self
+    }
+
+    /// Map the list entity of [`Text3dSegment::Each`] to list names written as `{#each name: ...}`.
+    pub fn with_list(mut self, f: impl FnMut(Entity) -> Option<String> + 't) -> Self {
+        self.list = Some(Box::new(f));
+        self
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.trim().is_empty() && !name.contains(['{', '}', ':', ',', '\\'])
+}
+
+/// Names in conditions cannot contain operators of [`ConditionExpr`].
+fn is_valid_condition(name: &str) -> bool {
+    is_valid_name(name)
+        && !name.trim().starts_with('?')
+        && !name.contains(['!', '<', '>', '=', '&', '|'])
+}
+
+/// Whether `segment` is the else branch of the conditional `previous`.
+fn is_else_branch(previous: &Text3dSegment, segment: &Text3dSegment) -> bool {
+    match (previous, segment) {
+        (
+            Text3dSegment::SkipIf {
+                condition: a,
+                skip_if: x,
+                ..
+            },
+            Text3dSegment::SkipIf {
+                condition: b,
+                skip_if: y,
+                ..
+            },
+        ) => a == b && x != y,
+        (
+            Text3dSegment::SkipIfExpr {
+                condition: a,
+                skip_if: x,
+                ..
+            },
+            Text3dSegment::SkipIfExpr {
+                condition: b,
+                skip_if: y,
+                ..
+            },
+        ) => a == b && x != y,
+        _ => false,
+    }
+}
+
+fn hex(color: Srgba) -> String {
+    let [r, g, b, a] = color.to_u8_array();
+    format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+}
+
+/// Escape special characters, whitespaces that would be collapsed or trimmed are also escaped.
+///
+/// Characters that may start line level markdown are escaped at the start of each line.
+fn escape(s: &str, result: &mut String) {
+    let end = s.trim_end().len();
+    let mut after_whitespace = true;
+    let mut line_start = true;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' | '}' | '*' | '_' | '~' | '`' | '|' | '\\' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '#' | '-' | '+' | '>' | '0'..='9' if line_start => {
+                result.push('\\');
+                result.push(c);
+            }
+            ' ' | '\n' if !after_whitespace && i < end => result.push(c),
+            c if c.is_whitespace() => {
+                result.push('\\');
+                result.push(c);
+            }
+            c => result.push(c),
+        }
+        after_whitespace = c.is_whitespace();
+        line_start = c == '\n' || (line_start && c.is_whitespace());
+    }
+}
+
+/// Returns true if all fields set in `named` are equal in `style`.
+fn contains_style(style: &SegmentStyle, named: &SegmentStyle) -> bool {
+    fn check<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+        b.is_none() || a == b
+    }
+    check(&style.font, &named.font)
+        && check(&style.size, &named.size)
+        && check(&style.fill_color, &named.fill_color)
+        && check(&style.stroke_color, &named.stroke_color)
+        && check(&style.fill, &named.fill)
+        && check(&style.stroke, &named.stroke)
+        && check(&style.weight, &named.weight)
+        && check(&style.style, &named.style)
+        && check(&style.underline, &named.underline)
+        && check(&style.strikethrough, &named.strikethrough)
+        && check(&style.magic_number, &named.magic_number)
+        && check(&style.params, &named.params)
+        && named
+            .named_params
+            .iter()
+            .all(|(k, v)| style.named_param(k) == Some(*v))
+        && check(&style.paragraph.margin_left, &named.paragraph.margin_left)
+        && check(&style.paragraph.margin_right, &named.paragraph.margin_right)
+        && check(&style.paragraph.indent, &named.paragraph.indent)
+        && check(
+            &style.paragraph.spacing_before,
+            &named.paragraph.spacing_before,
+        )
+        && check(
+            &style.paragraph.spacing_after,
+            &named.paragraph.spacing_after,
+        )
+        && check(
+            &style.paragraph.hanging_marker,
+            &named.paragraph.hanging_marker,
+        )
+        && check(&style.format, &named.format)
+        && SpanEffect::ALL.into_iter().all(|effect| {
+            !named.effects.contains(effect)
+                || named.effects.strength(effect) == style.effects.strength(effect)
+        })
+}
+
+/// Remove fields set in `named` from `style`.
+fn remove_style(style: &mut SegmentStyle, named: &SegmentStyle) {
+    macro_rules! remove {
+        ($($($field: ident).+),*) => {
+            $(if named.$($field).+.is_some() {
+                style.$($field).+ = None;
+            })*
+        };
+    }
+    remove!(
+        font,
+        size,
+        fill_color,
+        stroke_color,
+        fill,
+        stroke,
+        weight,
+        style,
+        underline,
+        strikethrough,
+        magic_number,
+        params,
+        paragraph.margin_left,
+        paragraph.margin_right,
+        paragraph.indent,
+        paragraph.spacing_before,
+        paragraph.spacing_after,
+        paragraph.hanging_marker,
+        format
+    );
+    style
+        .named_params
+        .retain(|(k, _)| named.named_param(k).is_none());
+    let mut effects = SpanEffects::NONE;
+    for effect in SpanEffect::ALL {
+        if style.effects.contains(effect) && !named.effects.contains(effect) {
+            effects = effects.with(effect, style.effects.strength(effect));
+        }
+    }
+    style.effects = effects;
+}
+
+/// Write the styles of a segment and the markdown markers that wrap its text.
+fn write_style(
+    mut style: SegmentStyle,
+    named: &[(&str, SegmentStyle)],
+    styles: &mut Vec<String>,
+    markers: &mut String,
+) -> Result<(), ParseError> {
+    for (name, named) in named {
+        if contains_style(&style, named) {
+            styles.push(name.to_string());
+            remove_style(&mut style, named);
+        }
+    }
+    if let Some(font) = &style.font {
+        if !is_valid_name(font) {
+            return Err(ParseError::NotSupported(
+                "font name with special characters",
+            ));
+        }
+        styles.push(format!("f-{font}"));
+    }
+    match style.size {
+        Some(SegmentSize::Flat(size)) => styles.push(format!("${size}")),
+        Some(SegmentSize::Multiply(size)) => styles.push(format!("*{size}")),
+        None => (),
+    }
+    if let Some(color) = style.fill_color {
+        styles.push(hex(color));
+    }
+    if let Some(color) = style.stroke_color {
+        styles.push(format!("s-{}", hex(color)));
+    }
+    if style.fill.is_some() {
+        return Err(ParseError::NotSupported("fill"));
+    }
+    if let Some(stroke) = style.stroke {
+        styles.push(format!("s-{stroke}"));
+    }
+    // `**` inside `{bold: ...}` produces `Weight::NORMAL`, same for other markers.
+    match style.weight {
+        Some(Weight::BOLD) => styles.push("bold".into()),
+        Some(Weight::NORMAL) => {
+            styles.push("bold".into());
+            markers.push_str("**");
+        }
+        Some(_) => return Err(ParseError::NotSupported("font weight")),
+        None => (),
+    }
+    match style.style {
+        Some(Style::Italic) => styles.push("italic".into()),
+        Some(_) => return Err(ParseError::NotSupported("font style")),
+        None => (),
+    }
+    match style.underline {
+        Some(true) => styles.push("underline".into()),
+        Some(false) => {
+            styles.push("underline".into());
+            markers.push_str("__");
+        }
+        None => (),
+    }
+    match style.strikethrough {
+        Some(true) => styles.push("strikethrough".into()),
+        Some(false) => {
+            styles.push("strikethrough".into());
+            markers.push_str("~~");
+        }
+        None => (),
+    }
+    match (style.magic_number, style.params) {
+        (Some(magic), Some(params)) if magic == params.x => {
+            styles.push(format!(
+                "v-{},{},{},{}",
+                params.x, params.y, params.z, params.w
+            ));
+        }
+        (None, None) => (),
+        _ => {
+            return Err(ParseError::NotSupported(
+                "magic_number different from params",
+            ))
+        }
+    }
+    for (key, value) in &style.named_params {
+        if !is_valid_name(key) || key.contains('=') {
+            return Err(ParseError::NotSupported(
+                "named param with special characters",
+            ));
+        }
+        styles.push(format!("param.{key}={value}"));
+    }
+    let paragraph = style.paragraph;
+    match (
+        paragraph.margin_left,
+        paragraph.indent,
+        paragraph.hanging_marker,
+    ) {
+        (Some(margin), Some(0.0), Some(true)) => styles.push(format!("hanging-{margin}")),
+        (_, _, Some(_)) => return Err(ParseError::NotSupported("list marker without margin")),
+        (margin, indent, None) => {
+            if let Some(margin) = margin {
+                styles.push(format!("margin-{margin}"));
+            }
+            if let Some(indent) = indent {
+                styles.push(format!("indent-{indent}"));
+            }
+        }
+    }
+    if let Some(margin) = paragraph.margin_right {
+        styles.push(format!("margin-right-{margin}"));
+    }
+    if let Some(spacing) = paragraph.spacing_before {
+        styles.push(format!("spacing-{spacing}"));
+    }
+    if let Some(spacing) = paragraph.spacing_after {
+        styles.push(format!("spacing-after-{spacing}"));
+    }
+    if style.format.is_some() {
+        return Err(ParseError::NotSupported("format outside of a value"));
+    }
+    for effect in SpanEffect::ALL
+        .into_iter()
+        .filter(|x| style.effects.contains(*x))
+    {
+        match style.effects.strength(effect) {
+            1.0 => styles.push(effect.name().into()),
+            strength => styles.push(format!("{}-{}", effect.name(), strength)),
+        }
+    }
+    Ok(())
+}
+
+impl Text3d {
+    /// Convert to markup accepted by [`Text3d::parse_raw`].
+    ///
+    /// Colors are written as hex and rounded to 8 bits per channel,
+    /// see [`Text3d::to_markup_with`] for details.
+    pub fn to_markup(&self) -> Result<String, ParseError> {
+        self.to_markup_with(MarkupBuilder::new())
+    }
+
+    /// Convert to markup accepted by [`Text3d::parse`].
+    ///
+    /// Parsing the result with a stylesheet that reverses `builder` produces an equivalent [`Text3d`],
+    /// except that colors are rounded to 8 bits per channel and adjacent strings with the same style may be merged.
+    ///
+    /// Returns [`ParseError::NotSupported`] if a style or segment has no equivalent markup,
+    /// like [`Text3dSegment::Extract`] without [`MarkupBuilder::with_value`].
+    pub fn to_markup_with(&self, mut builder: MarkupBuilder) -> Result<String, ParseError> {
+        let mut result = String::new();
+        // Number of segments left in each conditional or dynamic style, the segment that opened it
+        // and whether it is an else branch, which cannot have another else branch.
+        let mut conditionals: Vec<(usize, &Text3dSegment, bool)> = Vec::new();
+        // Conditional closed by the previous segment, may be followed by an else branch.
+        let mut closed = None;
+        let mut styles = Vec::new();
+        let mut markers = String::new();
+        for (segment, style) in &self.segments {
+            styles.clear();
+            markers.clear();
+            let previous = closed.take();
+            match segment {
+                Text3dSegment::String(s) if s.is_empty() => (),
+                Text3dSegment::String(s) => {
+                    write_style(style.clone(), &builder.styles, &mut styles, &mut markers)?;
+                    if styles.is_empty() {
+                        escape(s, &mut result);
+                    } else {
+                        let _ = write!(result, "{{{}:{markers}", styles.join(", "));
+                        escape(s, &mut result);
+                        let _ = write!(result, "{markers}}}");
+                    }
+                }
+                Text3dSegment::Extract(_) | Text3dSegment::Image { .. } => {
+                    let name = builder
+                        .value
+                        .as_mut()
+                        .and_then(|f| f(segment))
+                        .filter(|name| is_valid_name(name) && !name.trim().starts_with('?'))
+                        .ok_or(ParseError::NotSupported("value without a name"))?;
+                    let mut style = style.clone();
+                    let value = match style.format.take() {
+                        Some(format) => format!("{{{name}:{format}}}"),
+                        None => format!("{{{name}}}"),
+                    };
+                    write_style(style, &builder.styles, &mut styles, &mut markers)?;
+                    if styles.is_empty() {
+                        result.push_str(&value);
+                    } else {
+                        let _ = write!(result, "{{{}:{value}}}", styles.join(", "));
+                    }
+                }
+                Text3dSegment::SkipIf { offset, .. } | Text3dSegment::SkipIfExpr { offset, .. }
+                    if previous.is_some_and(|previous| is_else_branch(previous, segment)) =>
+                {
+                    result.push_str("{:else:");
+                    conditionals.push((*offset + 1, segment, true));
+                }
+                Text3dSegment::SkipIf {
+                    condition,
+                    skip_if,
+                    offset,
+                } => {
+                    let name = builder
+                        .condition
+                        .as_mut()
+                        .and_then(|f| f(*condition))
+                        .filter(|name| is_valid_condition(name))
+                        .ok_or(ParseError::NotSupported("condition without a name"))?;
+                    let not = if *skip_if { "!" } else { "" };
+                    let _ = write!(result, "{{?{not}{}:", name.trim());
+                    conditionals.push((*offset + 1, segment, false));
+                }
+                Text3dSegment::SkipIfExpr {
+                    condition,
+                    skip_if,
+                    offset,
+                } => {
+                    if *skip_if {
+                        return Err(ParseError::NotSupported("negated condition expression"));
+                    }
+                    let _ = write!(result, "{{?");
+                    for (i, group) in condition.0.iter().enumerate() {
+                        if i > 0 {
+                            result.push_str(" || ");
+                        }
+                        for (j, term) in group.iter().enumerate() {
+                            if j > 0 {
+                                result.push_str(" && ");
+                            }
+                            match term {
+                                ConditionTerm::Condition { condition, negate } => {
+                                    let name = builder
+                                        .condition
+                                        .as_mut()
+                                        .and_then(|f| f(*condition))
+                                        .filter(|name| is_valid_condition(name))
+                                        .ok_or(ParseError::NotSupported(
+                                            "condition without a name",
+                                        ))?;
+                                    let not = if *negate { "!" } else { "" };
+                                    let _ = write!(result, "{not}{}", name.trim());
+                                }
+                                ConditionTerm::Compare {
+                                    value,
+                                    op,
+                                    rhs,
+                                    negate,
+                                } => {
+                                    let name = builder
+                                        .value
+                                        .as_mut()
+                                        .and_then(|f| f(&Text3dSegment::Extract(*value)))
+                                        .filter(|name| is_valid_condition(name))
+                                        .ok_or(ParseError::NotSupported("value without a name"))?;
+                                    let not = if *negate { "!" } else { "" };
+                                    let _ = write!(result, "{not}{} {op} {rhs}", name.trim());
+                                }
+                            }
+                        }
+                    }
+                    result.push(':');
+                    conditionals.push((*offset + 1, segment, false));
+                }
+                Text3dSegment::Style { style, offset } => {
+                    let name = builder
+                        .dynamic_style
+                        .as_mut()
+                        .and_then(|f| f(*style))
+                        .filter(|name| is_valid_name(name))
+                        .ok_or(ParseError::NotSupported("dynamic style without a name"))?;
+                    let _ = write!(result, "{{@{}:", name.trim());
+                    conditionals.push((*offset + 1, segment, false));
+                }
+                Text3dSegment::Each { list, offset } => {
+                    let name = builder
+                        .list
+                        .as_mut()
+                        .and_then(|f| f(*list))
+                        .filter(|name| is_valid_name(name))
+                        .ok_or(ParseError::NotSupported("list without a name"))?;
+                    let _ = write!(result, "{{#each {}:", name.trim());
+                    conditionals.push((*offset + 1, segment, false));
+                }
+                Text3dSegment::Field(name) => {
+                    if !is_valid_name(name) || name.trim().starts_with(['?', '@', '#']) {
+                        return Err(ParseError::NotSupported("field without a name"));
+                    }
+                    let mut style = style.clone();
+                    let value = match style.format.take() {
+                        Some(format) => format!("{{{}:{format}}}", name.trim()),
+                        None => format!("{{{}}}", name.trim()),
+                    };
+                    write_style(style, &builder.styles, &mut styles, &mut markers)?;
+                    if styles.is_empty() {
+                        result.push_str(&value);
+                    } else {
+                        let _ = write!(result, "{{{}:{value}}}", styles.join(", "));
+                    }
+                }
+                Text3dSegment::Reveal(RevealCommand::Pause(seconds)) => {
+                    let _ = write!(result, "{{pause={seconds}}}");
+                }
+                Text3dSegment::Reveal(RevealCommand::Speed(multiplier)) => {
+                    let _ = write!(result, "{{speed={multiplier}}}");
+                }
+            }
+            for (remaining, _, _) in conditionals.iter_mut() {
+                *remaining -= 1;
+            }
+            while let Some((_, opened, is_else)) =
+                conditionals.pop_if(|(remaining, _, _)| *remaining == 0)
+            {
+                closed = (!is_else).then_some(opened);
+                result.push('}');
+            }
+        }
+        for _ in conditionals {
+            result.push('}');
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::MarkupBuilder;
+    use crate::{
+        misc::{Style, Weight},
+        parse_util::ConditionOutput,
+        ParseBuilder, ParseError, SegmentSize, SegmentStyle, Text3d, Text3dSegment,
+    };
+    use bevy::{
+        color::{ColorToPacked, Srgba},
+        ecs::entity::Entity,
+        math::Vec4,
+    };
+
+    const NAMES: [&str; 4] = ["hp", "alive", "hp_color", "items"];
+
+    fn entity(name: &str) -> Result<Entity, ParseError> {
+        NAMES
+            .iter()
+            .position(|x| *x == name)
+            .and_then(|i| Entity::from_raw_u32(i as u32))
+            .ok_or_else(|| ParseError::Custom(format!("Unknown name {name}.")))
+    }
+
+    fn name(entity: Entity) -> Option<String> {
+        (0..NAMES.len())
+            .find(|i| Entity::from_raw_u32(*i as u32) == Some(entity))
+            .map(|i| NAMES[i].to_string())
+    }
+
+    fn parse(text: &str) -> Text3d {
+        Text3d::parse(
+            text,
+            ParseBuilder::new()
+                .with_parse_style(|s| match s {
+                    "title" => Ok(SegmentStyle {
+                        size: Some(SegmentSize::Multiply(2.0)),
+                        ..Default::default()
+                    }),
+                    _ => Err(ParseError::Custom(format!("Unknown style {s}."))),
+                })
+                .with_parse_value(|s| {
+                    let segment = if let Some(s) = s.strip_prefix('@') {
+                        Text3dSegment::Style {
+                            style: entity(s)?,
+                            offset: 0,
+                        }
+                    } else if let Some(s) = s.strip_prefix("#each ") {
+                        Text3dSegment::Each {
+                            list: entity(s)?,
+                            offset: 0,
+                        }
+                    } else {
+                        Text3dSegment::Extract(entity(s)?)
+                    };
+                    Ok((segment, SegmentStyle::default()))
+                })
+                .with_parse_condition(|s| entity(s).map(ConditionOutput::Dynamic)),
+        )
+        .unwrap()
+    }
+
+    fn to_markup(text: &Text3d) -> String {
+        text.to_markup_with(
+            MarkupBuilder::new()
+                .with_style(
+                    "title",
+                    SegmentStyle {
+                        size: Some(SegmentSize::Multiply(2.0)),
+                        ..Default::default()
+                    },
+                )
+                .with_value(|segment| match segment {
+                    Text3dSegment::Extract(entity) => name(*entity),
+                    _ => None,
+                })
+                .with_condition(name)
+                .with_dynamic_style(name)
+                .with_list(name),
+        )
+        .unwrap()
+    }
+
+    fn assert_round_trip(source: &str) {
+        let text = parse(source);
+        let markup = to_markup(&text);
+        let parsed = parse(&markup);
+        assert_eq!(
+            format!("{:?}", text.segments),
+            format!("{:?}", parsed.segments),
+            "{source:?} -> {markup:?}"
+        );
+        assert_eq!(to_markup(&parsed), markup);
+    }
+
+    #[test]
+    fn round_trip_styles() {
+        assert_round_trip("Plain \\*text\\* with \\{escapes\\}.");
+        assert_round_trip("Deals **{#ff8000ff: fire}** *and* __more__ ~~damage~~.");
+        assert_round_trip("{title, italic: Heading} {bold, s-#000000ff, s-2: outlined}");
+        assert_round_trip("{margin-8, indent-4, spacing-2: paragraph} {wave-0.5: wavy}");
+    }
+
+    #[test]
+    fn round_trip_values() {
+        assert_round_trip("HP: {hp} / {bold: {hp:0.0}}");
+        assert_round_trip("{?alive: alive}{:else: dead} {?!alive: dead}");
+        assert_round_trip("{?alive: a|b}");
+        assert_round_trip("{?alive: a}{:else: b}{?alive: c}");
+        assert_round_trip("{?hp > 3 && !alive || alive: low}");
+        assert_round_trip("{@hp_color, bold: {hp}}");
+        assert_round_trip("{#each items: {name} x{count:,}\n}");
+        assert_round_trip("Well{pause=0.5}... {speed=2}I guess.");
+    }
+
+    #[test]
+    fn colors_are_quantized() {
+        let text = Text3d {
+            segments: vec![(
+                Text3dSegment::String("red".into()),
+                SegmentStyle {
+                    fill_color: Some(bevy::color::Srgba::new(0.5, 0.0, 0.0, 1.0)),
+                    ..Default::default()
+                },
+            )],
+            ..Default::default()
+        };
+        assert_eq!(to_markup(&text), "{#800000ff:red}");
+    }
+
+    #[test]
+    fn round_trip_text_values() {
+        let quantize = |color: Srgba| Srgba::from_u8_array(color.to_u8_array());
+        let styles = [
+            SegmentStyle {
+                fill_color: Some(Srgba::from_u8_array([255, 128, 0, 255])),
+                weight: Some(Weight::BOLD),
+                ..Default::default()
+            },
+            SegmentStyle {
+                stroke_color: Some(Srgba::from_u8_array([0, 0, 0, 128])),
+                stroke: NonZeroU32::new(2),
+                style: Some(Style::Italic),
+                size: Some(SegmentSize::Flat(18.0)),
+                ..Default::default()
+            },
+            SegmentStyle {
+                font: Some("Roboto".into()),
+                size: Some(SegmentSize::Multiply(1.5)),
+                underline: Some(true),
+                strikethrough: Some(true),
+                ..Default::default()
+            },
+            SegmentStyle {
+                magic_number: Some(1.0),
+                params: Some(Vec4::new(1.0, 2.0, 3.0, 4.0)),
+                ..Default::default()
+            },
+            SegmentStyle {
+                fill_color: Some(Srgba::new(0.3, 0.6, 0.9, 1.0)),
+                stroke_color: Some(Srgba::new(0.1, 0.2, 0.3, 0.4)),
+                ..Default::default()
+            },
+        ];
+        let text = Text3d {
+            segments: styles
+                .iter()
+                .enumerate()
+                .map(|(i, style)| (Text3dSegment::String(format!("s{i}")), style.clone()))
+                .collect(),
+            ..Default::default()
+        };
+        let parsed = Text3d::parse_raw(&text.to_markup().unwrap()).unwrap();
+        let expected: Vec<_> = text
+            .segments
+            .iter()
+            .map(|(segment, style)| {
+                let mut style = style.clone();
+                style.fill_color = style.fill_color.map(quantize);
+                style.stroke_color = style.stroke_color.map(quantize);
+                (segment.clone(), style)
+            })
+            .collect();
+        assert_eq!(format!("{:?}", parsed.segments), format!("{expected:?}"));
+        // Colors that are exact in 8 bits are not changed.
+        for (parsed, original) in parsed.segments.iter().zip(&text.segments).take(4) {
+            assert_eq!(parsed.1.fill_color, original.1.fill_color);
+            assert_eq!(parsed.1.stroke_color, original.1.stroke_color);
+        }
+    }
+}
@@ -1,14 +1,15 @@
 use bevy::{
     color::{ColorToComponents, LinearRgba, Srgba},
     image::Image,
-    math::{Rect, Vec2},
+    math::{Rect, Vec2, Vec4},
     mesh::{Indices, Mesh, VertexAttributeValues},
 };
 
 use crate::{
     export::{MeshExportCache, MeshExportCacheData, TextMeshFaceCategory},
     layers::Layer,
-    GlyphMeta, Text3dStyle,
+    modifier::SpanStarts,
+    GlyphMeta, GlyphModifiers, GlyphQuad, SegmentStyle, Text3dStyle,
 };
 
 // Take the allocation if possible but clear the data.
@@ -23,13 +24,8 @@ macro_rules! recycle_mesh {
     };
 }
 
-fn corners_z(rect: Rect, z: f32) -> [[f32; 3]; 4] {
-    [
-        [rect.min.x, rect.min.y, z],
-        [rect.max.x, rect.min.y, z],
-        [rect.min.x, rect.max.y, z],
-        [rect.max.x, rect.max.y, z],
-    ]
+fn corners_z(corners: [Vec2; 4], z: f32) -> [[f32; 3]; 4] {
+    corners.map(|v| [v.x, v.y, z])
 }
 
 fn corners(rect: Rect) -> [[f32; 2]; 4] {
@@ -41,6 +37,20 @@ fn corners(rect: Rect) -> [[f32; 2]; 4] {
     ]
 }
 
+/// Data of the glyph a quad belongs to.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GlyphData<'t> {
+    pub index: usize,
+    pub segment: usize,
+    pub line: usize,
+    pub word: usize,
+    pub char_index: usize,
+    pub advance: f32,
+    pub baseline: f32,
+    pub span_starts: SpanStarts,
+    pub style: &'t SegmentStyle,
+}
+
 pub(crate) struct ExtractedMesh<'t> {
     pub mesh: &'t mut Mesh,
     pub positions: Vec<[f32; 3]>,
@@ -49,14 +59,24 @@ pub(crate) struct ExtractedMesh<'t> {
     pub exports: Vec<MeshExportCache>,
     pub colors: Vec<[f32; 4]>,
     pub indices: Vec<u16>,
-    pub sort: &'t mut Vec<(Layer, [u16; 6])>,
+    /// Layer, glyph index and indices of each quad, sorted by layer on drop.
+    pub sort: &'t mut Vec<(Layer, usize, [u16; 6])>,
     pub layer_offset: f32,
+    pub modifiers: Option<&'t GlyphModifiers>,
+    pub time: f32,
+    pub age: f32,
+    /// Glyph index and line index of each quad.
+    pub quads: Vec<(usize, usize)>,
+    /// Baseline of each quad's line.
+    pub baselines: Vec<f32>,
+    /// Quads of glyphs with an index not less than this are not drawn.
+    pub revealed: usize,
 }
 
 impl<'t> ExtractedMesh<'t> {
     pub fn new(
         mesh: &'t mut Mesh,
-        sort_buffer: &'t mut Vec<(Layer, [u16; 6])>,
+        sort_buffer: &'t mut Vec<(Layer, usize, [u16; 6])>,
         style: &Text3dStyle,
     ) -> Self {
         sort_buffer.clear();
@@ -82,9 +102,28 @@ impl<'t> ExtractedMesh<'t> {
             indices,
             sort: sort_buffer,
             layer_offset: style.layer_offset,
+            modifiers: None,
+            time: 0.0,
+            age: 0.0,
+            quads: Vec::new(),
+            baselines: Vec::new(),
+            revealed: usize::MAX,
         }
     }
 
+    /// `age` is the number of seconds since the text started, see [`GlyphQuad::age`].
+    pub fn with_modifiers(
+        mut self,
+        modifiers: Option<&'t GlyphModifiers>,
+        time: f32,
+        age: f32,
+    ) -> Self {
+        self.modifiers = modifiers;
+        self.time = time;
+        self.age = age;
+        self
+    }
+
     pub fn pixel_to_uv(&mut self, image: &Image) {
         let inv_width = 1.0 / image.width() as f32;
         let inv_height = 1.0 / image.height() as f32;
@@ -95,9 +134,12 @@ impl<'t> ExtractedMesh<'t> {
         });
     }
 
-    pub fn post_process(&mut self, min: Vec2, dimension: Vec2) {
+    /// `line_glyph_counts` is the number of glyphs in each line.
+    pub fn post_process(&mut self, min: Vec2, dimension: Vec2, line_glyph_counts: &[usize]) {
         let w = dimension.x.max(0.0001);
         let h = dimension.y.max(0.0001);
+        let glyph_count = line_glyph_counts.iter().sum::<usize>().max(1) as f32;
+        let quads = self.quads.iter().flat_map(|x| [*x; 4]);
         for item in &mut self.exports {
             for (idx, meta_type) in item.entry.iter() {
                 match meta_type {
@@ -113,6 +155,23 @@ impl<'t> ExtractedMesh<'t> {
                                 arr[idx] = (position[1] - min.y) / h;
                             })
                     }
+                    GlyphMeta::GlyphCountInLine => {
+                        item.data
+                            .for_each_zipped_mut(quads.clone(), |arr, (_, line)| {
+                                arr[idx] = line_glyph_counts.get(line).copied().unwrap_or(0) as f32;
+                            })
+                    }
+                    GlyphMeta::LineCount => {
+                        item.data.for_each_zipped_mut(quads.clone(), |arr, _| {
+                            arr[idx] = line_glyph_counts.len() as f32;
+                        })
+                    }
+                    GlyphMeta::IndexFraction => {
+                        item.data
+                            .for_each_zipped_mut(quads.clone(), |arr, (index, _)| {
+                                arr[idx] = index as f32 / glyph_count;
+                            })
+                    }
                     _ => (),
                 }
             }
@@ -126,6 +185,39 @@ impl<'t> ExtractedMesh<'t> {
             *x = v.x;
             *y = v.y;
         }
+        for y in &mut self.baselines {
+            let mut v = Vec2::new(0.0, *y);
+            f(&mut v);
+            *y = v.y;
+        }
+    }
+
+    /// Write exports that depend on final positions, must be called after `translate`.
+    pub fn post_translate(&mut self) {
+        fn corner(quad: &[[f32; 3]], i: usize) -> Vec2 {
+            Vec2::new(quad[i][0], quad[i][1])
+        }
+        for item in &mut self.exports {
+            for (idx, meta_type) in item.entry.iter() {
+                let f: fn(&[[f32; 3]], f32) -> f32 = match meta_type {
+                    GlyphMeta::GlyphCenterX => |q, _| (q[0][0] + q[1][0] + q[2][0] + q[3][0]) / 4.0,
+                    GlyphMeta::GlyphCenterY => |q, _| (q[0][1] + q[1][1] + q[2][1] + q[3][1]) / 4.0,
+                    GlyphMeta::GlyphWidth => |q, _| corner(q, 0).distance(corner(q, 1)),
+                    GlyphMeta::GlyphHeight => |q, _| corner(q, 0).distance(corner(q, 2)),
+                    GlyphMeta::Baseline => |_, baseline| baseline,
+                    _ => continue,
+                };
+                let quads = self
+                    .positions
+                    .chunks_exact(4)
+                    .zip(self.baselines.iter().copied())
+                    .flat_map(|x| [x; 4]);
+                item.data
+                    .for_each_zipped_mut(quads, |arr, (quad, baseline)| {
+                        arr[idx] = f(quad, baseline);
+                    });
+            }
+        }
     }
 
     pub fn cache_rectangle(
@@ -135,9 +227,7 @@ impl<'t> ExtractedMesh<'t> {
         texture: Rect,
         color: Srgba,
         layer: Layer,
-        real_index: usize,
-        advance: f32,
-        magic_number: f32,
+        glyph: GlyphData,
         category: TextMeshFaceCategory,
         styling: &Text3dStyle,
         rng: &mut fastrand::Rng,
@@ -147,16 +237,7 @@ impl<'t> ExtractedMesh<'t> {
             max: base + dimension,
         };
         self.cache_rectangle2(
-            mesh_rect,
-            texture,
-            color,
-            layer,
-            real_index,
-            advance,
-            magic_number,
-            category,
-            styling,
-            rng,
+            mesh_rect, texture, color, layer, glyph, category, styling, rng,
         );
     }
 
@@ -166,21 +247,46 @@ impl<'t> ExtractedMesh<'t> {
         texture: Rect,
         color: Srgba,
         layer: Layer,
-        real_index: usize,
-        advance: f32,
-        magic_number: f32,
+        glyph: GlyphData,
         category: TextMeshFaceCategory,
         styling: &Text3dStyle,
         rng: &mut fastrand::Rng,
     ) {
+        let GlyphData {
+            index: real_index,
+            advance,
+            ..
+        } = glyph;
+        let magic_number = glyph.style.magic_number.unwrap_or(0.);
+        let effects = glyph.style.effects;
+        let params = glyph.style.params.unwrap_or(Vec4::ZERO);
+
+        let mut quad = GlyphQuad {
+            index: real_index,
+            segment: glyph.segment,
+            style: glyph.style,
+            advance: (advance + mesh_rect.width() / 2.0) / styling.size,
+            category: category as u8,
+            em: styling.size,
+            corners: GlyphQuad::corners_of(mesh_rect),
+            color,
+            age: self.age,
+            span_starts: glyph.span_starts,
+        };
+        if let Some(modifiers) = self.modifiers {
+            modifiers.modify(self.time, &mut quad);
+        }
+
         let i = self.positions.len() as u16;
+        self.quads.push((real_index, glyph.line));
+        self.baselines.push(glyph.baseline);
         self.sort
-            .push((layer, [i, i + 1, i + 2, i + 1, i + 3, i + 2]));
+            .push((layer, real_index, [i, i + 1, i + 2, i + 1, i + 3, i + 2]));
 
-        self.positions.extend(corners_z(mesh_rect, 0.));
+        self.positions.extend(corners_z(quad.corners, 0.));
         self.normals.extend([[0., 0., 1.]; 4]);
         self.colors
-            .extend([LinearRgba::from(color).to_f32_array(); 4]);
+            .extend([LinearRgba::from(quad.color).to_f32_array(); 4]);
 
         // First we cache the pixel position since the texture may be resized.
         self.uv0.extend(corners(texture));
@@ -190,7 +296,7 @@ impl<'t> ExtractedMesh<'t> {
             for (index, meta) in item.entry.iter() {
                 let random_per_glyph = rng.f32();
                 item.data
-                    .with_inserted_quad(index, |vertex, item| match meta {
+                    .with_inserted_quad(index, |vertex, item| match &meta {
                         GlyphMeta::Index => {
                             *item = real_index as f32;
                         }
@@ -211,15 +317,57 @@ impl<'t> ExtractedMesh<'t> {
                         GlyphMeta::RandomPerVertex => *item = rng.f32(),
                         GlyphMeta::GlyphUvX => *item = if vertex & 1 == 0 { 0.0 } else { 1.0 },
                         GlyphMeta::GlyphUvY => *item = if vertex < 2 { 0.0 } else { 1.0 },
-                        GlyphMeta::UvX => (),
-                        GlyphMeta::UvY => (),
+                        GlyphMeta::ParamX => *item = params.x,
+                        GlyphMeta::ParamY => *item = params.y,
+                        GlyphMeta::ParamZ => *item = params.z,
+                        GlyphMeta::ParamW => *item = params.w,
+                        GlyphMeta::NamedParam(key) => {
+                            *item = glyph.style.named_param(key.as_str()).unwrap_or(0.);
+                        }
+                        GlyphMeta::LineIndex => *item = glyph.line as f32,
+                        GlyphMeta::WordIndex => *item = glyph.word as f32,
+                        GlyphMeta::SegmentIndex => *item = glyph.segment as f32,
+                        GlyphMeta::CharIndex => *item = glyph.char_index as f32,
+                        // Written in `post_process`.
+                        GlyphMeta::UvX
+                        | GlyphMeta::UvY
+                        | GlyphMeta::GlyphCountInLine
+                        | GlyphMeta::LineCount
+                        | GlyphMeta::IndexFraction => (),
+                        // Written in `post_translate`.
+                        GlyphMeta::GlyphCenterX
+                        | GlyphMeta::GlyphCenterY
+                        | GlyphMeta::GlyphWidth
+                        | GlyphMeta::GlyphHeight
+                        | GlyphMeta::Baseline => (),
                         GlyphMeta::Category => *item = category.as_value(),
+                        GlyphMeta::EffectMask => *item = effects.mask() as f32,
+                        GlyphMeta::EffectStrength(effect) => *item = effects.strength(*effect),
                     });
             }
         }
     }
 }
 
+/// Write indices of quads with a glyph index less than `revealed`.
+///
+/// Writes a degenerate triangle if nothing is revealed, since empty buffers panic on some platforms.
+pub(crate) fn write_revealed_indices(
+    quads: impl IntoIterator<Item = (usize, [u16; 6])>,
+    revealed: usize,
+    indices: &mut Vec<u16>,
+) {
+    indices.extend(
+        quads
+            .into_iter()
+            .filter(|(glyph, _)| *glyph < revealed)
+            .flat_map(|(_, v)| v),
+    );
+    if indices.is_empty() {
+        indices.extend([0, 0, 0]);
+    }
+}
+
 impl Drop for ExtractedMesh<'_> {
     fn drop(&mut self) {
         use std::mem::take;
@@ -227,7 +375,7 @@ impl Drop for ExtractedMesh<'_> {
         if self.layer_offset != 0.0 {
             let mut offset = 0.0;
             let mut layer = self.sort.last().map(|x| x.0).unwrap_or(Layer::None);
-            for (l, entry) in self.sort.iter().rev() {
+            for (l, _, entry) in self.sort.iter().rev() {
                 if layer != *l {
                     offset -= self.layer_offset;
                     layer = *l;
@@ -239,8 +387,12 @@ impl Drop for ExtractedMesh<'_> {
                 }
             }
         }
-        self.indices
-            .extend(self.sort.drain(..).flat_map(|(_, v)| v));
+        // Kept in `sort` so the indices can be written again when more glyphs are revealed.
+        write_revealed_indices(
+            self.sort.iter().map(|(_, glyph, v)| (*glyph, *v)),
+            self.revealed,
+            &mut self.indices,
+        );
         if !self.positions.is_empty() {
             self.mesh
                 .insert_attribute(Mesh::ATTRIBUTE_POSITION, take(&mut self.positions));
@@ -251,7 +403,7 @@ impl Drop for ExtractedMesh<'_> {
             self.mesh
                 .insert_attribute(Mesh::ATTRIBUTE_UV_0, take(&mut self.uv0));
             for entry in take(&mut self.exports) {
-                self.mesh.insert_attribute(entry.entry.id, entry.data);
+                self.mesh.insert_attribute(entry.attribute, entry.data);
             }
             self.mesh
                 .insert_indices(Indices::U16(take(&mut self.indices)));
@@ -268,19 +420,19 @@ impl Drop for ExtractedMesh<'_> {
             for entry in take(&mut self.exports) {
                 match entry.data {
                     MeshExportCacheData::F1(_) => {
-                        self.mesh.insert_attribute(entry.entry.id, vec![0.0; 3]);
+                        self.mesh.insert_attribute(entry.attribute, vec![0.0; 3]);
                     }
                     MeshExportCacheData::F2(_) => {
                         self.mesh
-                            .insert_attribute(entry.entry.id, vec![[0.0; 2]; 3]);
+                            .insert_attribute(entry.attribute, vec![[0.0; 2]; 3]);
                     }
                     MeshExportCacheData::F3(_) => {
                         self.mesh
-                            .insert_attribute(entry.entry.id, vec![[0.0; 3]; 3]);
+                            .insert_attribute(entry.attribute, vec![[0.0; 3]; 3]);
                     }
                     MeshExportCacheData::F4(_) => {
                         self.mesh
-                            .insert_attribute(entry.entry.id, vec![[0.0; 4]; 3]);
+                            .insert_attribute(entry.attribute, vec![[0.0; 4]; 3]);
                     }
                 }
             }
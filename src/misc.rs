@@ -2,18 +2,23 @@ use bevy::{
     ecs::component::Component,
     math::{IVec2, Vec2},
 };
-use cosmic_text::{Style as CosmicStyle, Weight as CosmicWeight};
+use cosmic_text::{Buffer, Style as CosmicStyle, Weight as CosmicWeight, Wrap};
 use std::ops::{Deref, DerefMut};
 
+use crate::ParagraphStyle;
+
 #[cfg(feature = "reflect")]
 use bevy::{
     ecs::reflect::ReflectComponent,
     prelude::{Reflect, ReflectDefault},
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// Horizontal align of text.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TextAlign {
     #[default]
     Left,
@@ -31,10 +36,94 @@ impl TextAlign {
     }
 }
 
+/// Line breaking of text wider than [`Text3dBounds::width`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TextWrap {
+    /// Only break at line breaks.
+    None,
+    /// Break between words, long words overflow.
+    Word,
+    /// Break between glyphs.
+    Glyph,
+    /// Break between words, or between glyphs if a word does not fit in a line.
+    #[default]
+    WordOrGlyph,
+}
+
+impl From<TextWrap> for Wrap {
+    fn from(val: TextWrap) -> Self {
+        match val {
+            TextWrap::None => Wrap::None,
+            TextWrap::Word => Wrap::Word,
+            TextWrap::Glyph => Wrap::Glyph,
+            TextWrap::WordOrGlyph => Wrap::WordOrGlyph,
+        }
+    }
+}
+
+/// Alignment of the text between a tab and the next tab or the end of the line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TabAlign {
+    /// Text starts at the tab stop.
+    #[default]
+    Left,
+    /// Text ends at the tab stop.
+    Right,
+    /// Text is centered on the tab stop.
+    Center,
+    /// The first `.` is placed at the tab stop, text without `.` ends at the tab stop.
+    Decimal,
+}
+
+/// A tab stop in [`Text3dStyle::tab_stops`](crate::Text3dStyle::tab_stops).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TabStop {
+    /// Distance from the start of the line, in multiples of [`Text3dStyle::size`](crate::Text3dStyle::size).
+    pub position: f32,
+    pub align: TabAlign,
+}
+
+impl TabStop {
+    pub const fn left(position: f32) -> Self {
+        TabStop {
+            position,
+            align: TabAlign::Left,
+        }
+    }
+
+    pub const fn right(position: f32) -> Self {
+        TabStop {
+            position,
+            align: TabAlign::Right,
+        }
+    }
+
+    pub const fn center(position: f32) -> Self {
+        TabStop {
+            position,
+            align: TabAlign::Center,
+        }
+    }
+
+    pub const fn decimal(position: f32) -> Self {
+        TabStop {
+            position,
+            align: TabAlign::Decimal,
+        }
+    }
+}
+
 /// Determines the maximum width of rendered text, by default infinite.
 #[derive(Debug, Component)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Component, Default))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Text3dBounds {
     pub width: f32,
 }
@@ -48,6 +137,7 @@ impl Default for Text3dBounds {
 /// Anchor of a text block, usually in `(-0.5, -0.5)..=(0.5, 0.5)`.
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TextAnchor(pub Vec2);
 
 impl Deref for TextAnchor {
@@ -91,11 +181,28 @@ pub struct Text3dDimensionOut {
     pub dimension: Vec2,
     pub(crate) atlas_dimension: IVec2,
     pub(crate) initialized: bool,
+    /// Elapsed seconds of `Time` when the text was first rendered or [`Text3d`](crate::Text3d) last changed.
+    pub(crate) started: Option<f32>,
+    /// Layout of the last frame, lines that did not change are not shaped again.
+    ///
+    /// This only saves shaping, when anything changes the segments are still split into lines
+    /// with `set_rich_text` and the whole mesh is rebuilt from the layout.
+    #[cfg_attr(feature = "reflect", reflect(ignore))]
+    pub(crate) buffer: Option<Buffer>,
+    /// Paragraph styles of the lines in `buffer`.
+    #[cfg_attr(feature = "reflect", reflect(ignore))]
+    pub(crate) paragraphs: Vec<ParagraphStyle>,
+    /// Number of lines laid out in the last update, paint only changes lay out no lines.
+    pub(crate) laid_out_lines: usize,
+    /// Glyph index and indices of each quad in the mesh, in draw order.
+    #[cfg_attr(feature = "reflect", reflect(ignore))]
+    pub(crate) quads: Vec<(usize, [u16; 6])>,
 }
 
 /// Allows italic or oblique faces to be selected.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Style {
     /// A face that is neither italic not obliqued.
     Normal,
@@ -135,6 +242,7 @@ impl From<CosmicStyle> for Style {
 /// Specifies the weight of glyphs in the font, their degree of blackness or stroke thickness.
 #[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Debug, Hash)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Weight(pub u16);
 
 impl Default for Weight {
@@ -184,6 +292,7 @@ impl From<CosmicWeight> for Weight {
 /// Usually [`StrokeJoin::Round`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum StrokeJoin {
     #[default]
     Round,
@@ -200,3 +309,111 @@ impl From<StrokeJoin> for tiny_skia::LineJoin {
         }
     }
 }
+
+/// An animation effect applied to a span of text, i.e. `{wave: text}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[repr(u8)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SpanEffect {
+    /// `{wave: text}`, bit `1`.
+    Wave = 0,
+    /// `{shake: text}`, bit `2`.
+    Shake = 1,
+    /// `{fade: text}`, bit `4`.
+    Fade = 2,
+}
+
+impl SpanEffect {
+    pub const ALL: [SpanEffect; 3] = [SpanEffect::Wave, SpanEffect::Shake, SpanEffect::Fade];
+
+    /// Returns the bit of this effect in [`SpanEffects::mask`].
+    pub const fn bit(self) -> u32 {
+        1 << self as u8
+    }
+
+    /// Name of the effect used in markup.
+    pub const fn name(self) -> &'static str {
+        match self {
+            SpanEffect::Wave => "wave",
+            SpanEffect::Shake => "shake",
+            SpanEffect::Fade => "fade",
+        }
+    }
+
+    /// Parse the name of the effect used in markup.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "wave" => Some(SpanEffect::Wave),
+            "shake" => Some(SpanEffect::Shake),
+            "fade" => Some(SpanEffect::Fade),
+            _ => None,
+        }
+    }
+}
+
+/// A set of [`SpanEffect`]s and their strengths.
+///
+/// Can be exported via [`GlyphMeta::EffectMask`](crate::GlyphMeta::EffectMask) and
+/// [`GlyphMeta::EffectStrength`](crate::GlyphMeta::EffectStrength).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SpanEffects {
+    mask: u32,
+    strength: [f32; SpanEffect::ALL.len()],
+}
+
+impl SpanEffects {
+    pub const NONE: SpanEffects = SpanEffects {
+        mask: 0,
+        strength: [0.0; SpanEffect::ALL.len()],
+    };
+
+    /// Create a set with a single effect.
+    pub fn new(effect: SpanEffect, strength: f32) -> Self {
+        Self::NONE.with(effect, strength)
+    }
+
+    /// Add an effect or replace its strength.
+    pub fn with(mut self, effect: SpanEffect, strength: f32) -> Self {
+        self.mask |= effect.bit();
+        self.strength[effect as usize] = strength;
+        self
+    }
+
+    /// Returns true if no effect is set.
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
+    /// Returns true if an effect is set.
+    pub fn contains(&self, effect: SpanEffect) -> bool {
+        self.mask & effect.bit() != 0
+    }
+
+    /// Returns the strength of an effect, or `0.0` if not set.
+    pub fn strength(&self, effect: SpanEffect) -> f32 {
+        if self.contains(effect) {
+            self.strength[effect as usize]
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns a bitset of [`SpanEffect::bit`]s.
+    pub fn mask(&self) -> u32 {
+        self.mask
+    }
+
+    /// Union of two sets, strengths in `other` take priority.
+    pub fn join(&self, other: Self) -> Self {
+        let mut result = *self;
+        for effect in SpanEffect::ALL {
+            if other.contains(effect) {
+                result = result.with(effect, other.strength[effect as usize]);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,415 @@
+use std::{fmt::Debug, sync::Arc};
+
+use bevy::{
+    color::Srgba,
+    ecs::component::Component,
+    math::{Rect, Vec2},
+};
+
+use crate::{SegmentStyle, SpanEffect};
+
+/// Index of the first glyph of the span of each [`SpanEffect`], indexed by `SpanEffect as usize`.
+pub(crate) type SpanStarts = [usize; SpanEffect::ALL.len()];
+
+/// A quad about to be written to the mesh of a [`Text3d`](crate::Text3d).
+///
+/// Positions are in local text space before anchoring and [`Text3dStyle::world_scale`](crate::Text3dStyle::world_scale)
+/// are applied, where `1 em` equals [`GlyphQuad::em`].
+#[derive(Debug, Clone, Copy)]
+pub struct GlyphQuad<'t> {
+    /// Left to right count of the glyph, same as [`GlyphMeta::Index`](crate::GlyphMeta::Index).
+    pub index: usize,
+    /// Index of the segment in [`Text3d::segments`](crate::Text3d::segments).
+    pub segment: usize,
+    /// Style of the segment this glyph belongs to.
+    pub style: &'t SegmentStyle,
+    /// x position in `em` of the center of the glyph as if the text is rendered in a single line,
+    /// same as [`GlyphMeta::PerGlyphAdvance`](crate::GlyphMeta::PerGlyphAdvance).
+    pub advance: f32,
+    /// Returns `0` for fill, `1` for stroke, `2` for shadow, `3` for image/emoji,
+    /// same as [`GlyphMeta::Category`](crate::GlyphMeta::Category).
+    pub category: u8,
+    /// Size of `em` in local text space, i.e. [`Text3dStyle::size`](crate::Text3dStyle::size).
+    pub em: f32,
+    /// Corners of the quad, in order bottom left, bottom right, top left, top right.
+    pub corners: [Vec2; 4],
+    /// Vertex color of the quad.
+    pub color: Srgba,
+    /// Seconds since the text was first rendered or [`Text3d`](crate::Text3d) last changed.
+    pub age: f32,
+    pub(crate) span_starts: SpanStarts,
+}
+
+impl GlyphQuad<'_> {
+    pub(crate) fn corners_of(rect: Rect) -> [Vec2; 4] {
+        [
+            rect.min,
+            Vec2::new(rect.max.x, rect.min.y),
+            Vec2::new(rect.min.x, rect.max.y),
+            rect.max,
+        ]
+    }
+
+    /// Returns the center of the quad.
+    pub fn center(&self) -> Vec2 {
+        self.corners.iter().copied().sum::<Vec2>() / 4.0
+    }
+
+    /// Moves the quad by an offset in local text space.
+    pub fn translate(&mut self, offset: Vec2) {
+        self.corners.iter_mut().for_each(|x| *x += offset);
+    }
+
+    /// Rotates the quad counterclockwise around its center, in radians.
+    pub fn rotate(&mut self, angle: f32) {
+        let center = self.center();
+        let rotation = Vec2::from_angle(angle);
+        self.corners
+            .iter_mut()
+            .for_each(|x| *x = center + rotation.rotate(*x - center));
+    }
+
+    /// Returns the index of the glyph counted from the first glyph of its span of `effect`,
+    /// i.e. `0` for `b` in `a{fade: bc}`.
+    ///
+    /// Returns [`GlyphQuad::index`] if the glyph does not have the effect.
+    pub fn span_index(&self, effect: SpanEffect) -> usize {
+        if self.style.effects.contains(effect) {
+            self.index - self.span_starts[effect as usize]
+        } else {
+            self.index
+        }
+    }
+
+    /// Scales the quad around its center.
+    pub fn scale(&mut self, scale: Vec2) {
+        let center = self.center();
+        self.corners
+            .iter_mut()
+            .for_each(|x| *x = center + (*x - center) * scale);
+    }
+}
+
+/// A CPU side effect applied to each glyph of a [`Text3d`](crate::Text3d) before it is written to the mesh.
+///
+/// Unlike shader based effects, this works with stock materials like `StandardMaterial` and `ColorMaterial`.
+pub trait GlyphModifier: Debug + Send + Sync + 'static {
+    /// If true, the text is rebuilt every frame.
+    ///
+    /// Return `false` for effects that does not depend on `time`.
+    fn is_animated(&self) -> bool {
+        true
+    }
+
+    /// Modify a quad, `time` is the elapsed seconds of [`Time`](bevy::time::Time).
+    fn modify(&self, time: f32, quad: &mut GlyphQuad);
+}
+
+/// A list of [`GlyphModifier`]s applied in order to a [`Text3d`](crate::Text3d).
+///
+/// # Performance
+///
+/// If any modifier is animated, the text is rebuilt every frame.
+#[derive(Debug, Component, Default, Clone)]
+pub struct GlyphModifiers(pub Vec<Arc<dyn GlyphModifier>>);
+
+impl GlyphModifiers {
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Add a modifier to the end of the list.
+    pub fn with(mut self, modifier: impl GlyphModifier) -> Self {
+        self.0.push(Arc::new(modifier));
+        self
+    }
+
+    /// Returns true if any modifier is animated.
+    pub fn is_animated(&self) -> bool {
+        self.0.iter().any(|x| x.is_animated())
+    }
+
+    pub(crate) fn modify(&self, time: f32, quad: &mut GlyphQuad) {
+        for modifier in &self.0 {
+            modifier.modify(time, quad);
+        }
+    }
+}
+
+impl<T: GlyphModifier> From<T> for GlyphModifiers {
+    fn from(value: T) -> Self {
+        GlyphModifiers(vec![Arc::new(value)])
+    }
+}
+
+/// Moves glyphs up and down in a sine wave.
+#[derive(Debug, Clone, Copy)]
+pub struct Wave {
+    /// Height of the wave in `em`.
+    pub amplitude: f32,
+    /// Number of waves per `em` of advance.
+    pub frequency: f32,
+    /// Number of cycles per second.
+    pub speed: f32,
+}
+
+impl Default for Wave {
+    fn default() -> Self {
+        Self {
+            amplitude: 0.1,
+            frequency: 0.5,
+            speed: 1.0,
+        }
+    }
+}
+
+impl GlyphModifier for Wave {
+    fn modify(&self, time: f32, quad: &mut GlyphQuad) {
+        let phase = (quad.advance * self.frequency + time * self.speed) * std::f32::consts::TAU;
+        quad.translate(Vec2::new(0.0, phase.sin() * self.amplitude * quad.em));
+    }
+}
+
+/// Randomly offsets glyphs.
+#[derive(Debug, Clone, Copy)]
+pub struct Shake {
+    /// Maximum offset in `em`.
+    pub intensity: f32,
+    /// Number of times the offset changes per second.
+    pub speed: f32,
+}
+
+impl Default for Shake {
+    fn default() -> Self {
+        Self {
+            intensity: 0.05,
+            speed: 20.0,
+        }
+    }
+}
+
+impl GlyphModifier for Shake {
+    fn modify(&self, time: f32, quad: &mut GlyphQuad) {
+        let tick = (time * self.speed).floor() as u64;
+        let mut rng =
+            fastrand::Rng::with_seed(tick.wrapping_mul(0x9E3779B97F4A7C15) ^ quad.index as u64);
+        let offset = Vec2::new(rng.f32() * 2.0 - 1.0, rng.f32() * 2.0 - 1.0);
+        quad.translate(offset * self.intensity * quad.em);
+    }
+}
+
+/// Fades in glyphs one by one from left to right.
+///
+/// Fading starts when the text is first rendered and restarts when [`Text3d`](crate::Text3d) changes.
+#[derive(Debug, Clone, Copy)]
+pub struct Fade {
+    /// Delay in seconds before the first glyph starts fading in.
+    pub start: f32,
+    /// Number of glyphs faded in per second.
+    pub speed: f32,
+}
+
+impl Default for Fade {
+    fn default() -> Self {
+        Self {
+            start: 0.0,
+            speed: 20.0,
+        }
+    }
+}
+
+impl Fade {
+    fn fade(&self, quad: &mut GlyphQuad, index: usize) {
+        let alpha = ((quad.age - self.start) * self.speed - index as f32).clamp(0.0, 1.0);
+        quad.color.alpha *= alpha;
+    }
+}
+
+impl GlyphModifier for Fade {
+    fn modify(&self, _: f32, quad: &mut GlyphQuad) {
+        self.fade(quad, quad.index);
+    }
+}
+
+/// Applies [`Wave`], [`Shake`] and [`Fade`] to glyphs with the corresponding
+/// [`SpanEffect`] in [`SegmentStyle::effects`], i.e. `{wave: text}`.
+///
+/// Strength of the effect multiplies `amplitude` and `intensity` for [`Wave`] and [`Shake`],
+/// and `speed` for [`Fade`]. Each `{fade: ..}` span fades in from its own first glyph.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpanEffectModifier {
+    pub wave: Wave,
+    pub shake: Shake,
+    pub fade: Fade,
+}
+
+impl GlyphModifier for SpanEffectModifier {
+    fn modify(&self, time: f32, quad: &mut GlyphQuad) {
+        let effects = quad.style.effects;
+        if effects.is_empty() {
+            return;
+        }
+        if effects.contains(SpanEffect::Wave) {
+            Wave {
+                amplitude: self.wave.amplitude * effects.strength(SpanEffect::Wave),
+                ..self.wave
+            }
+            .modify(time, quad);
+        }
+        if effects.contains(SpanEffect::Shake) {
+            Shake {
+                intensity: self.shake.intensity * effects.strength(SpanEffect::Shake),
+                ..self.shake
+            }
+            .modify(time, quad);
+        }
+        if effects.contains(SpanEffect::Fade) {
+            Fade {
+                speed: self.fade.speed * effects.strength(SpanEffect::Fade),
+                ..self.fade
+            }
+            .fade(quad, quad.span_index(SpanEffect::Fade));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        color::Srgba,
+        math::{Rect, Vec2},
+    };
+
+    use super::{Fade, GlyphModifier, GlyphQuad, Shake, SpanEffectModifier, SpanStarts, Wave};
+    use crate::{SegmentStyle, SpanEffect, SpanEffects};
+
+    fn quad(style: &SegmentStyle, index: usize, span_starts: SpanStarts) -> GlyphQuad<'_> {
+        GlyphQuad {
+            index,
+            segment: 0,
+            style,
+            advance: 0.25,
+            category: 0,
+            em: 10.0,
+            corners: GlyphQuad::corners_of(Rect::new(0.0, 0.0, 4.0, 8.0)),
+            color: Srgba::new(1.0, 0.5, 0.25, 1.0),
+            age: 0.5,
+            span_starts,
+        }
+    }
+
+    fn offset(before: &GlyphQuad, after: &GlyphQuad) -> Vec2 {
+        after.center() - before.center()
+    }
+
+    #[test]
+    fn wave_moves_glyphs_vertically() {
+        let style = SegmentStyle::default();
+        let wave = Wave {
+            amplitude: 0.1,
+            frequency: 1.0,
+            speed: 0.0,
+        };
+        let before = quad(&style, 0, [0; 3]);
+        let mut after = before;
+        // Phase is `0.25` of a cycle, the peak of the sine wave.
+        wave.modify(0.0, &mut after);
+        let offset = offset(&before, &after);
+        assert_eq!(offset.x, 0.0);
+        assert!((offset.y - 1.0).abs() < 1e-5);
+        assert_eq!(after.color, before.color);
+    }
+
+    #[test]
+    fn shake_is_bounded_and_stable_within_a_tick() {
+        let style = SegmentStyle::default();
+        let shake = Shake {
+            intensity: 0.1,
+            speed: 10.0,
+        };
+        let before = quad(&style, 3, [0; 3]);
+        let mut a = before;
+        let mut b = before;
+        shake.modify(1.01, &mut a);
+        shake.modify(1.09, &mut b);
+        let offset = offset(&before, &a);
+        assert!(offset.x.abs() <= 1.0 && offset.y.abs() <= 1.0);
+        assert_eq!(a.corners, b.corners);
+        assert_eq!(a.color, before.color);
+    }
+
+    #[test]
+    fn fade_scales_alpha_by_index() {
+        let style = SegmentStyle::default();
+        let fade = Fade {
+            start: 0.0,
+            speed: 4.0,
+        };
+        // `age * speed` is `2.0`, glyph 0 and 1 are opaque, 3 is transparent.
+        let mut alphas = [0.0; 4];
+        for (index, alpha) in alphas.iter_mut().enumerate() {
+            let before = quad(&style, index, [0; 3]);
+            let mut after = before;
+            fade.modify(0.0, &mut after);
+            assert_eq!(after.corners, before.corners);
+            assert_eq!(after.color.red, before.color.red);
+            *alpha = after.color.alpha;
+        }
+        assert_eq!(alphas, [1.0, 1.0, 0.0, 0.0]);
+
+        let mut half = quad(&style, 1, [0; 3]);
+        half.age = 0.375;
+        fade.modify(0.0, &mut half);
+        assert!((half.color.alpha - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn span_effect_modifier_only_applies_effects_of_the_span() {
+        let modifier = SpanEffectModifier {
+            wave: Wave {
+                amplitude: 0.1,
+                frequency: 1.0,
+                speed: 0.0,
+            },
+            shake: Shake::default(),
+            fade: Fade {
+                start: 0.0,
+                speed: 4.0,
+            },
+        };
+
+        let plain = SegmentStyle::default();
+        let before = quad(&plain, 5, [0; 3]);
+        let mut after = before;
+        modifier.modify(0.0, &mut after);
+        assert_eq!(after.corners, before.corners);
+        assert_eq!(after.color, before.color);
+
+        let wave = SegmentStyle {
+            effects: SpanEffects::new(SpanEffect::Wave, 2.0),
+            ..Default::default()
+        };
+        let before = quad(&wave, 5, [0; 3]);
+        let mut after = before;
+        modifier.modify(0.0, &mut after);
+        let offset = offset(&before, &after);
+        assert_eq!(offset.x, 0.0);
+        assert!((offset.y - 2.0).abs() < 1e-5);
+        assert_eq!(after.color, before.color);
+
+        // Glyph 5 is the second glyph of a fade span starting at 4.
+        let fade = SegmentStyle {
+            effects: SpanEffects::new(SpanEffect::Fade, 0.5),
+            ..Default::default()
+        };
+        let before = quad(&fade, 5, [0, 0, 4]);
+        let mut after = before;
+        modifier.modify(0.0, &mut after);
+        assert_eq!(after.corners, before.corners);
+        assert_eq!(after.color.alpha, 0.0);
+        after = before;
+        after.age = 1.0;
+        modifier.modify(0.0, &mut after);
+        assert_eq!(after.color.alpha, 1.0);
+    }
+}
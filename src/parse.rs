@@ -1,22 +1,33 @@
-use std::{iter::repeat_n, num::NonZeroU32, str::FromStr};
+use std::{num::NonZeroU32, ops::Range, str::FromStr};
+
+use bevy::math::Vec4;
 
 use crate::{
     color_table::parse_color,
+    condition::parse_condition_expr,
     misc::{Style, Weight},
     parse_util::{
-        ConditionOutput, Flip, ParseBuilder, ParseConditionFn, ParseError, ParseStyleFn,
-        ParseValueFn,
+        Flip, ParseBuilder, ParseConditionFn, ParseError, ParseStyleFn, ParseValueFn, ParseWarning,
+        SpannedParseError, WhitespaceMode,
     },
-    SegmentSize, SegmentStyle, Text3d, Text3dSegment,
+    styling::SegmentParagraphStyle,
+    RevealCommand, SegmentSize, SegmentStyle, SpanEffect, SpanEffects, Text3d, Text3dSegment,
+    ValueFormat,
 };
 
-fn trim_mut(s: &mut String) {
+/// Trim whitespaces, except escaped characters in `keep`, returns the range of `s` that is kept.
+fn trim_mut(s: &mut String, keep: Option<Range<usize>>) -> Range<usize> {
     let trimmed = s.trim();
-    let start = trimmed.as_ptr() as usize - s.as_ptr() as usize;
-    let end = start + trimmed.len();
+    let mut start = trimmed.as_ptr() as usize - s.as_ptr() as usize;
+    let mut end = start + trimmed.len();
+    if let Some(keep) = keep {
+        start = start.min(keep.start);
+        end = end.max(keep.end);
+    }
 
     s.truncate(end);
     s.drain(..start);
+    start..end
 }
 
 impl Text3d {
@@ -68,10 +79,21 @@ impl Text3d {
     /// * `s-4` Sets stroke to a number.
     /// * `s-red` Parses color names as stroke color.
     /// * `v-4.0` Sets the `magic_number` field.
+    /// * `v-1,0.5,0,2` Sets the `params` field, up to 4 numbers, `magic_number` is set to the first number.
+    /// * `param.speed=2` Sets a named parameter in `named_params`.
     /// * `f-Roboto` Sets the font to Roboto.
     /// * `$18` Sets font size to `18`.
     /// * `*1.5` Sets font size to `1.5` times the original.
     /// * `h1` - `h4` Sets font size to `2`, `1.75`, `1.5`, `1.25` times the original.
+    /// * `margin-2`, `margin-right-2` Sets the left or right margin of the paragraph to `2` times the font size.
+    /// * `indent-2` Indents the first line of the paragraph, `indent--2` for a hanging indent.
+    /// * `spacing-1`, `spacing-after-1` Adds space before or after the paragraph.
+    /// * `hanging-2` Sets the left margin, the first segment is a list marker placed in the margin.
+    /// * `wave`, `shake`, `fade` Adds a [`SpanEffect`], `wave-2` sets the strength to `2`.
+    ///   Has no visible effect unless [`SpanEffectModifier`](crate::SpanEffectModifier) is added to the text,
+    ///   or a custom shader reads [`GlyphMeta::EffectMask`](crate::GlyphMeta::EffectMask).
+    ///
+    /// Paragraph styles are read from the first segment of each paragraph, see [`ParagraphStyle`](crate::ParagraphStyle).
     ///
     /// ## Dynamic value
     ///
@@ -83,6 +105,14 @@ impl Text3d {
     /// The result should either be a string fetched from the world
     /// or an [`Entity`](bevy::ecs::entity::Entity) with a [`FetchedTextSegment`](crate::FetchedTextSegment) component.
     ///
+    /// ```md
+    /// {hp:0.0} {gold:,} {time:mm:ss} {pct:%}
+    /// ```
+    ///
+    /// Numbers can be formatted with a [`ValueFormat`] directly after `:` if the name is not a style,
+    /// formatted with the separators of [`Text3dPlugin::locale`](crate::Text3dPlugin::locale) when rendered.
+    /// `{red:0}` is the styled text `0`, use `{b: 0}` to style text that looks like a format.
+    ///
     /// ## Conditions
     ///
     /// ```md
@@ -94,6 +124,53 @@ impl Text3d {
     /// The result should either be a boolean value fetched from the world
     /// or an [`Entity`](bevy::ecs::entity::Entity) with a [`FetchedCondition`](crate::FetchedCondition) component.
     ///
+    /// ```md
+    /// { ?alive : Ready }{:else: Respawning...}
+    /// { ?hp < 10 && !shielded || cursed : {red:Danger!} }
+    /// ```
+    ///
+    /// `{:else: ...}` directly after a conditional scope is displayed when the condition is false.
+    ///
+    /// Conditions can be combined with `&&`, `||` and `!` into a [`ConditionExpr`](crate::ConditionExpr), `&&` binds tighter than `||`.
+    /// Comparisons `<`, `<=`, `>`, `>=`, `==` and `!=` compare a dynamic value parsed by the `fetch_string` function
+    /// with a number, the value is compared as a number when rendered.
+    ///
+    /// ## Dynamic Styles
+    ///
+    /// ```md
+    /// HP: {@hp_color, bold: {hp}}
+    /// ```
+    ///
+    /// Styles starting with `@` are passed to the `fetch_string` function as `@name`,
+    /// which returns a [`Text3dSegment::Style`] on an entity with a [`FetchedStyle`](crate::FetchedStyle) component.
+    /// The style overrides outer scopes when rendered, so it can change without parsing again.
+    /// Styles set in the scope, like `bold` above, or in inner scopes take priority.
+    /// Each scope can have at most one dynamic style.
+    ///
+    /// ## Lists
+    ///
+    /// ```md
+    /// {#each items: {name} x{count:,}
+    /// }
+    /// ```
+    ///
+    /// The scope is repeated for each item of a list, `#each items` is passed to the `fetch_string` function,
+    /// which returns a [`Text3dSegment::Each`] on an entity with a [`FetchedList`](crate::FetchedList) component.
+    /// Values in the scope are fields of the item, read from its [`FetchedItem`](crate::FetchedItem) when rendered,
+    /// so items can be added or removed without parsing again. Lists cannot be nested.
+    ///
+    /// Whitespaces directly after `:` are ignored and trailing whitespaces are kept, i.e. the line break above separates items.
+    ///
+    /// ## Reveal Commands
+    ///
+    /// ```md
+    /// Well{pause=0.5}... {speed=0.5}I guess.
+    /// ```
+    ///
+    /// `{pause=seconds}` waits before revealing the next glyph and `{speed=multiplier}`
+    /// multiplies the reveal speed until the next `speed` command,
+    /// if the text has a [`Text3dReveal`](crate::Text3dReveal) component.
+    ///
     /// ## Markdown
     ///
     /// A subset of markdown features are supported:
@@ -103,9 +180,41 @@ impl Text3d {
     /// * `~~strikethrough~~`
     /// * `\*` escape character
     ///
+    /// With [`ParseBuilder::with_markdown_blocks`]:
+    /// * `` `inline code` `` in the `monospace` font
+    ///
+    /// At the start of a line:
+    /// * `#` to `####` Headings, same sizes as `h1` to `h4` in bold.
+    /// * `-`, `*`, `+` Bulleted list items, `1.` or `1)` numbered list items.
+    ///   Items are indented with a hanging list marker, nested by two spaces or a tab.
+    /// * `>` Block quotes, indented, can be nested and combined with lists.
+    ///
+    /// Line level styles end at the next line break.
+    ///
     /// ## Whitespace Rule
     ///
-    /// Consecutive whitespaces are rendered either as one whitespace or multiple linebreaks.
+    /// By default consecutive whitespaces are rendered either as one whitespace or multiple linebreaks,
+    /// this can be changed with [`ParseBuilder::with_whitespace`].
+    ///
+    /// ```md
+    /// {pre:
+    /// +----+----+
+    /// | HP | 10 |
+    /// +----+----+
+    /// }
+    /// ```
+    ///
+    /// Whitespaces inside `{pre: ...}` are preserved as in [`WhitespaceMode::Preserve`],
+    /// a line break directly after `pre:` is ignored. Line level markdown is only parsed
+    /// in [`WhitespaceMode::PreserveNewlines`] and [`WhitespaceMode::PreserveTabs`].
+    ///
+    /// Spaces inside `{nobr: Sir Gareth}` are non-breaking, the text is never split across lines.
+    ///
+    /// ## Diagnostics
+    ///
+    /// Recoverable issues are ignored, see [`ParseBuilder::strict`].
+    /// Use [`Text3d::parse_with_warnings`] to obtain errors and warnings as [`SpannedParseError`]
+    /// with the byte range and an excerpt of the source.
     ///
     /// ## Inputs
     ///
@@ -113,37 +222,114 @@ impl Text3d {
     ///   * [`Text3dSegment::String`] should be returned for static values.
     ///   * [`Text3dSegment::Extract`] should be returned after spawning a [`FetchedText`](crate::FetchedText) for dynamic values.
     ///   * Since an index is provided, it is possible to return an empty segment and manually update it.
+    ///   * [`Text3dSegment::Style`] should be returned for dynamic styles `@name`.
+    ///   * [`Text3dSegment::Each`] should be returned for lists `#each name`.
     /// * [`ParseStyleFn`]
     ///   Parses strings as [`SegmentStyle`].
     /// * [`ParseConditionFn`]
-    ///   * [`ConditionOutput::Constant`] should be returned for static condition.
-    ///   * [`ConditionOutput::Dynamic`] should be returned after spawning a [`FetchedCondition`](crate::FetchedCondition) for dynamic values.
+    ///   * [`ConditionOutput::Constant`](crate::ConditionOutput::Constant) should be returned for static condition.
+    ///   * [`ConditionOutput::Dynamic`](crate::ConditionOutput::Dynamic) should be returned after spawning a [`FetchedCondition`](crate::FetchedCondition) for dynamic values.
+    ///   * Names in a condition expression are passed separately, the left hand side of comparisons is passed to [`ParseValueFn`].
     ///
     /// We trim whitespaces before passing arguments to these functions.
     pub fn parse(
         text: &str,
-        mut parser: ParseBuilder<impl ParseStyleFn, impl ParseValueFn, impl ParseConditionFn>,
+        parser: ParseBuilder<impl ParseStyleFn, impl ParseValueFn, impl ParseConditionFn>,
     ) -> Result<Self, ParseError> {
+        Text3d::parse_with_warnings(text, parser)
+            .map(|(text, _)| text)
+            .map_err(|e| e.error)
+    }
+
+    /// Parse rich text string, returns recoverable issues as [`ParseWarning`]s.
+    ///
+    /// Warnings include unclosed `{`, unexpected `}` and unterminated markdown like `**` or `__`.
+    /// With [`ParseBuilder::strict`], the first warning is returned as an error instead.
+    pub fn parse_with_warnings(
+        text: &str,
+        mut parser: ParseBuilder<impl ParseStyleFn, impl ParseValueFn, impl ParseConditionFn>,
+    ) -> Result<(Self, Vec<ParseWarning>), SpannedParseError> {
         #[derive(Debug, Clone, Copy)]
         enum ParseState {
             Text,
             Command,
-            Conditional(bool),
+            Conditional,
         }
 
         let mut buffer = String::new();
+        // Offset in `text` of each byte of `buffer` in text state.
+        let mut buffer_sources: Vec<usize> = Vec::new();
         let mut state = ParseState::Text;
         let mut segments = Vec::new();
-        let mut stack: Vec<(SegmentStyle, Option<usize>)> = vec![(SegmentStyle::default(), None)];
+        let mut source_offsets = Vec::new();
+        let mut warnings = Vec::new();
+        // Style, position of `SkipIf` and position of the opening bracket.
+        let mut stack: Vec<(SegmentStyle, Option<usize>, usize)> =
+            vec![(SegmentStyle::default(), None, 0)];
+        // Depth in stack, marker and span of unterminated markdown.
+        let mut markers: Vec<(usize, &'static str, Range<usize>)> = Vec::new();
+        let mut command_start = 0;
+        // Span of the last bracket, reported if the style stack is unbalanced.
+        let mut bracket = 0..0;
+        // Range of escaped characters in buffer, not trimmed.
+        let mut escaped: Option<Range<usize>> = None;
+        // Whether only whitespaces are parsed since the last line break, and their width.
+        let mut line_start = true;
+        let mut line_indent = 0;
+        // Stack depth of the style of a markdown heading, list item or block quote.
+        let mut line_scope: Option<usize> = None;
+        // Stack depth of the outermost `{pre: ...}` scope.
+        let mut pre_depth: Option<usize> = None;
+        // Stack depth of the outermost `{nobr: ...}` scope.
+        let mut nobr_depth: Option<usize> = None;
+        // Stack depth of the `{#each name: ...}` scope.
+        let mut each_depth: Option<usize> = None;
+        // Stack depths of open conditional scopes.
+        let mut conditional_depths: Vec<usize> = Vec::new();
+        // End of the last closed conditional scope, an `{:else: ...}` must start there.
+        let mut last_conditional: Option<(usize, Closed)> = None;
 
+        macro_rules! error {
+            ($error: expr, $span: expr) => {
+                SpannedParseError::new(text, $error, $span)
+            };
+        }
+        macro_rules! warn {
+            ($error: expr, $span: expr) => {
+                let warning = SpannedParseError::new(text, $error, $span);
+                if parser.strict {
+                    return Err(warning);
+                }
+                warnings.push(warning);
+            };
+        }
+        macro_rules! push {
+            ($segment: expr, $offset: expr) => {
+                segments.push($segment);
+                source_offsets.push($offset);
+            };
+        }
         macro_rules! push_seg {
             () => {
+                escaped = None;
                 if !buffer.is_empty() {
-                    segments.push((
-                        Text3dSegment::String(core::mem::take(&mut buffer)),
-                        style!(),
-                    ));
+                    let offset = buffer_sources.first().copied().unwrap_or(0);
+                    push!(
+                        (
+                            Text3dSegment::String(core::mem::take(&mut buffer)),
+                            style!(),
+                        ),
+                        offset
+                    );
                 }
+                buffer_sources.clear();
+            };
+        }
+        macro_rules! trim {
+            () => {
+                let kept = trim_mut(&mut buffer, escaped.clone());
+                buffer_sources.truncate(kept.end);
+                buffer_sources.drain(..kept.start);
             };
         }
         macro_rules! style {
@@ -151,96 +337,424 @@ impl Text3d {
                 stack
                     .last()
                     .map(|x| &x.0)
-                    .ok_or(ParseError::BracketMismatch)?
+                    .ok_or_else(|| error!(ParseError::BracketMismatch, bracket.clone()))?
                     .clone()
             };
             (mut) => {
                 stack
                     .last_mut()
                     .map(|x| &mut x.0)
-                    .ok_or(ParseError::BracketMismatch)?
+                    .ok_or_else(|| error!(ParseError::BracketMismatch, bracket.clone()))?
+            };
+        }
+        macro_rules! toggle {
+            ($marker: expr, $span: expr) => {
+                let depth = stack.len();
+                match markers
+                    .iter()
+                    .rposition(|(d, m, _)| *d == depth && *m == $marker)
+                {
+                    Some(position) => {
+                        markers.remove(position);
+                    }
+                    None => markers.push((depth, $marker, $span)),
+                }
+            };
+        }
+        macro_rules! close_markers {
+            () => {
+                while let Some((_, marker, span)) =
+                    markers.pop_if(|(depth, _, _)| *depth >= stack.len())
+                {
+                    warn!(ParseError::UnterminatedMarkdown(marker), span);
+                }
+            };
+        }
+        macro_rules! close_line_scope {
+            () => {
+                if line_scope == Some(stack.len()) {
+                    push_seg!();
+                    close_markers!();
+                    stack.pop();
+                    line_scope = None;
+                }
+            };
+        }
+        macro_rules! whitespace_mode {
+            () => {
+                match pre_depth {
+                    Some(_) => WhitespaceMode::Preserve,
+                    None => parser.whitespace,
+                }
             };
         }
         use ParseState::*;
-        let mut iter = text.chars().peekable();
-        while let Some(c) = iter.next() {
+        let mut iter = text.char_indices().peekable();
+        while let Some((i, c)) = iter.next() {
+            let at_line_start = core::mem::take(&mut line_start);
+            if at_line_start
+                && parser.markdown_blocks
+                && line_scope.is_none()
+                && matches!(state, Text)
+                && matches!(
+                    whitespace_mode!(),
+                    WhitespaceMode::PreserveNewlines | WhitespaceMode::PreserveTabs
+                )
+            {
+                if let Some((style, marker, len)) = line_markdown(&text[i..], line_indent / 2) {
+                    // Leading whitespaces of the text.
+                    if buffer.ends_with(' ')
+                        && escaped.as_ref().is_none_or(|x| x.end < buffer.len())
+                    {
+                        buffer.pop();
+                        buffer_sources.pop();
+                    }
+                    push_seg!();
+                    stack.push((style!().join(style), None, i));
+                    line_scope = Some(stack.len());
+                    if let Some(marker) = marker {
+                        push!((Text3dSegment::String(marker), style!()), i);
+                    }
+                    while iter.peek().is_some_and(|(j, _)| *j < i + len) {
+                        iter.next();
+                    }
+                    continue;
+                }
+            }
+            if matches!(c, '{' | '}') {
+                bracket = i..i + 1;
+            }
             match (c, state) {
                 ('{', Text) => {
                     push_seg!();
                     state = Command;
+                    command_start = i;
                 }
                 (' ', Command) if buffer.is_empty() => {}
                 ('?', Command) if buffer.is_empty() => {
-                    state = Conditional(true);
+                    state = Conditional;
                 }
-                ('!', Conditional(true)) if buffer.is_empty() => {
-                    state = Conditional(false);
+                (':', Command)
+                    if buffer.trim().is_empty() && text[i + 1..].starts_with("else:") =>
+                {
+                    let Some((_, closed)) = last_conditional
+                        .take()
+                        .filter(|(end, _)| *end == command_start)
+                    else {
+                        return Err(error!(
+                            ParseError::BadCommand(
+                                "`{:else: ...}` must directly follow a conditional.".into()
+                            ),
+                            command_start..i + 6
+                        ));
+                    };
+                    for _ in 0.."else:".len() {
+                        iter.next();
+                    }
+                    while iter.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+                        iter.next();
+                    }
+                    match closed {
+                        Closed::Dynamic(r) => match negated(&segments[r].0) {
+                            Some(segment) => {
+                                let pos = segments.len();
+                                push!((segment, style!()), command_start);
+                                stack.push((style!(), Some(pos), command_start));
+                            }
+                            None => stack.push((style!(), None, command_start)),
+                        },
+                        Closed::Hidden => stack.push((style!(), None, command_start)),
+                        Closed::Shown => {
+                            // The condition is always true, skip the else branch.
+                            let mut depth = 1;
+                            while depth > 0 {
+                                match iter.next() {
+                                    Some((_, '{')) => depth += 1,
+                                    Some((_, '}')) => depth -= 1,
+                                    Some((_, '\\')) => {
+                                        iter.next();
+                                    }
+                                    Some(_) => (),
+                                    None => {
+                                        warn!(
+                                            ParseError::UnclosedBracket,
+                                            command_start..command_start + 1
+                                        );
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    buffer.clear();
+                    state = Text;
                 }
                 (':', Command) => {
-                    let mut style = style!();
-                    for s in buffer.trim().split(",") {
-                        style = style.join(parse_style(s.trim(), &mut parser.parse_style)?)
+                    // `{name:format}` is a value only if the text after `:` is a format
+                    // and `name` is not a list of styles, e.g. `{bold:0}` is a bold `0`.
+                    let is_each = buffer.trim().starts_with("#each");
+                    let value_format = parse_value_format(&text[i + 1..]).filter(|_| !is_each);
+                    let scope_style = (!is_each)
+                        .then(|| parse_scope_style(buffer.trim(), &mut parser.parse_style));
+                    if let Some((format, len)) =
+                        value_format.filter(|_| matches!(scope_style, Some(Err(_))))
+                    {
+                        let (segment, style) = match each_depth {
+                            Some(_) => (
+                                Text3dSegment::Field(buffer.trim().to_owned()),
+                                SegmentStyle::default(),
+                            ),
+                            None => parser
+                                .parse_value
+                                .call(segments.len(), buffer.trim())
+                                .map_err(|e| error!(e, command_start..i + len + 2))?,
+                        };
+                        let mut style = style!().join(style);
+                        style.format = Some(format);
+                        push!((segment, style), command_start);
+                        while iter.peek().is_some_and(|(j, _)| *j <= i + len + 1) {
+                            iter.next();
+                        }
+                        buffer.clear();
+                        state = Text;
+                        continue;
+                    }
+                    if let Some(name) = buffer.trim().strip_prefix("#each") {
+                        if each_depth.is_some() {
+                            return Err(error!(
+                                ParseError::NotSupported("nested lists"),
+                                command_start..i + 1
+                            ));
+                        }
+                        let list = match parser
+                            .parse_value
+                            .call(segments.len(), &format!("#each {}", name.trim()))
+                            .map_err(|e| error!(e, command_start..i + 1))?
+                        {
+                            (Text3dSegment::Each { list, .. }, _) => list,
+                            _ => {
+                                return Err(error!(
+                                    ParseError::BadCommand(format!(
+                                        "Expected a list for {}.",
+                                        buffer.trim()
+                                    )),
+                                    command_start..i + 1
+                                ))
+                            }
+                        };
+                        push!(
+                            (Text3dSegment::Each { list, offset: 0 }, style!()),
+                            command_start
+                        );
+                        stack.push((style!(), Some(segments.len() - 1), command_start));
+                        each_depth = Some(stack.len());
+                        while iter.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+                            iter.next();
+                        }
+                        buffer.clear();
+                        state = Text;
+                        continue;
+                    }
+                    let style = style!().join(
+                        scope_style
+                            .unwrap_or(Ok(SegmentStyle::default()))
+                            .map_err(|e| error!(e, command_start..i + 1))?,
+                    );
+                    let mut pre = false;
+                    let mut nobr = false;
+                    let mut dynamic_style = None;
+                    for s in split_styles(buffer.trim()) {
+                        match s.trim() {
+                            "pre" => pre = true,
+                            "nobr" => nobr = true,
+                            s if s.starts_with('@') => {
+                                if dynamic_style.is_some() {
+                                    return Err(error!(
+                                        ParseError::NotSupported(
+                                            "multiple dynamic styles in a scope"
+                                        ),
+                                        command_start..i + 1
+                                    ));
+                                }
+                                let name = format!("@{}", s[1..].trim());
+                                match parser
+                                    .parse_value
+                                    .call(segments.len(), &name)
+                                    .map_err(|e| error!(e, command_start..i + 1))?
+                                {
+                                    (Text3dSegment::Style { style, .. }, _) => {
+                                        dynamic_style = Some(style)
+                                    }
+                                    _ => {
+                                        return Err(error!(
+                                            ParseError::BadCommand(format!(
+                                                "Expected a dynamic style for {name}."
+                                            )),
+                                            command_start..i + 1
+                                        ))
+                                    }
+                                }
+                            }
+                            _ => (),
+                        }
+                    }
+                    let pos = match dynamic_style {
+                        Some(entity) => {
+                            push!(
+                                (
+                                    Text3dSegment::Style {
+                                        style: entity,
+                                        offset: 0,
+                                    },
+                                    style!(),
+                                ),
+                                command_start
+                            );
+                            Some(segments.len() - 1)
+                        }
+                        None => None,
+                    };
+                    stack.push((style, pos, command_start));
+                    if nobr && nobr_depth.is_none() {
+                        nobr_depth = Some(stack.len());
+                    }
+                    if pre && pre_depth.is_none() {
+                        pre_depth = Some(stack.len());
+                        // Allow the content to start on a new line.
+                        if iter.peek().is_some_and(|(_, c)| *c == '\n') {
+                            iter.next();
+                        }
                     }
-                    stack.push((style, None));
                     buffer.clear();
                     state = Text;
                 }
-                (':', Conditional(should_be)) => {
-                    match parser.parse_condition.call(buffer.trim())? {
-                        ConditionOutput::Constant(b) if b == should_be => {
+                (':', Conditional) => {
+                    match parse_condition_expr(
+                        buffer.trim(),
+                        segments.len(),
+                        &mut parser.parse_condition,
+                        &mut parser.parse_value,
+                    )
+                    .map_err(|e| error!(e, command_start..i + 1))?
+                    {
+                        Err(true) => {
                             // start a scope and do nothing
-                            stack.push((style!(), None));
+                            stack.push((style!(), None, command_start));
+                            conditional_depths.push(stack.len());
                         }
-                        ConditionOutput::Constant(_) => {
+                        Err(false) => {
                             // skip all wrapped items.
                             let mut depth = 1;
                             while depth > 0 {
                                 match iter.next() {
-                                    Some('{') => depth += 1,
-                                    Some('}') => depth -= 1,
-                                    _ => (),
+                                    Some((_, '{')) => depth += 1,
+                                    Some((j, '}')) => {
+                                        depth -= 1;
+                                        if depth == 0 {
+                                            last_conditional = Some((j + 1, Closed::Hidden));
+                                        }
+                                    }
+                                    Some((_, '\\')) => {
+                                        iter.next();
+                                    }
+                                    Some(_) => (),
+                                    None => {
+                                        warn!(
+                                            ParseError::UnclosedBracket,
+                                            command_start..command_start + 1
+                                        );
+                                        break;
+                                    }
                                 }
                             }
                         }
-                        ConditionOutput::Dynamic(entity) => {
+                        Ok(condition) => {
                             let pos = segments.len();
-                            segments.push((
-                                Text3dSegment::SkipIf {
-                                    condition: entity,
-                                    skip_if: !should_be,
-                                    offset: 0,
-                                },
-                                style!(),
-                            ));
-                            stack.push((style!(), Some(pos)));
+                            push!((condition.into_segment(false, 0), style!()), command_start);
+                            stack.push((style!(), Some(pos), command_start));
+                            conditional_depths.push(stack.len());
                         }
                     }
                     buffer.clear();
                     state = Text;
                 }
                 ('}', Text) => {
-                    trim_mut(&mut buffer);
+                    // Trailing whitespaces separate items of a list.
+                    if whitespace_mode!() != WhitespaceMode::Preserve
+                        && each_depth != Some(stack.len())
+                    {
+                        trim!();
+                    }
                     push_seg!();
-                    if let Some((_, Some(r))) = stack.pop() {
+                    close_line_scope!();
+                    if stack.len() <= 1 {
+                        warn!(ParseError::UnexpectedBracket, i..i + 1);
+                        continue;
+                    }
+                    close_markers!();
+                    if conditional_depths.last() == Some(&stack.len()) {
+                        conditional_depths.pop();
+                        let closed = match stack[stack.len() - 1].1 {
+                            Some(r) => Closed::Dynamic(r),
+                            None => Closed::Shown,
+                        };
+                        last_conditional = Some((i + 1, closed));
+                    }
+                    if let Some((_, Some(r), _)) = stack.pop() {
                         let l = segments.len().saturating_sub(1 + r);
-                        if let Text3dSegment::SkipIf { offset, .. } = &mut segments[r].0 {
+                        if let Text3dSegment::SkipIf { offset, .. }
+                        | Text3dSegment::SkipIfExpr { offset, .. }
+                        | Text3dSegment::Style { offset, .. }
+                        | Text3dSegment::Each { offset, .. } = &mut segments[r].0
+                        {
                             *offset = l;
                         }
                     };
+                    if each_depth.is_some_and(|depth| stack.len() < depth) {
+                        each_depth = None;
+                    }
+                    if pre_depth.is_some_and(|depth| stack.len() < depth) {
+                        pre_depth = None;
+                    }
+                    if nobr_depth.is_some_and(|depth| stack.len() < depth) {
+                        nobr_depth = None;
+                    }
                 }
                 ('}', Command) => {
-                    let (segment, style) =
-                        parser.parse_value.call(segments.len(), buffer.trim())?;
+                    if let Some(command) = parse_reveal_command(buffer.trim()) {
+                        push!((Text3dSegment::Reveal(command), style!()), command_start);
+                        buffer.clear();
+                        state = Text;
+                        continue;
+                    }
+                    let (segment, style) = match each_depth {
+                        Some(_) => (
+                            Text3dSegment::Field(buffer.trim().to_owned()),
+                            SegmentStyle::default(),
+                        ),
+                        None => parser
+                            .parse_value
+                            .call(segments.len(), buffer.trim())
+                            .map_err(|e| error!(e, command_start..i + 1))?,
+                    };
                     let style = style!().join(style);
-                    segments.push((segment, style));
+                    push!((segment, style), command_start);
+                    buffer.clear();
+                    state = Text;
+                }
+                ('}', Conditional) => {
+                    // A condition without a value, skip it.
+                    warn!(
+                        ParseError::BadCommand(format!("?{}", buffer.trim())),
+                        command_start..i + 1
+                    );
                     buffer.clear();
                     state = Text;
                 }
-                ('}', Conditional(_)) => {} // do nothing, failing is too harsh.
                 ('*', Text) => {
                     push_seg!();
                     let mut stars = 1;
-                    while let Some(c) = iter.peek() {
+                    while let Some((_, c)) = iter.peek() {
                         if *c == '*' {
                             stars += 1;
                             iter.next();
@@ -249,68 +763,349 @@ impl Text3d {
                         }
                     }
                     match stars {
-                        1 => style!(mut).style.flip(),
-                        2 => style!(mut).weight.flip(),
+                        1 => {
+                            style!(mut).style.flip();
+                            toggle!("*", i..i + 1);
+                        }
+                        2 => {
+                            style!(mut).weight.flip();
+                            toggle!("**", i..i + 2);
+                        }
                         3 => {
                             style!(mut).style.flip();
                             style!(mut).weight.flip();
+                            toggle!("***", i..i + 3);
                         }
                         n if n % 2 == 0 => (),
-                        _ => style!(mut).style.flip(),
+                        n => {
+                            style!(mut).style.flip();
+                            toggle!("*", i..i + n);
+                        }
                     }
                 }
-                ('_', Text) if iter.peek() == Some(&'_') => {
+                ('_', Text) if iter.peek().is_some_and(|(_, c)| *c == '_') => {
                     push_seg!();
                     iter.next();
-                    style!(mut).underline.flip()
+                    style!(mut).underline.flip();
+                    toggle!("__", i..i + 2);
                 }
-                ('~', Text) if iter.peek() == Some(&'~') => {
+                ('~', Text) if iter.peek().is_some_and(|(_, c)| *c == '~') => {
                     push_seg!();
                     iter.next();
-                    style!(mut).strikethrough.flip()
+                    style!(mut).strikethrough.flip();
+                    toggle!("~~", i..i + 2);
+                }
+                ('`', Text) if parser.markdown_blocks => {
+                    let Some(len) = text[i + 1..].find('`') else {
+                        warn!(ParseError::UnterminatedMarkdown("`"), i..i + 1);
+                        buffer.push('`');
+                        buffer_sources.push(i);
+                        continue;
+                    };
+                    push_seg!();
+                    let code = &text[i + 1..i + 1 + len];
+                    if !code.is_empty() {
+                        let style = style!().join(SegmentStyle {
+                            font: Some("monospace".into()),
+                            ..Default::default()
+                        });
+                        push!(
+                            (Text3dSegment::String(code.replace('\n', " ")), style),
+                            i + 1
+                        );
+                    }
+                    while iter.peek().is_some_and(|(j, _)| *j <= i + 1 + len) {
+                        iter.next();
+                    }
                 }
-                (c, Command | Conditional(_)) => buffer.push(c),
+                (c, Command | Conditional) => buffer.push(c),
                 ('\\', Text) => {
-                    if let Some(c) = iter.peek() {
+                    if let Some((_, c)) = iter.peek() {
+                        let start = escaped.as_ref().map_or(buffer.len(), |x| x.start);
                         buffer.push(*c);
+                        escaped = Some(start..buffer.len());
+                        buffer_sources.resize(buffer.len(), i);
                         iter.next();
                     } else {
                         buffer.push('\\');
+                        buffer_sources.push(i);
                     }
                 }
                 (c, Text) if c.is_whitespace() => {
-                    let mut linebreaks = if c == '\n' { 1 } else { 0 };
-                    while let Some(c) = iter.peek() {
+                    let mut end = i + c.len_utf8();
+                    line_indent = indent_width(c, 0);
+                    while let Some((j, c)) = iter.peek() {
                         if !c.is_whitespace() {
                             break;
-                        } else if *c == '\n' {
-                            linebreaks += 1;
                         }
+                        end = j + c.len_utf8();
+                        line_indent = indent_width(*c, line_indent);
                         iter.next();
                     }
-                    match linebreaks {
-                        0 => buffer.push(' '),
-                        n => buffer.extend(repeat_n('\n', n)),
+                    let whitespaces = &text[i..end];
+                    let linebreak = whitespaces.contains('\n');
+                    line_start = at_line_start || linebreak;
+                    if linebreak {
+                        close_line_scope!();
                     }
+                    let start = buffer.len();
+                    whitespace_mode!().push_whitespace(whitespaces, &mut buffer);
+                    if nobr_depth.is_some() && buffer[start..].contains(' ') {
+                        let nobr = buffer[start..].replace(' ', "\u{A0}");
+                        buffer.truncate(start);
+                        buffer.push_str(&nobr);
+                    }
+                    buffer_sources.resize(buffer.len(), i);
                 }
                 (c, Text) => {
                     buffer.push(c);
+                    buffer_sources.resize(buffer.len(), i);
+                }
+            }
+        }
+        if !matches!(state, Text) {
+            buffer.clear();
+            warn!(ParseError::UnclosedBracket, command_start..text.len());
+        }
+        if !buffer.is_empty() {
+            let offset = buffer_sources.first().copied().unwrap_or(0);
+            push!((Text3dSegment::String(buffer), style!()), offset);
+        }
+        while stack.len() > 1 {
+            let is_line_scope = line_scope == Some(stack.len());
+            close_markers!();
+            if let Some((_, pos, start)) = stack.pop() {
+                if let Some(r) = pos {
+                    let l = segments.len().saturating_sub(1 + r);
+                    if let Text3dSegment::Style { offset, .. }
+                    | Text3dSegment::Each { offset, .. } = &mut segments[r].0
+                    {
+                        *offset = l;
+                    }
+                }
+                if !is_line_scope {
+                    warn!(ParseError::UnclosedBracket, start..start + 1);
                 }
             }
         }
-        push_seg!();
-        Ok(Text3d { segments })
+        close_markers!();
+        Ok((
+            Text3d {
+                segments,
+                source_offsets,
+            },
+            warnings,
+        ))
+    }
+}
+
+/// A conditional scope that was closed, see `{:else: ...}`.
+enum Closed {
+    /// The scope starts at this segment.
+    Dynamic(usize),
+    /// The condition is always true.
+    Shown,
+    /// The condition is always false.
+    Hidden,
+}
+
+/// The condition of an else branch after a conditional `segment`.
+fn negated(segment: &Text3dSegment) -> Option<Text3dSegment> {
+    match segment {
+        Text3dSegment::SkipIf {
+            condition, skip_if, ..
+        } => Some(Text3dSegment::SkipIf {
+            condition: *condition,
+            skip_if: !*skip_if,
+            offset: 0,
+        }),
+        Text3dSegment::SkipIfExpr {
+            condition, skip_if, ..
+        } => Some(Text3dSegment::SkipIfExpr {
+            condition: condition.clone(),
+            skip_if: !*skip_if,
+            offset: 0,
+        }),
+        _ => None,
+    }
+}
+
+/// Width of leading whitespaces in a line after `c`, tabs count as two spaces.
+fn indent_width(c: char, width: usize) -> usize {
+    match c {
+        '\n' => 0,
+        '\t' => width + 2,
+        _ => width + 1,
+    }
+}
+
+/// Indentation of a block quote, in multiples of font size.
+const QUOTE_INDENT: f32 = 1.0;
+/// Indentation of each level of list items, in multiples of font size.
+const LIST_INDENT: f32 = 1.5;
+
+/// Parse markdown headings, list items and block quotes at the start of a line.
+///
+/// Returns the style of the line, the list marker and the length of the syntax.
+fn line_markdown(line: &str, level: usize) -> Option<(SegmentStyle, Option<String>, usize)> {
+    const HEADINGS: [f32; 4] = [2.0, 1.75, 1.5, 1.25];
+    let spaced = |s: &str| s.starts_with([' ', '\t']);
+    let mut rest = line;
+    let mut quotes = 0;
+    while let Some(quoted) = rest.strip_prefix('>') {
+        quotes += 1;
+        rest = quoted.trim_start_matches([' ', '\t']);
+    }
+    let mut style = SegmentStyle::default();
+    let mut marker = None;
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if (1..=4).contains(&hashes) && spaced(&rest[hashes..]) {
+        style.size = Some(SegmentSize::Multiply(HEADINGS[hashes - 1]));
+        style.weight = Some(Weight::BOLD);
+        rest = &rest[hashes..];
+    } else if rest.starts_with(['-', '*', '+']) && spaced(&rest[1..]) {
+        marker = Some("• ".to_owned());
+        rest = &rest[1..];
+    } else if (1..=9).contains(&digits)
+        && rest[digits..].starts_with(['.', ')'])
+        && spaced(&rest[digits + 1..])
+    {
+        marker = Some(format!("{} ", &rest[..digits + 1]));
+        rest = &rest[digits + 1..];
+    } else if quotes == 0 {
+        return None;
+    }
+    let rest = rest.trim_start_matches([' ', '\t']);
+    let start = quotes as f32 * QUOTE_INDENT
+        + marker
+            .as_ref()
+            .map_or(0.0, |_| (level + 1) as f32 * LIST_INDENT);
+    if start > 0.0 {
+        style.paragraph.margin_left = Some(start);
+    }
+    if marker.is_some() {
+        style.paragraph.indent = Some(0.0);
+        style.paragraph.hanging_marker = Some(true);
+    }
+    Some((style, marker, line.len() - rest.len()))
+}
+
+/// Split styles by `,`, except numbers following `v-`, i.e. `v-1,0.5,0,2`.
+fn split_styles(styles: &str) -> Vec<&str> {
+    let mut result: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    for piece in styles.split(',') {
+        let end = start + piece.len();
+        match result.last_mut() {
+            Some((last_start, last_end))
+                if styles[*last_start..*last_end]
+                    .trim_start()
+                    .starts_with("v-")
+                    && styles[*last_start..*last_end].matches(',').count() < 3
+                    && f32::from_str(piece.trim()).is_ok() =>
+            {
+                *last_end = end;
+            }
+            _ => result.push((start, end)),
+        }
+        start = end + 1;
+    }
+    result.into_iter().map(|(s, e)| &styles[s..e]).collect()
+}
+
+/// Parse the styles of a scope `{name: ..}`, skipping `pre`, `nobr` and dynamic styles.
+///
+/// Returns an error if `name` is not a list of styles, i.e. a value followed by a [`ValueFormat`].
+fn parse_scope_style(
+    name: &str,
+    stylesheet: &mut impl ParseStyleFn,
+) -> Result<SegmentStyle, ParseError> {
+    let mut style = SegmentStyle::default();
+    for s in split_styles(name) {
+        match s.trim() {
+            "pre" | "nobr" => (),
+            s if s.starts_with('@') => (),
+            s => style = style.join(parse_style(s, stylesheet)?),
+        }
+    }
+    Ok(style)
+}
+
+/// Parse a [`ValueFormat`] followed by `}`, returns the format and its length.
+pub(crate) fn parse_value_format(s: &str) -> Option<(ValueFormat, usize)> {
+    let len = s.find('}')?;
+    let format = &s[..len];
+    if format.contains(|c: char| c.is_whitespace() || c == '{') {
+        return None;
+    }
+    Some((format.parse().ok()?, len))
+}
+
+pub(crate) fn parse_reveal_command(command: &str) -> Option<RevealCommand> {
+    let (name, value) = command.split_once('=')?;
+    let value = f32::from_str(value.trim()).ok()?;
+    match name.trim() {
+        "pause" => Some(RevealCommand::Pause(value)),
+        "speed" => Some(RevealCommand::Speed(value)),
+        _ => None,
     }
 }
 
-fn parse_style(
+fn parse_span_effect(style: &str) -> Option<SpanEffects> {
+    let (name, strength) = match style.split_once('-') {
+        Some((name, strength)) => (name, f32::from_str(strength).ok()?),
+        None => (style, 1.0),
+    };
+    SpanEffect::from_name(name).map(|effect| SpanEffects::new(effect, strength))
+}
+
+fn parse_paragraph_style(style: &str) -> Option<SegmentParagraphStyle> {
+    let mut paragraph = SegmentParagraphStyle::default();
+    // Longer prefixes first.
+    let (field, value) = if let Some(value) = style.strip_prefix("margin-right-") {
+        (&mut paragraph.margin_right, value)
+    } else if let Some(value) = style.strip_prefix("margin-") {
+        (&mut paragraph.margin_left, value)
+    } else if let Some(value) = style.strip_prefix("indent-") {
+        (&mut paragraph.indent, value)
+    } else if let Some(value) = style.strip_prefix("spacing-after-") {
+        (&mut paragraph.spacing_after, value)
+    } else if let Some(value) = style.strip_prefix("spacing-") {
+        (&mut paragraph.spacing_before, value)
+    } else if let Some(value) = style.strip_prefix("hanging-") {
+        paragraph.indent = Some(0.0);
+        paragraph.hanging_marker = Some(true);
+        (&mut paragraph.margin_left, value)
+    } else {
+        return None;
+    };
+    *field = Some(f32::from_str(value).ok()?);
+    Some(paragraph)
+}
+
+pub(crate) fn parse_style(
     style: &str,
     stylesheet: &mut impl ParseStyleFn,
 ) -> Result<SegmentStyle, ParseError> {
-    if let Some(number) = style.strip_prefix("v-") {
-        if let Ok(magic_number) = f32::from_str(number) {
+    if let Some(numbers) = style.strip_prefix("v-") {
+        let mut params = Vec4::ZERO;
+        for (i, number) in numbers.split(',').enumerate() {
+            match f32::from_str(number.trim()) {
+                Ok(value) if i < 4 => params[i] = value,
+                _ => return stylesheet.call(style),
+            }
+        }
+        Ok(SegmentStyle {
+            magic_number: Some(params.x),
+            params: Some(params),
+            ..Default::default()
+        })
+    } else if let Some((key, value)) = style.strip_prefix("param.").and_then(|x| x.split_once('='))
+    {
+        if let Ok(value) = f32::from_str(value.trim()) {
             Ok(SegmentStyle {
-                magic_number: Some(magic_number),
+                named_params: vec![(key.trim().into(), value)],
                 ..Default::default()
             })
         } else {
@@ -353,6 +1148,16 @@ fn parse_style(
         } else {
             stylesheet.call(style)
         }
+    } else if let Some(paragraph) = parse_paragraph_style(style) {
+        Ok(SegmentStyle {
+            paragraph,
+            ..Default::default()
+        })
+    } else if let Some(effects) = parse_span_effect(style) {
+        Ok(SegmentStyle {
+            effects,
+            ..Default::default()
+        })
     } else if let Some(color) = parse_color(style) {
         Ok(SegmentStyle {
             fill_color: Some(color),
@@ -396,3 +1201,464 @@ fn parse_style(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::entity::Entity;
+
+    use crate::{
+        misc::Weight, parse_util::ConditionOutput, ParseBuilder, ParseError, SegmentSize,
+        SegmentStyle, SpanEffect, Text3d, Text3dSegment, WhitespaceMode,
+    };
+
+    /// `a` and `b` are dynamic conditions, `yes` and `no` are constants.
+    fn parse(text: &str) -> Text3d {
+        Text3d::parse(
+            text,
+            ParseBuilder::new().with_parse_condition(|s| match s {
+                "a" => Ok(ConditionOutput::Dynamic(Entity::from_raw_u32(0).unwrap())),
+                "b" => Ok(ConditionOutput::Dynamic(Entity::from_raw_u32(1).unwrap())),
+                "yes" => Ok(ConditionOutput::Constant(true)),
+                "no" => Ok(ConditionOutput::Constant(false)),
+                _ => Err(ParseError::Custom(format!("Unknown condition {s}."))),
+            }),
+        )
+        .unwrap()
+    }
+
+    /// Visible text if conditions `a` and `b` have these values.
+    fn visible(text: &Text3d, a: bool, b: bool) -> String {
+        let condition = |e: Entity| [a, b].get(e.index_u32() as usize).copied();
+        let mut result = String::new();
+        let mut skip = 0;
+        for (segment, _) in &text.segments {
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            match segment {
+                Text3dSegment::String(s) => result.push_str(s),
+                Text3dSegment::SkipIf {
+                    condition: e,
+                    skip_if,
+                    offset,
+                } if condition(*e) == Some(*skip_if) => skip = *offset,
+                Text3dSegment::SkipIfExpr {
+                    condition: expr,
+                    skip_if,
+                    offset,
+                } if expr.evaluate(condition, |_| None) == Some(*skip_if) => skip = *offset,
+                _ => (),
+            }
+        }
+        result
+    }
+
+    fn assert_visible(text: &str, expected: [&str; 4]) {
+        let text = parse(text);
+        let cases = [(false, false), (true, false), (false, true), (true, true)];
+        for ((a, b), expected) in cases.into_iter().zip(expected) {
+            assert_eq!(visible(&text, a, b), expected, "a = {a}, b = {b}");
+        }
+    }
+
+    #[test]
+    fn else_branches() {
+        assert_visible("{?a: on}{:else: off}", ["off", "on", "off", "on"]);
+        assert_visible("{?!a: on}{:else: off}", ["on", "off", "on", "off"]);
+        assert_visible(
+            "[{bold:{?a: on}{:else: off}}]",
+            ["[off]", "[on]", "[off]", "[on]"],
+        );
+        assert_visible(
+            "{?a && b || !a && !b: same}{:else: different}",
+            ["same", "different", "different", "same"],
+        );
+    }
+
+    #[test]
+    fn bars_are_literal_in_conditionals() {
+        assert_visible("{?a: on|off}", ["", "on|off", "", "on|off"]);
+        assert_visible("{?a: on\\|off}", ["", "on|off", "", "on|off"]);
+        assert_visible("{?yes: on|off}", ["on|off"; 4]);
+        assert_visible("{?a: x}{:else: y|z}", ["y|z", "x", "y|z", "x"]);
+        // `{:else: ...}` must directly follow a conditional scope.
+        let constant = || {
+            ParseBuilder::new().with_parse_condition(|_: &str| Ok(ConditionOutput::Constant(true)))
+        };
+        assert!(Text3d::parse("{:else: x}", constant()).is_err());
+        assert!(Text3d::parse("{?c: x} {:else: y}", constant()).is_err());
+        assert!(Text3d::parse("{?c: x}{:else: y}{:else: z}", constant()).is_err());
+    }
+
+    #[test]
+    fn nested_else_branches() {
+        assert_visible(
+            "{?a:A{?b: B}{:else: C}}{:else: D{?b: E}}",
+            ["D", "AC", "DE", "AB"],
+        );
+        assert_visible("{?a:{bold: x|y}}{:else: z}", ["z", "x|y", "z", "x|y"]);
+        assert_visible("{?a:{?b: x}}{:else: y}", ["y", "", "y", "x"]);
+    }
+
+    #[test]
+    fn constant_else_branches() {
+        assert_visible("{?yes: on}{:else: off}", ["on"; 4]);
+        assert_visible("{?no: {bold: x|y}}{:else: off}", ["off"; 4]);
+        assert_visible("{?no: on}{:else: {?b: x}{:else: y}}", ["y", "y", "x", "x"]);
+        assert_visible(
+            "{?yes && a || no: on}{:else: off}",
+            ["off", "on", "off", "on"],
+        );
+        assert_visible("{?a || yes: on}{:else: off}", ["on"; 4]);
+    }
+
+    #[test]
+    fn combined_span_effects() {
+        let text = Text3d::parse_raw("{wave, shake-0.5: x}").unwrap();
+        let effects = text.segments[0].1.effects;
+        assert_eq!(
+            effects.mask(),
+            SpanEffect::Wave.bit() | SpanEffect::Shake.bit()
+        );
+        assert_eq!(effects.strength(SpanEffect::Wave), 1.0);
+        assert_eq!(effects.strength(SpanEffect::Shake), 0.5);
+        assert_eq!(effects.strength(SpanEffect::Fade), 0.0);
+    }
+
+    #[test]
+    fn source_offsets() {
+        let text = parse("a {red: b} {?yes: c}{:else: d}{pause=1}\\{e\\}");
+        let strings: Vec<_> = text
+            .segments
+            .iter()
+            .zip(&text.source_offsets)
+            .filter_map(|((segment, _), offset)| match segment {
+                Text3dSegment::String(s) => Some((s.as_str(), *offset)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            strings,
+            [("a ", 0), ("b", 8), (" ", 10), ("c", 18), ("{e}", 39)]
+        );
+        assert_eq!(text.source_offsets.len(), text.segments.len());
+    }
+
+    #[test]
+    fn error_spans_and_excerpts() {
+        let source = "first line\nHP: {?missing: x} left";
+        let error = Text3d::parse_with_warnings(source, ParseBuilder::new()).unwrap_err();
+        assert!(matches!(error.error, ParseError::Custom(_)));
+        assert_eq!(&source[error.span.clone()], "{?missing:");
+        assert_eq!(error.excerpt, "HP: {?missing: x} left\n    ^^^^^^^^^^");
+        assert!(matches!(
+            Text3d::parse_raw(source),
+            Err(ParseError::Custom(_))
+        ));
+    }
+
+    #[test]
+    fn warnings_and_strict_mode() {
+        let source = "a {red: b **c";
+        let (text, warnings) = Text3d::parse_with_warnings(source, ParseBuilder::new()).unwrap();
+        assert!(!text.segments.is_empty());
+        let found: Vec<_> = warnings
+            .iter()
+            .map(|w| (format!("{}", w.error), &source[w.span.clone()]))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Unterminated markdown **.".to_owned(), "**"),
+                ("Unclosed bracket.".to_owned(), "{"),
+            ]
+        );
+        assert!(Text3d::parse_raw(source).is_ok());
+
+        let error = Text3d::parse_with_warnings(source, ParseBuilder::new().strict()).unwrap_err();
+        assert_eq!(format!("{}", error.error), found[0].0);
+        assert_eq!(error.excerpt, "a {red: b **c\n          ^^");
+        assert!(Text3d::parse_raw("x}").is_ok());
+        let error = Text3d::parse("x}", ParseBuilder::new().strict()).unwrap_err();
+        assert!(matches!(error, ParseError::UnexpectedBracket));
+    }
+
+    #[test]
+    fn dynamic_styles_and_lists_use_parse_value() {
+        let entity = Entity::from_raw_u32(7).unwrap();
+        let mut names = Vec::new();
+        let text = Text3d::parse(
+            "{@hp: a}{#each items: b}",
+            ParseBuilder::new().with_parse_value(|s| {
+                names.push(s.to_owned());
+                let segment = match s {
+                    "@hp" => Text3dSegment::Style {
+                        style: entity,
+                        offset: 0,
+                    },
+                    _ => Text3dSegment::Each {
+                        list: entity,
+                        offset: 0,
+                    },
+                };
+                Ok((segment, Default::default()))
+            }),
+        )
+        .unwrap();
+        assert_eq!(names, ["@hp", "#each items"]);
+        assert!(matches!(
+            text.segments[0].0,
+            Text3dSegment::Style { style, offset: 1 } if style == entity
+        ));
+        assert!(matches!(
+            text.segments[2].0,
+            Text3dSegment::Each { list, offset: 1 } if list == entity
+        ));
+        let error = Text3d::parse(
+            "{@hp: a}",
+            ParseBuilder::new()
+                .with_parse_value(|s| Ok((Text3dSegment::String(s.into()), Default::default()))),
+        )
+        .unwrap_err();
+        assert!(matches!(error, ParseError::BadCommand(_)));
+    }
+
+    /// Strings and their styles, parsed with markdown blocks.
+    fn blocks(text: &str, mode: WhitespaceMode) -> Vec<(String, SegmentStyle)> {
+        Text3d::parse(
+            text,
+            ParseBuilder::new()
+                .with_markdown_blocks(true)
+                .with_whitespace(mode),
+        )
+        .unwrap()
+        .segments
+        .into_iter()
+        .filter_map(|(segment, style)| match segment {
+            Text3dSegment::String(s) => Some((s, style)),
+            _ => None,
+        })
+        .collect()
+    }
+
+    /// Strings and their left margins, parsed with markdown blocks.
+    fn margins(text: &str) -> Vec<(String, Option<f32>)> {
+        blocks(text, WhitespaceMode::PreserveNewlines)
+            .into_iter()
+            .map(|(s, style)| (s, style.paragraph.margin_left))
+            .collect()
+    }
+
+    #[test]
+    fn markdown_headings() {
+        let parsed = blocks("# Title\nbody", WhitespaceMode::PreserveNewlines);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].0, "Title");
+        assert_eq!(parsed[0].1.size, Some(SegmentSize::Multiply(2.0)));
+        assert_eq!(parsed[0].1.weight, Some(Weight::BOLD));
+        assert_eq!(parsed[1], ("\nbody".to_owned(), SegmentStyle::default()));
+
+        let parsed = blocks("#### Four\n##### five\n#none", WhitespaceMode::PreserveTabs);
+        assert_eq!(parsed[0].0, "Four");
+        assert_eq!(parsed[0].1.size, Some(SegmentSize::Multiply(1.25)));
+        assert_eq!(
+            parsed[1],
+            ("\n##### five\n#none".to_owned(), SegmentStyle::default())
+        );
+    }
+
+    #[test]
+    fn markdown_lists() {
+        let item = |s: &str, margin: f32| (s.to_owned(), Some(margin));
+        let line = || ("\n".to_owned(), None);
+        assert_eq!(
+            margins("- a\n* b\n  + c\nd"),
+            [
+                item("• ", 1.5),
+                item("a", 1.5),
+                line(),
+                item("• ", 1.5),
+                item("b", 1.5),
+                line(),
+                item("• ", 3.0),
+                item("c", 3.0),
+                ("\nd".to_owned(), None),
+            ]
+        );
+        assert_eq!(
+            margins("1. one\n2) two\n10. ten"),
+            [
+                item("1. ", 1.5),
+                item("one", 1.5),
+                line(),
+                item("2) ", 1.5),
+                item("two", 1.5),
+                line(),
+                item("10. ", 1.5),
+                item("ten", 1.5),
+            ]
+        );
+        let parsed = blocks("- a", WhitespaceMode::PreserveNewlines);
+        assert_eq!(parsed[0].1.paragraph.indent, Some(0.0));
+        assert_eq!(parsed[0].1.paragraph.hanging_marker, Some(true));
+    }
+
+    #[test]
+    fn markdown_block_quotes() {
+        let line = || ("\n".to_owned(), None);
+        assert_eq!(
+            margins("> q\n> > nested\n> - item"),
+            [
+                ("q".to_owned(), Some(1.0)),
+                line(),
+                ("nested".to_owned(), Some(2.0)),
+                line(),
+                ("• ".to_owned(), Some(2.5)),
+                ("item".to_owned(), Some(2.5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_inline_code() {
+        let parsed = blocks("a `co{de}` b `x", WhitespaceMode::PreserveNewlines);
+        let strings: Vec<_> = parsed
+            .iter()
+            .map(|(s, style)| (s.as_str(), style.font.as_deref()))
+            .collect();
+        assert_eq!(
+            strings,
+            [("a ", None), ("co{de}", Some("monospace")), (" b `x", None)]
+        );
+        assert_eq!(
+            Text3d::parse_raw("a `b`").unwrap().get_single(),
+            Some("a `b`")
+        );
+    }
+
+    #[test]
+    fn markdown_blocks_are_opt_in() {
+        let source = "# Title\n- a\n> b";
+        assert_eq!(
+            Text3d::parse_raw(source).unwrap().get_single(),
+            Some(source)
+        );
+        for mode in [WhitespaceMode::Collapse, WhitespaceMode::Preserve] {
+            for (_, style) in blocks(source, mode) {
+                assert_eq!(style, SegmentStyle::default(), "{mode:?}");
+            }
+        }
+    }
+
+    fn whitespace(text: &str, mode: WhitespaceMode) -> Vec<String> {
+        Text3d::parse(text, ParseBuilder::new().with_whitespace(mode))
+            .unwrap()
+            .segments
+            .into_iter()
+            .filter_map(|(segment, _)| match segment {
+                Text3dSegment::String(s) => Some(s),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn whitespace_modes() {
+        let source = "a  b\n\n c\t d {bold: e }";
+        assert_eq!(
+            whitespace(source, WhitespaceMode::Collapse),
+            ["a b c d ", "e"]
+        );
+        assert_eq!(
+            whitespace(source, WhitespaceMode::PreserveNewlines),
+            ["a b\n\nc d ", "e"]
+        );
+        assert_eq!(
+            whitespace(source, WhitespaceMode::PreserveTabs),
+            ["a b\n\nc\td ", "e"]
+        );
+        assert_eq!(
+            whitespace(source, WhitespaceMode::Preserve),
+            ["a  b\n\n c\t d ", " e "]
+        );
+        assert_eq!(
+            whitespace("{pre:\n  x\n   y}  z", WhitespaceMode::Collapse),
+            ["  x\n   y", " z"]
+        );
+    }
+
+    #[test]
+    fn default_whitespace_keeps_line_breaks() {
+        for source in ["a \n b", "a\n\n\nb", "a\t\tb", " a  b ", "{bold: a\n\nb}"] {
+            assert_eq!(
+                format!("{:?}", Text3d::parse_raw(source).unwrap().segments),
+                format!(
+                    "{:?}",
+                    Text3d::parse(
+                        source,
+                        ParseBuilder::new().with_whitespace(WhitespaceMode::PreserveNewlines)
+                    )
+                    .unwrap()
+                    .segments
+                ),
+            );
+        }
+        assert_eq!(
+            Text3d::parse_raw("a \n b").unwrap().get_single(),
+            Some("a\nb")
+        );
+        assert_eq!(
+            Text3d::parse_raw("a\n\n\nb").unwrap().get_single(),
+            Some("a\n\n\nb")
+        );
+        assert_eq!(
+            Text3d::parse_raw("a\t\tb").unwrap().get_single(),
+            Some("a b")
+        );
+    }
+
+    #[test]
+    fn value_format_after_colon() {
+        let mut styles = Vec::new();
+        let mut parse = |text: &str| {
+            Text3d::parse(
+                text,
+                ParseBuilder::new()
+                    .with_parse_style(|s: &str| {
+                        styles.push(s.to_owned());
+                        match s {
+                            "big" => Ok(SegmentStyle {
+                                size: Some(SegmentSize::Multiply(2.0)),
+                                ..Default::default()
+                            }),
+                            _ => Err(ParseError::Custom(format!("Unknown style {s}."))),
+                        }
+                    })
+                    .with_parse_value(|s: &str| {
+                        Ok((
+                            Text3dSegment::String(format!("<{s}>")),
+                            SegmentStyle::default(),
+                        ))
+                    }),
+            )
+        };
+        // A name that is not a style followed by a format is a value.
+        let text = parse("{hp:0.0}").unwrap();
+        assert!(matches!(&text.segments[0].0, Text3dSegment::String(s) if s == "<hp>"));
+        assert!(text.segments[0].1.format.is_some());
+        // Built-in and stylesheet styles followed by a format are styled text.
+        let text = parse("{bold:0}").unwrap();
+        assert_eq!(text.get_single(), Some("0"));
+        assert_eq!(text.segments[0].1.weight, Some(Weight::BOLD));
+        let text = parse("{big:0}").unwrap();
+        assert_eq!(text.get_single(), Some("0"));
+        assert_eq!(text.segments[0].1.size, Some(SegmentSize::Multiply(2.0)));
+        // Without a format after `:` an unknown style is an error, not a value.
+        assert!(parse("{hp: 0}").is_err());
+        assert!(parse("{hp:text}").is_err());
+        assert_eq!(parse("{bold:0,}").unwrap().get_single(), Some("0,"));
+        // The stylesheet is asked once per scope.
+        assert_eq!(styles, ["hp", "big", "hp", "hp"]);
+    }
+}
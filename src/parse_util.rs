@@ -1,3 +1,5 @@
+use std::{iter::repeat_n, ops::Range};
+
 use bevy::ecs::entity::Entity;
 
 use crate::{SegmentStyle, Style, Text3dSegment, Weight};
@@ -47,6 +49,51 @@ pub enum ParseError {
     MissingStyle(String),
     #[error("{0}")]
     Custom(String),
+    #[error("Unclosed bracket.")]
+    UnclosedBracket,
+    #[error("Unexpected closing bracket.")]
+    UnexpectedBracket,
+    #[error("Unterminated markdown {0}.")]
+    UnterminatedMarkdown(&'static str),
+}
+
+/// [`ParseError`] with its location in the source string.
+#[derive(Debug, thiserror::Error)]
+#[error("{error}\n{excerpt}")]
+pub struct SpannedParseError {
+    pub error: ParseError,
+    /// Byte range in the source string.
+    pub span: Range<usize>,
+    /// The line containing the error, with the range underlined.
+    pub excerpt: String,
+}
+
+/// A recoverable issue found when parsing rich text, returned as an error in strict mode.
+pub type ParseWarning = SpannedParseError;
+
+impl SpannedParseError {
+    pub fn new(source: &str, error: ParseError, span: Range<usize>) -> Self {
+        let start = span.start.min(source.len());
+        let end = span.end.clamp(start, source.len());
+        let line_start = source[..start].rfind('\n').map(|x| x + 1).unwrap_or(0);
+        let line_end = source[start..]
+            .find('\n')
+            .map(|x| x + start)
+            .unwrap_or(source.len());
+        let offset = source[line_start..start].chars().count();
+        let len = source[start..end.min(line_end)].chars().count().max(1);
+        let excerpt = format!(
+            "{}\n{}{}",
+            &source[line_start..line_end],
+            " ".repeat(offset),
+            "^".repeat(len)
+        );
+        Self {
+            error,
+            span,
+            excerpt,
+        }
+    }
 }
 
 /// Output for parsing condition.
@@ -65,6 +112,12 @@ pub trait ParseStyleFn {
     fn call(&mut self, s: &str) -> Result<SegmentStyle, ParseError>;
 }
 
+/// Parses values, and dynamic styles and lists in [`Text3d::parse`](crate::Text3d::parse).
+///
+/// * Dynamic styles are passed as `@name` and should return [`Text3dSegment::Style`].
+/// * Lists are passed as `#each name` and should return [`Text3dSegment::Each`].
+///
+/// The `offset` of these segments is set by the parser.
 pub trait ParseValueFn {
     fn call(&mut self, index: usize, s: &str) -> Result<(Text3dSegment, SegmentStyle), ParseError>;
 }
@@ -117,6 +170,42 @@ impl<T: FnMut(&str) -> Result<ConditionOutput, ParseError>> ParseConditionFn for
     }
 }
 
+/// How whitespaces are handled when parsing rich text, similar to `white-space` in css.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum WhitespaceMode {
+    /// Consecutive whitespaces including line breaks are rendered as one whitespace.
+    Collapse,
+    /// Whitespaces are rendered as is and styled scopes are not trimmed.
+    ///
+    /// Useful for ascii art, code snippets and pre-formatted tables.
+    Preserve,
+    /// Consecutive whitespaces are rendered either as one whitespace or multiple line breaks.
+    #[default]
+    PreserveNewlines,
+    /// Same as [`WhitespaceMode::PreserveNewlines`], but whitespaces containing a tab and no line breaks
+    /// are rendered as a tab, for [`Text3dStyle::tab_stops`](crate::Text3dStyle::tab_stops).
+    PreserveTabs,
+}
+
+impl WhitespaceMode {
+    /// Write a run of consecutive whitespaces to `buffer`.
+    pub(crate) fn push_whitespace(self, whitespaces: &str, buffer: &mut String) {
+        match self {
+            WhitespaceMode::Collapse => buffer.push(' '),
+            WhitespaceMode::Preserve => buffer.push_str(whitespaces),
+            WhitespaceMode::PreserveNewlines | WhitespaceMode::PreserveTabs => {
+                match whitespaces.matches('\n').count() {
+                    0 if self == WhitespaceMode::PreserveTabs && whitespaces.contains('\t') => {
+                        buffer.push('\t')
+                    }
+                    0 => buffer.push(' '),
+                    n => buffer.extend(repeat_n('\n', n)),
+                }
+            }
+        }
+    }
+}
+
 /// Builder pattern input for parsing rich text.
 pub struct ParseBuilder<
     Style: ParseStyleFn = DefaultFn,
@@ -126,6 +215,9 @@ pub struct ParseBuilder<
     pub(crate) parse_style: Style,
     pub(crate) parse_value: Value,
     pub(crate) parse_condition: Condition,
+    pub(crate) strict: bool,
+    pub(crate) whitespace: WhitespaceMode,
+    pub(crate) markdown_blocks: bool,
 }
 
 impl Default for ParseBuilder {
@@ -140,10 +232,34 @@ impl ParseBuilder {
             parse_style: DefaultFn,
             parse_value: DefaultFn,
             parse_condition: DefaultFn,
+            strict: false,
+            whitespace: WhitespaceMode::PreserveNewlines,
+            markdown_blocks: false,
         }
     }
 }
 
+impl<A: ParseStyleFn, B: ParseValueFn, C: ParseConditionFn> ParseBuilder<A, B, C> {
+    /// Return recoverable issues like unclosed brackets and unterminated markdown as errors.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Set how whitespaces are handled, by default [`WhitespaceMode::PreserveNewlines`].
+    pub fn with_whitespace(mut self, mode: WhitespaceMode) -> Self {
+        self.whitespace = mode;
+        self
+    }
+
+    /// Parse headings, lists and block quotes at the start of a line and `` `inline code` ``,
+    /// disabled by default.
+    pub fn with_markdown_blocks(mut self, enabled: bool) -> Self {
+        self.markdown_blocks = enabled;
+        self
+    }
+}
+
 impl<B: ParseValueFn, C: ParseConditionFn> ParseBuilder<DefaultFn, B, C> {
     pub fn with_parse_style<F: FnMut(&str) -> Result<SegmentStyle, ParseError>>(
         self,
@@ -153,6 +269,9 @@ impl<B: ParseValueFn, C: ParseConditionFn> ParseBuilder<DefaultFn, B, C> {
             parse_style: f,
             parse_value: self.parse_value,
             parse_condition: self.parse_condition,
+            strict: self.strict,
+            whitespace: self.whitespace,
+            markdown_blocks: self.markdown_blocks,
         }
     }
 }
@@ -166,6 +285,9 @@ impl<A: ParseStyleFn, C: ParseConditionFn> ParseBuilder<A, DefaultFn, C> {
             parse_style: self.parse_style,
             parse_value: f,
             parse_condition: self.parse_condition,
+            strict: self.strict,
+            whitespace: self.whitespace,
+            markdown_blocks: self.markdown_blocks,
         }
     }
 }
@@ -179,6 +301,9 @@ impl<A: ParseStyleFn, B: ParseValueFn> ParseBuilder<A, B, DefaultFn> {
             parse_style: self.parse_style,
             parse_value: self.parse_value,
             parse_condition: f,
+            strict: self.strict,
+            whitespace: self.whitespace,
+            markdown_blocks: self.markdown_blocks,
         }
     }
 }
@@ -10,7 +10,9 @@ use bevy::{
     ecs::resource::Resource,
     image::Image,
 };
-use cosmic_text::{Attrs, Buffer, Family, FontSystem, Metrics, Shaping, Style, Weight};
+use cosmic_text::{
+    fontdb::Database, Attrs, Buffer, Family, FontSystem, Metrics, Shaping, Style, Weight,
+};
 use ttf_parser::Face;
 
 use crate::{render::cache_glyph, StrokeJoin, Text3dPlugin, TextAtlas};
@@ -24,6 +26,8 @@ impl TextRenderer {
     pub fn new(font_system: FontSystem) -> Self {
         Self(Arc::new(Mutex::new(TextRendererInner {
             font_system,
+            shards: Vec::new(),
+            shard_fonts: 0,
             queue: VecDeque::new(),
         })))
     }
@@ -39,6 +43,22 @@ impl TextRenderer {
     pub fn try_lock(&mut self) -> Option<FontSystemGuard<'_>> {
         self.0.try_lock().ok().map(FontSystemGuard)
     }
+
+    /// Recreate the underlying [`FontSystem`] with a new locale, which affects font fallback.
+    ///
+    /// Loaded fonts are kept, existing text is not redrawn.
+    /// Does nothing if the locale is unchanged.
+    pub fn set_locale(&mut self, locale: impl Into<String>) {
+        let locale = locale.into();
+        let mut inner = self.0.lock().unwrap();
+        if inner.font_system.locale() == locale {
+            return;
+        }
+        let placeholder = FontSystem::new_with_locale_and_db(String::new(), Database::new());
+        let (_, db) = std::mem::replace(&mut inner.font_system, placeholder).into_locale_and_db();
+        inner.font_system = FontSystem::new_with_locale_and_db(locale, db);
+        inner.shards.clear();
+    }
 }
 
 /// Mutex guard over a [`FontSystem`].
@@ -61,6 +81,10 @@ impl DerefMut for FontSystemGuard<'_> {
 #[derive(Debug)]
 pub(crate) struct TextRendererInner {
     pub(crate) font_system: FontSystem,
+    /// Copies of `font_system` used to shape text in parallel.
+    pub(crate) shards: Vec<FontSystem>,
+    /// Number of fonts in the database when `shards` were copied.
+    pub(crate) shard_fonts: usize,
     pub(crate) queue: VecDeque<(AssetId<TextAtlas>, TextAtlas, Image)>,
 }
 
@@ -138,7 +162,9 @@ impl TextRenderer {
         let scale_factor = settings.scale_factor;
         move || {
             let mut guard = font_system.0.lock().unwrap();
-            let TextRendererInner { font_system, queue } = guard.deref_mut();
+            let TextRendererInner {
+                font_system, queue, ..
+            } = guard.deref_mut();
             for (id, mut atlas, mut image, workload) in workload {
                 for (str, style) in workload {
                     let mut buffer = Buffer::new(font_system, Metrics::new(style.size, style.size));
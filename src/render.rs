@@ -1,28 +1,38 @@
 use bevy::{
     asset::{AssetId, Assets, RenderAssetUsages},
     ecs::{
-        change_detection::DetectChanges,
+        change_detection::{DetectChanges, DetectChangesMut},
+        entity::Entity,
         system::{Local, Query, Res, ResMut},
         world::{Mut, Ref},
     },
     image::Image,
     math::{IRect, IVec2, Rect, Vec2, Vec3, Vec4},
     mesh::{Indices, Mesh, Mesh2d, Mesh3d, PrimitiveTopology, VertexAttributeValues},
+    tasks::ComputeTaskPool,
+    time::Time,
+};
+use cosmic_text::{
+    Attrs, Buffer, BufferLine, Family, FontSystem, LayoutGlyph, LayoutRun, Metrics, Shaping,
+    Weight, Wrap,
 };
-use cosmic_text::{Attrs, Buffer, Family, FontSystem, LayoutGlyph, Metrics, Shaping, Weight, Wrap};
 use std::{mem, num::NonZero};
 use ttf_parser::{Face, GlyphId};
 
 use crate::{
-    fetch::{FetchedCondition, FetchedText},
+    fetch::{FetchedCondition, FetchedItem, FetchedList, FetchedStyle, FetchedText},
     layers::{DrawRequest, DrawType, Layer},
     line::LineRun,
-    mesh_util::ExtractedMesh,
+    mesh_util::{write_revealed_indices, ExtractedMesh, GlyphData},
+    modifier::SpanStarts,
+    prepare::TextRendererInner,
+    reveal::RevealGlyph,
     styling::{FloatDecimal, GlyphEntry},
     tess::PathEncoder,
     text3d::{Text3d, Text3dSegment},
-    SegmentStyle, StrokeJoin, Text3dBounds, Text3dDimensionOut, Text3dPlugin, Text3dStyle,
-    TextAtlas, TextAtlasHandle, TextRenderer,
+    GlyphModifiers, ParagraphStyle, SegmentStyle, SpanEffect, SpanEffects, StrokeJoin, TabAlign,
+    TabStop, Text3dBounds, Text3dDimensionOut, Text3dHyphenation, Text3dPlugin, Text3dReveal,
+    Text3dStyle, TextAtlas, TextAtlasHandle, TextRenderer, SOFT_HYPHEN,
 };
 
 fn default_mesh() -> Mesh {
@@ -66,13 +76,82 @@ mod private {
     }
 }
 
+/// Repeat the scope of each [`Text3dSegment::Each`] for its items, replacing fields with [`Text3dSegment::Extract`].
+///
+/// Offsets of scopes containing a list are adjusted, `origins` maps each output segment to its index in `segments`.
+fn expand_lists<'t>(
+    segments: &[(Text3dSegment, SegmentStyle)],
+    lists: impl Fn(Entity) -> Option<&'t [Entity]>,
+    items: impl Fn(Entity) -> Option<&'t FetchedItem>,
+    out: &mut Vec<(Text3dSegment, SegmentStyle)>,
+    origins: &mut Vec<usize>,
+) {
+    out.clear();
+    origins.clear();
+    // Output position of each open scope and the number of input segments left in it.
+    let mut scopes: Vec<(usize, usize)> = Vec::new();
+    let mut i = 0;
+    while i < segments.len() {
+        let (segment, style) = &segments[i];
+        let consumed = match segment {
+            Text3dSegment::Each { list, offset } => {
+                let body = &segments[i + 1..(i + 1 + offset).min(segments.len())];
+                for item in lists(*list).unwrap_or_default() {
+                    let item = items(*item);
+                    for (j, (segment, style)) in body.iter().enumerate() {
+                        let segment = match segment {
+                            Text3dSegment::Field(name) => {
+                                match item.and_then(|item| item.field(name)) {
+                                    Some(entity) => Text3dSegment::Extract(entity),
+                                    None => Text3dSegment::String(String::new()),
+                                }
+                            }
+                            segment => segment.clone(),
+                        };
+                        out.push((segment, style.clone()));
+                        origins.push(i + 1 + j);
+                    }
+                }
+                1 + body.len()
+            }
+            _ => {
+                out.push((segment.clone(), style.clone()));
+                origins.push(i);
+                1
+            }
+        };
+        for (_, remaining) in &mut scopes {
+            *remaining = remaining.saturating_sub(consumed);
+        }
+        if let Text3dSegment::SkipIf { offset, .. }
+        | Text3dSegment::SkipIfExpr { offset, .. }
+        | Text3dSegment::Style { offset, .. } = segment
+        {
+            scopes.push((out.len() - 1, *offset));
+        }
+        while let Some((position, _)) = scopes.pop_if(|(_, remaining)| *remaining == 0) {
+            let len = out.len() - position - 1;
+            if let Text3dSegment::SkipIf { offset, .. }
+            | Text3dSegment::SkipIfExpr { offset, .. }
+            | Text3dSegment::Style { offset, .. } = &mut out[position].0
+            {
+                *offset = len;
+            }
+        }
+        i += consumed;
+    }
+}
+
 pub fn text_render(
     settings: Res<Text3dPlugin>,
+    time: Res<Time>,
     font_system: ResMut<TextRenderer>,
+    hyphenation: Option<Res<Text3dHyphenation>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut images: ResMut<Assets<Image>>,
     mut atlases: ResMut<Assets<TextAtlas>>,
     mut text_query: Query<(
+        Entity,
         Ref<Text3d>,
         Ref<Text3dBounds>,
         Ref<Text3dStyle>,
@@ -80,18 +159,48 @@ pub fn text_render(
         Option<&mut Mesh2d>,
         Option<&mut Mesh3d>,
         &mut Text3dDimensionOut,
+        Option<Ref<GlyphModifiers>>,
+        Option<Mut<Text3dReveal>>,
     )>,
     segments: Query<Ref<FetchedText>>,
     conditions: Query<Ref<FetchedCondition>>,
+    // Grouped to stay within the system parameter limit.
+    (fetched_styles, lists, items): (
+        Query<Ref<FetchedStyle>>,
+        Query<Ref<FetchedList>>,
+        Query<Ref<FetchedItem>>,
+    ),
     mut draw_requests: Local<Vec<DrawRequest>>,
-    mut sort_buffer: Local<Vec<(Layer, [u16; 6])>>,
+    mut sort_buffer: Local<Vec<(Layer, usize, [u16; 6])>>,
     mut rng: Local<private::TextRng>,
+    mut reveal_glyphs: Local<Vec<RevealGlyph>>,
+    // Grouped to stay within the system parameter limit.
+    (
+        mut line_glyph_counts,
+        mut line_offsets,
+        mut jobs,
+        mut line_glyphs,
+        mut strings,
+        mut resolved_segments,
+        mut segment_origins,
+        mut rich_text,
+    ): (
+        Local<Vec<usize>>,
+        Local<Vec<usize>>,
+        Local<Vec<LayoutJob>>,
+        Local<Vec<LayoutGlyph>>,
+        Local<Vec<String>>,
+        Local<Vec<(Text3dSegment, SegmentStyle)>>,
+        Local<Vec<usize>>,
+        Local<Option<Buffer>>,
+    ),
 ) {
     let Ok(mut lock) = font_system.0.try_lock() else {
         return;
     };
+    let fonts_changed = font_system.is_changed();
     let mut redraw = false;
-    if font_system.is_changed() {
+    if fonts_changed || hyphenation.as_ref().is_some_and(|x| x.is_changed()) {
         redraw = true;
     }
     // Add asynchronously drawn text.
@@ -101,10 +210,30 @@ pub fn text_render(
         let _ = atlases.insert(id, atlas);
         redraw = true;
     }
-    let font_system = &mut lock.font_system;
+    let TextRendererInner {
+        font_system,
+        shards,
+        shard_fonts,
+        ..
+    } = &mut *lock;
+    // Fonts can be added through a clone of `TextRenderer` without triggering change detection.
+    let fonts_added = *shard_fonts != font_system.db().len();
+    if fonts_added {
+        redraw = true;
+    }
     let scale_factor = settings.scale_factor;
-    'main: for (text, bounds, styling, atlas, mut mesh2d, mut mesh3d, mut output) in
-        text_query.iter_mut()
+    'main: for (
+        entity,
+        text,
+        bounds,
+        styling,
+        atlas,
+        mut mesh2d,
+        mut mesh3d,
+        mut output,
+        modifiers,
+        reveal,
+    ) in text_query.iter_mut()
     {
         let Some(atlas) = atlases.get_mut(atlas.0.id()) else {
             continue;
@@ -138,6 +267,10 @@ pub fn text_render(
             && !text.is_changed()
             && !bounds.is_changed()
             && !styling.is_changed()
+            && !modifiers
+                .as_ref()
+                .is_some_and(|x| x.is_changed() || x.is_animated())
+            && !reveal.as_ref().is_some_and(|x| x.is_added())
         {
             let mut unchanged = true;
             for segment in &text.segments {
@@ -153,8 +286,55 @@ pub fn text_render(
                         break;
                     }
                 }
+                if let Text3dSegment::Style { style, .. } = &segment.0 {
+                    if fetched_styles.get(*style).is_ok_and(|x| x.is_changed()) {
+                        unchanged = false;
+                        break;
+                    }
+                }
+                if let Text3dSegment::Each { list, .. } = &segment.0 {
+                    if lists.get(*list).is_ok_and(|list| {
+                        list.is_changed()
+                            || list.0.iter().any(|item| {
+                                items.get(*item).is_ok_and(|item| {
+                                    item.is_changed()
+                                        || item.0.iter().any(|(_, field)| {
+                                            segments.get(*field).is_ok_and(|x| x.is_changed())
+                                        })
+                                })
+                            })
+                    }) {
+                        unchanged = false;
+                        break;
+                    }
+                }
+                if let Text3dSegment::SkipIfExpr { condition, .. } = &segment.0 {
+                    if condition.entities().any(|entity| {
+                        conditions.get(entity).is_ok_and(|x| x.is_changed())
+                            || segments.get(entity).is_ok_and(|x| x.is_changed())
+                    }) {
+                        unchanged = false;
+                        break;
+                    }
+                }
             }
             if unchanged {
+                // Revealing glyphs only changes which quads are drawn.
+                if let Some(reveal) = reveal.as_ref().filter(|x| x.is_changed()) {
+                    if let Some(mesh) = get_mesh(&mut mesh2d, &mut mesh3d, &mut meshes) {
+                        let mut indices = match mesh.remove_indices() {
+                            Some(Indices::U16(indices)) => indices,
+                            _ => Vec::new(),
+                        };
+                        indices.clear();
+                        write_revealed_indices(
+                            output.quads.iter().copied(),
+                            reveal.revealed(),
+                            &mut indices,
+                        );
+                        mesh.insert_indices(Indices::U16(indices));
+                    }
+                }
                 let Some(image) = images.get(atlas.image.id()) else {
                     continue;
                 };
@@ -181,22 +361,72 @@ pub fn text_render(
             }
         }
 
-        let mut buffer = Buffer::new(
-            font_system,
-            Metrics::new(styling.size, styling.size * styling.line_height),
+        let resolve = resolve_segments(
+            &text.segments,
+            &lists,
+            &items,
+            &fetched_styles,
+            &mut resolved_segments,
+            &mut segment_origins,
         );
+        let text_segments: &[(Text3dSegment, SegmentStyle)] = if resolve {
+            &resolved_segments
+        } else {
+            &text.segments
+        };
+
+        let metrics = Metrics::new(styling.size, styling.size * styling.line_height);
         let width_limit = if let Some(world_scale) = styling.world_scale {
             bounds.width * styling.size / world_scale.x
         } else {
             bounds.width
         };
-        buffer.set_wrap(Wrap::WordOrGlyph);
-        buffer.set_size(Some(width_limit), None);
-        buffer.set_tab_width(styling.tab_width);
+        // Lines are split here and copied to the cached layout afterwards.
+        let rich_text = rich_text.get_or_insert_with(|| Buffer::new_empty(metrics));
+
+        let dictionary = styling
+            .hyphenate
+            .as_deref()
+            .and_then(|language| hyphenation.as_deref()?.get(language));
+        // Format values and insert soft hyphens, `strings` is empty if not needed.
+        strings.clear();
+        if dictionary.is_some()
+            || text_segments.iter().any(|(segment, style)| {
+                matches!(segment, Text3dSegment::Extract(_)) && style.format.is_some()
+            })
+        {
+            let locale = settings
+                .locale
+                .as_deref()
+                .unwrap_or_else(|| font_system.locale());
+            for (segment, style) in text_segments {
+                let value = match segment {
+                    Text3dSegment::String(s) => s.as_str(),
+                    Text3dSegment::Extract(e) => segments
+                        .get(*e)
+                        .map(|x| x.into_inner().as_str())
+                        .unwrap_or(""),
+                    _ => "",
+                };
+                let mut result = match (segment, style.format) {
+                    (Text3dSegment::Extract(_), Some(format)) => format.format_str(value, locale),
+                    _ => value.to_string(),
+                };
+                if let Some(dictionary) = dictionary {
+                    let value = mem::take(&mut result);
+                    dictionary.insert_soft_hyphens(&value, &mut result);
+                }
+                strings.push(result);
+            }
+        }
 
         let mut to_skip = 0;
-        buffer.set_rich_text(
-            text.segments
+        // Offset of each segment in the laid out text and offsets of inserted soft hyphens.
+        let mut text_starts = vec![0; text_segments.len()];
+        let mut soft_hyphens = Vec::new();
+        let mut text_len = 0;
+        rich_text.set_rich_text(
+            text_segments
                 .iter()
                 .enumerate()
                 .filter_map(|(idx, (text, style))| {
@@ -204,29 +434,67 @@ pub fn text_render(
                         to_skip -= 1;
                         return None;
                     }
-                    Some((
-                        match text {
-                            Text3dSegment::String(s) => s.as_str(),
-                            Text3dSegment::Extract(e) => segments
-                                .get(*e)
-                                .map(|x| x.into_inner().as_str())
-                                .unwrap_or(""),
-                            Text3dSegment::Image { image: _, width } => {
-                                settings.get_placeholder_glyph(*width)
-                            }
-                            Text3dSegment::SkipIf {
-                                condition,
-                                skip_if,
-                                offset,
-                            } => {
-                                if let Ok(condition) = conditions.get(*condition) {
-                                    if condition.0 == *skip_if {
-                                        to_skip = *offset;
-                                    }
+                    let string = match text {
+                        Text3dSegment::String(_) | Text3dSegment::Extract(_)
+                            if !strings.is_empty() =>
+                        {
+                            strings[idx].as_str()
+                        }
+                        Text3dSegment::String(s) => s.as_str(),
+                        Text3dSegment::Extract(e) => segments
+                            .get(*e)
+                            .map(|x| x.into_inner().as_str())
+                            .unwrap_or(""),
+                        Text3dSegment::Image { image: _, width } => {
+                            settings.get_placeholder_glyph(*width)
+                        }
+                        Text3dSegment::SkipIf {
+                            condition,
+                            skip_if,
+                            offset,
+                        } => {
+                            if let Ok(condition) = conditions.get(*condition) {
+                                if condition.0 == *skip_if {
+                                    to_skip = *offset;
                                 }
-                                return None;
                             }
-                        },
+                            return None;
+                        }
+                        Text3dSegment::SkipIfExpr {
+                            condition,
+                            skip_if,
+                            offset,
+                        } => {
+                            let value = condition.evaluate(
+                                |e| conditions.get(e).ok().map(|x| x.0),
+                                |e| {
+                                    segments
+                                        .get(e)
+                                        .ok()
+                                        .map(|x| x.0.trim().parse().unwrap_or(f64::NAN))
+                                },
+                            );
+                            if value == Some(*skip_if) {
+                                to_skip = *offset;
+                            }
+                            return None;
+                        }
+                        Text3dSegment::Field(_) => "",
+                        Text3dSegment::Reveal(_)
+                        | Text3dSegment::Style { .. }
+                        | Text3dSegment::Each { .. } => return None,
+                    };
+                    if let Text3dSegment::String(s) = text {
+                        if dictionary.is_some() && !s.contains(SOFT_HYPHEN) {
+                            soft_hyphens.extend(
+                                string.match_indices(SOFT_HYPHEN).map(|(i, _)| text_len + i),
+                            );
+                        }
+                    }
+                    text_starts[idx] = text_len;
+                    text_len += string.len();
+                    Some((
+                        string,
                         match text {
                             Text3dSegment::Image { .. } => style
                                 .as_attr(&styling)
@@ -244,17 +512,169 @@ pub fn text_render(
             None,
         );
 
-        buffer.shape_until_scroll(font_system, true);
+        // Fonts may have changed, so the cached shaping cannot be trusted.
+        if redraw {
+            output.buffer = None;
+        }
+        // The width, metrics or wrapping of every line may have changed,
+        // changed lines and paragraph styles are found below.
+        let relayout_all = output.buffer.is_none() || bounds.is_changed() || styling.is_changed();
+        let mut buffer = output
+            .buffer
+            .take()
+            .unwrap_or_else(|| Buffer::new_empty(metrics));
+        buffer.set_metrics(metrics);
+        buffer.set_wrap(styling.wrap.into());
+        buffer.set_size(Some(width_limit), None);
+        // Tabs are shaped with the tab width.
+        if buffer.tab_width() != styling.tab_width {
+            for line in buffer.lines.iter_mut() {
+                if line.text().contains('\t') {
+                    line.reset_shaping();
+                }
+            }
+        }
+        buffer.set_tab_width(styling.tab_width);
+        let mut stale_lines = Vec::new();
+        update_lines(&mut buffer, &rich_text.lines, &mut stale_lines);
+        let paragraphs: Vec<ParagraphStyle> = buffer
+            .lines
+            .iter()
+            .map(|line| {
+                let segment = line.attrs_list().get_span(0).metadata;
+                match text_segments.get(segment) {
+                    Some((_, style)) => style.paragraph.apply(styling.paragraph),
+                    None => styling.paragraph,
+                }
+            })
+            .collect();
+        // Dynamic styles can change the paragraph style of a line without changing its text.
+        for (index, paragraph) in paragraphs.iter().enumerate() {
+            if !stale_lines[index] && output.paragraphs.get(index) != Some(paragraph) {
+                buffer.lines[index].reset_layout();
+                stale_lines[index] = true;
+            }
+        }
+        jobs.push(LayoutJob {
+            entity,
+            resolved: resolve.then(|| {
+                (
+                    mem::take(&mut *resolved_segments),
+                    mem::take(&mut *segment_origins),
+                )
+            }),
+            buffer,
+            paragraphs,
+            text_starts,
+            soft_hyphens,
+            relayout_all,
+            laid_out: 0,
+            width_limit,
+            size: styling.size,
+            wrap: styling.wrap.into(),
+            tab_width: styling.tab_width,
+        });
+    }
+
+    // Shaping does not touch the atlas, so it can run on copies of the font system in parallel.
+    if fonts_changed || fonts_added || shards.len() != settings.layout_threads {
+        *shard_fonts = font_system.db().len();
+        shards.clear();
+        if settings.layout_threads > 0 {
+            share_font_data(font_system);
+        }
+        shards.extend((0..settings.layout_threads).map(|_| {
+            FontSystem::new_with_locale_and_db(
+                font_system.locale().to_string(),
+                font_system.db().clone(),
+            )
+        }));
+    }
+    let pool = ComputeTaskPool::try_get().filter(|_| !shards.is_empty() && jobs.len() > 1);
+    if let Some(pool) = pool {
+        let chunk_size = jobs.len().div_ceil(shards.len());
+        pool.scope(|scope| {
+            for (shard, chunk) in shards.iter_mut().zip(jobs.chunks_mut(chunk_size)) {
+                scope.spawn(async move {
+                    for job in chunk {
+                        job.layout(shard);
+                    }
+                });
+            }
+        });
+    } else {
+        for job in jobs.iter_mut() {
+            job.layout(font_system);
+        }
+    }
+
+    // Glyphs are drawn to the atlas and meshes are built on a single thread.
+    for LayoutJob {
+        entity,
+        resolved,
+        buffer,
+        paragraphs,
+        text_starts,
+        soft_hyphens,
+        laid_out,
+        ..
+    } in jobs.drain(..)
+    {
+        let Ok((
+            _,
+            text,
+            _,
+            styling,
+            atlas,
+            mut mesh2d,
+            mut mesh3d,
+            mut output,
+            modifiers,
+            mut reveal,
+        )) = text_query.get_mut(entity)
+        else {
+            continue;
+        };
+        let Some(atlas) = atlases.get_mut(atlas.0.id()) else {
+            continue;
+        };
+        let atlas = atlas.into_inner();
+        let (text_segments, segment_origins) = match &resolved {
+            Some((segments, origins)) => (segments.as_slice(), origins.as_slice()),
+            None => (text.segments.as_slice(), &[][..]),
+        };
+        let source_offsets = match text.source_offsets.len() == text.segments.len() {
+            true => text.source_offsets.as_slice(),
+            false => &[],
+        };
+        output.paragraphs.clone_from(&paragraphs);
+        output.laid_out_lines = laid_out;
+        let now = time.elapsed_secs();
+        if text.is_changed() {
+            output.started = Some(now);
+        }
+        let age = now - *output.started.get_or_insert(now);
+        let buffer = &*output.buffer.insert(buffer);
 
         let Some(mesh) = get_mesh(&mut mesh2d, &mut mesh3d, &mut meshes) else {
             continue;
         };
 
-        let mut mesh = ExtractedMesh::new(mesh, &mut sort_buffer, &styling);
+        let mut mesh = ExtractedMesh::new(mesh, &mut sort_buffer, &styling).with_modifiers(
+            modifiers.as_deref(),
+            now,
+            age,
+        );
+        // Hidden glyphs are still built, so revealing them does not need a new layout.
+        mesh.revealed = reveal.as_ref().map(|x| x.revealed()).unwrap_or(usize::MAX);
 
         let mut width = 0.0f32;
         let mut advance = 0.0f32;
         let mut real_index = 0;
+        let mut word_index = 0;
+        let mut word_started = false;
+        let mut span_starts = SpanStarts::default();
+        let mut span_effects = SpanEffects::NONE;
 
         let mut height = 0.0f32;
 
@@ -266,22 +686,112 @@ pub fn text_render(
             mem::swap(image, &mut im);
         };
 
-        for run in buffer.layout_runs() {
-            width = width.max(run.line_w);
-            height = height.max(run.line_top + run.line_height);
+        line_glyph_counts.clear();
+        line_offsets.clear();
+        let mut line_offset = 0;
+        for line in &buffer.lines {
+            line_offsets.push(line_offset);
+            line_offset += line.text().len() + line.ending().as_str().len();
+        }
+
+        let mut paragraph_index = usize::MAX;
+        let mut spacing_after = 0.0;
+        let mut y_offset = 0.0;
+        for (line_index, run) in buffer.layout_runs().enumerate() {
+            let paragraph = paragraphs.get(run.line_i).copied().unwrap_or_default();
+            let mut indent_x = paragraph.margin_left * styling.size;
+            if mem::replace(&mut paragraph_index, run.line_i) != run.line_i {
+                if line_index > 0 {
+                    y_offset += (spacing_after + paragraph.spacing_before) * styling.size;
+                }
+                indent_x += paragraph.indent * styling.size;
+                // Place the list marker before the margin.
+                if paragraph.hanging_marker {
+                    if let Some(first) = run.glyphs.first() {
+                        indent_x -= run
+                            .glyphs
+                            .iter()
+                            .find(|glyph| glyph.metadata != first.metadata)
+                            .map_or(0.0, |glyph| glyph.x);
+                    }
+                }
+            }
+            spacing_after = paragraph.spacing_after;
+            let line_y = run.line_y + y_offset;
+            let has_tabs = !styling.tab_stops.is_empty() && run.text.contains('\t');
+            let soft_hyphen = run
+                .glyphs
+                .last()
+                .is_some_and(|glyph| run.text[glyph.start..glyph.end].contains(SOFT_HYPHEN));
+            let (glyphs, line_w) = if has_tabs || soft_hyphen {
+                line_glyphs.clear();
+                line_glyphs.extend_from_slice(run.glyphs);
+                let mut line_w = run.line_w;
+                if has_tabs {
+                    line_w =
+                        apply_tab_stops(&run, &styling.tab_stops, styling.size, &mut line_glyphs);
+                }
+                if soft_hyphen {
+                    line_w = show_soft_hyphen(font_system, &mut line_glyphs, line_w);
+                }
+                (line_glyphs.as_slice(), line_w)
+            } else {
+                (run.glyphs, run.line_w)
+            };
+            width = width.max(line_w + indent_x);
+            height = height.max(run.line_top + run.line_height + y_offset);
+            let line_first_glyph = real_index;
             let mut underline_run = LineRun::default();
             let mut strikethrough_run = LineRun::default();
-            for glyph_index in 0..run.glyphs.len() {
-                let glyph = &run.glyphs[glyph_index];
-                let Some((seg, attrs)) = text.segments.get(glyph.metadata) else {
+            for glyph_index in 0..glyphs.len() {
+                let glyph = &glyphs[glyph_index];
+                let Some((seg, attrs)) = text_segments.get(glyph.metadata) else {
                     continue;
                 };
-                let line_start = -run.line_w * styling.align.as_fac();
+                let cluster = &run.text[glyph.start..glyph.end];
+                // Words broken by wrapping are not preceded by whitespace in the paragraph.
+                if !cluster.chars().all(char::is_whitespace)
+                    && run.text[..glyph.start]
+                        .chars()
+                        .next_back()
+                        .is_none_or(char::is_whitespace)
+                {
+                    if word_started {
+                        word_index += 1;
+                    }
+                    word_started = true;
+                }
+                let segment = segment_origins
+                    .get(glyph.metadata)
+                    .copied()
+                    .unwrap_or(glyph.metadata);
+                if reveal.is_some() {
+                    reveal_glyphs.push(RevealGlyph {
+                        segment,
+                        char: cluster.chars().next().unwrap_or(' '),
+                    });
+                }
+                let offset = line_offsets.get(run.line_i).copied().unwrap_or(0) + glyph.start;
+                let char_index = match (source_offsets.get(segment), seg) {
+                    (Some(source), Text3dSegment::String(_)) => {
+                        let start = text_starts.get(glyph.metadata).copied().unwrap_or(0);
+                        let inserted = soft_hyphens.partition_point(|x| *x < offset)
+                            - soft_hyphens.partition_point(|x| *x < start);
+                        source + offset - start - inserted * SOFT_HYPHEN.len_utf8()
+                    }
+                    (Some(source), _) => *source,
+                    (None, _) => offset,
+                };
+                for effect in SpanEffect::ALL {
+                    if attrs.effects.contains(effect) && !span_effects.contains(effect) {
+                        span_starts[effect as usize] = real_index;
+                    }
+                }
+                span_effects = attrs.effects;
+                let line_start = -line_w * styling.align.as_fac() + indent_x;
 
                 styling.fill_draw_requests(seg, attrs, &mut draw_requests);
 
-                let magic_number = attrs.magic_number.unwrap_or(0.);
-
                 let scale_factor = if glyph.font_size <= settings.double_scale_factor_threshold {
                     scale_factor * 2.0
                 } else {
@@ -316,7 +826,7 @@ pub fn text_render(
                             let base = Vec2::new(glyph.x, glyph.y)
                                 + base
                                 + offset
-                                + Vec2::new(line_start, -run.line_y);
+                                + Vec2::new(line_start, -line_y);
 
                             mesh.cache_rectangle(
                                 base,
@@ -324,9 +834,17 @@ pub fn text_render(
                                 pixel_rect,
                                 color,
                                 layer,
-                                real_index,
-                                advance + line_advance,
-                                magic_number,
+                                GlyphData {
+                                    index: real_index,
+                                    segment,
+                                    line: line_index,
+                                    word: word_index,
+                                    char_index,
+                                    baseline: -line_y,
+                                    span_starts,
+                                    advance: advance + line_advance,
+                                    style: attrs,
+                                },
                                 category,
                                 &styling,
                                 &mut rng.0,
@@ -364,7 +882,7 @@ pub fn text_render(
 
                             let base = Vec2::new(glyph.x + bx, glyph.y + by)
                                 + offset
-                                + Vec2::new(line_start, -run.line_y);
+                                + Vec2::new(line_start, -line_y);
 
                             mesh.cache_rectangle(
                                 base,
@@ -372,9 +890,17 @@ pub fn text_render(
                                 pixel_rect,
                                 color,
                                 layer,
-                                real_index,
-                                advance + glyph.x,
-                                magic_number,
+                                GlyphData {
+                                    index: real_index,
+                                    segment,
+                                    line: line_index,
+                                    word: word_index,
+                                    char_index,
+                                    baseline: -line_y,
+                                    span_starts,
+                                    advance: advance + glyph.x,
+                                    style: attrs,
+                                },
                                 category,
                                 &styling,
                                 &mut rng.0,
@@ -386,8 +912,8 @@ pub fn text_render(
                                 *line = mode.new_run(
                                     mode.size(font_system, glyph.font_id, glyph.font_size),
                                     glyph_index,
-                                    run.glyphs,
-                                    &text.segments,
+                                    glyphs,
+                                    text_segments,
                                 );
                             }
                             let stroke_size = stroke.map(|x| x.get()).unwrap_or(0) as f32
@@ -406,7 +932,7 @@ pub fn text_render(
                                 continue;
                             };
                             let (min, max) =
-                                mode.boundary(run.glyphs, &text.segments, glyph_index, stroke_size);
+                                mode.boundary(glyphs, text_segments, glyph_index, stroke_size);
                             for ((min, uv_min), (max, uv_max)) in
                                 line.uv_range(min, max, stroke_size).iter()
                             {
@@ -421,8 +947,8 @@ pub fn text_render(
                                     continue;
                                 };
                                 let rect = Rect {
-                                    min: rect.min + offset + Vec2::new(line_start, -run.line_y),
-                                    max: rect.max + offset + Vec2::new(line_start, -run.line_y),
+                                    min: rect.min + offset + Vec2::new(line_start, -line_y),
+                                    max: rect.max + offset + Vec2::new(line_start, -line_y),
                                 };
                                 let result_rect = Rect {
                                     min: Vec2::new(
@@ -439,9 +965,17 @@ pub fn text_render(
                                     result_rect,
                                     color,
                                     layer,
-                                    real_index,
-                                    advance + min,
-                                    magic_number,
+                                    GlyphData {
+                                        index: real_index,
+                                        segment,
+                                        line: line_index,
+                                        word: word_index,
+                                        char_index,
+                                        baseline: -line_y,
+                                        span_starts,
+                                        advance: advance + min,
+                                        style: attrs,
+                                    },
                                     category,
                                     &styling,
                                     &mut rng.0,
@@ -452,7 +986,8 @@ pub fn text_render(
                 }
                 real_index += 1;
             }
-            advance += run.line_w;
+            line_glyph_counts.push(real_index - line_first_glyph);
+            advance += line_w;
         }
 
         let dimension = Vec2::new(width, height);
@@ -460,7 +995,7 @@ pub fn text_render(
         let offset = -*styling.anchor * dimension - center;
         let bb_min = Vec2::new(width * -styling.align.as_fac(), -height);
 
-        mesh.post_process(bb_min, dimension);
+        mesh.post_process(bb_min, dimension, &line_glyph_counts);
 
         if let Some(world_scale) = styling.world_scale {
             mesh.translate(|v| *v = (*v + offset) * world_scale / styling.size);
@@ -470,17 +1005,239 @@ pub fn text_render(
             output.dimension = dimension;
         }
 
+        mesh.post_translate();
+
         output.atlas_dimension = IVec2::new(image.width() as i32, image.height() as i32);
 
         mesh.pixel_to_uv(image);
+        drop(mesh);
+        output.quads.clear();
+        output.quads.extend(
+            sort_buffer
+                .iter()
+                .map(|(_, glyph, indices)| (*glyph, *indices)),
+        );
 
         if let Some(mut im) = images.get_mut(atlas.image.id()) {
             mem::swap(image, &mut im);
         };
+        if let Some(reveal) = &mut reveal {
+            reveal
+                .bypass_change_detection()
+                .set_glyphs(&mut reveal_glyphs);
+        }
         output.initialized = true;
     }
 }
 
+/// Expand lists and apply dynamic styles to `out`, returns false if `segments` can be used as is.
+///
+/// `origins` maps segments in `out` to their index in `segments`.
+fn resolve_segments(
+    segments: &[(Text3dSegment, SegmentStyle)],
+    lists: &Query<Ref<FetchedList>>,
+    items: &Query<Ref<FetchedItem>>,
+    styles: &Query<Ref<FetchedStyle>>,
+    out: &mut Vec<(Text3dSegment, SegmentStyle)>,
+    origins: &mut Vec<usize>,
+) -> bool {
+    origins.clear();
+    if !segments.iter().any(|(segment, _)| {
+        matches!(
+            segment,
+            Text3dSegment::Style { .. } | Text3dSegment::Each { .. }
+        )
+    }) {
+        return false;
+    }
+    expand_lists(
+        segments,
+        |list| lists.get(list).ok().map(|x| x.into_inner().0.as_slice()),
+        |item| items.get(item).ok().map(|x| x.into_inner()),
+        out,
+        origins,
+    );
+    for i in 0..out.len() {
+        let Text3dSegment::Style { style, offset } = out[i].0 else {
+            continue;
+        };
+        let Ok(fetched) = styles.get(style) else {
+            continue;
+        };
+        // Override outer scopes, but not styles set inside the scope.
+        let outer = out[i].1.clone();
+        let scope = outer.join(fetched.0.clone());
+        for (_, segment_style) in out.iter_mut().skip(i + 1).take(offset) {
+            *segment_style = scope.join(segment_style.changes_from(&outer));
+        }
+    }
+    true
+}
+
+/// Text that needs a new mesh, shaped before glyphs are drawn.
+pub(crate) struct LayoutJob {
+    entity: Entity,
+    /// Segments with lists expanded and dynamic styles applied, and the index of each segment in [`Text3d`].
+    ///
+    /// `None` if the segments of the text are used as is.
+    resolved: Option<(Vec<(Text3dSegment, SegmentStyle)>, Vec<usize>)>,
+    buffer: Buffer,
+    paragraphs: Vec<ParagraphStyle>,
+    /// Offset of each segment in the laid out text.
+    text_starts: Vec<usize>,
+    /// Offsets of soft hyphens inserted by hyphenation in the laid out text.
+    soft_hyphens: Vec<usize>,
+    /// Lay out all lines again, otherwise only lines without a layout are laid out.
+    relayout_all: bool,
+    /// Number of lines laid out by [`LayoutJob::layout`].
+    laid_out: usize,
+    width_limit: f32,
+    size: f32,
+    wrap: Wrap,
+    tab_width: u16,
+}
+
+impl LayoutJob {
+    /// Shape and lay out lines that changed.
+    ///
+    /// Lines are laid out once with their own width, `shape_until_scroll` is not used
+    /// since it lays out every line with the width of the buffer.
+    fn layout(&mut self, font_system: &mut FontSystem) {
+        let buffer = &mut self.buffer;
+        let font_size = buffer.metrics().font_size;
+        let ellipsize = buffer.ellipsize();
+        let hinting = buffer.hinting();
+        let monospace_width = buffer.monospace_width();
+        self.laid_out = 0;
+        for (index, line) in buffer.lines.iter_mut().enumerate() {
+            if self.relayout_all {
+                line.reset_layout();
+            } else if line.layout_opt().is_some() {
+                continue;
+            }
+            // Lines are wrapped with a single width so a positive indent narrows every line.
+            let paragraph = self.paragraphs[index];
+            let margin = paragraph.margin_left + paragraph.margin_right + paragraph.indent.max(0.0);
+            line.layout(
+                font_system,
+                font_size,
+                Some((self.width_limit - margin * self.size).max(0.0)),
+                self.wrap,
+                ellipsize,
+                monospace_width,
+                self.tab_width,
+                hinting,
+            );
+            self.laid_out += 1;
+        }
+    }
+}
+
+/// Map font files into memory shared by copies of the database, instead of each copy reading them again.
+fn share_font_data(font_system: &mut FontSystem) {
+    let db = font_system.db_mut();
+    let ids: Vec<_> = db.faces().map(|face| face.id).collect();
+    for id in ids {
+        // SAFETY: `FontSystem::get_font` maps font files the same way when a font is first used,
+        // this only does it before the database is copied.
+        unsafe {
+            db.make_shared_face_data(id);
+        }
+    }
+}
+
+/// Copy the lines of `source` to `buffer`, only lines that changed are reset and shaped again.
+///
+/// `stale` is set to whether each line needs a new layout.
+fn update_lines(buffer: &mut Buffer, source: &[BufferLine], stale: &mut Vec<bool>) {
+    stale.clear();
+    buffer.lines.truncate(source.len());
+    for (index, line) in source.iter().enumerate() {
+        match buffer.lines.get_mut(index) {
+            Some(cached)
+                if cached.text() == line.text()
+                    && cached.ending() == line.ending()
+                    && cached.attrs_list() == line.attrs_list() =>
+            {
+                stale.push(cached.layout_opt().is_none());
+            }
+            Some(cached) => {
+                cached.set_text(line.text(), line.ending(), line.attrs_list().clone());
+                stale.push(true);
+            }
+            None => {
+                buffer.lines.push(line.clone());
+                stale.push(true);
+            }
+        }
+    }
+}
+
+/// Move text after tabs in `glyphs`, a copy of `run.glyphs`, to tab stops, returns the new width of the line.
+fn apply_tab_stops(run: &LayoutRun, stops: &[TabStop], em: f32, glyphs: &mut [LayoutGlyph]) -> f32 {
+    let is_tab = |glyph: &LayoutGlyph| &run.text[glyph.start..glyph.end] == "\t";
+    let mut shift = 0.0;
+    for (i, glyph) in glyphs.iter_mut().enumerate() {
+        glyph.x += shift;
+        if !is_tab(glyph) {
+            continue;
+        }
+        let tab_start = glyph.x;
+        // Glyphs until the next tab, in original positions.
+        let start = i + 1;
+        let end = (start..run.glyphs.len())
+            .find(|j| is_tab(&run.glyphs[*j]))
+            .unwrap_or(run.glyphs.len());
+        let column = &run.glyphs[start..end];
+        let (Some(first), Some(last)) = (column.first(), column.last()) else {
+            continue;
+        };
+        let Some(stop) = stops.iter().find(|stop| stop.position * em > tab_start) else {
+            continue;
+        };
+        let column_width = last.x + last.w - first.x;
+        let position = stop.position * em;
+        let target = match stop.align {
+            TabAlign::Left => position,
+            TabAlign::Right => position - column_width,
+            TabAlign::Center => position - column_width / 2.0,
+            TabAlign::Decimal => {
+                position
+                    - column
+                        .iter()
+                        .find(|glyph| run.text[glyph.start..glyph.end].contains('.'))
+                        .map_or(column_width, |glyph| glyph.x - first.x)
+            }
+        }
+        .max(tab_start);
+        glyph.w = target - tab_start;
+        shift = target - first.x;
+    }
+    glyphs
+        .iter()
+        .fold(0.0, |width, glyph| width.max(glyph.x + glyph.w))
+}
+
+/// Render the soft hyphen at the end of a line as `-`, returns the new width of the line.
+fn show_soft_hyphen(font_system: &mut FontSystem, glyphs: &mut [LayoutGlyph], line_w: f32) -> f32 {
+    let Some(glyph) = glyphs.last_mut() else {
+        return line_w;
+    };
+    let Some(Some((glyph_id, advance))) =
+        font_system.db().with_face_data(glyph.font_id, |file, _| {
+            let face = Face::parse(file, 0).ok()?;
+            let glyph_id = face.glyph_index('-')?;
+            let advance = face.glyph_hor_advance(glyph_id)? as f32 / face.units_per_em() as f32;
+            Some((glyph_id.0, advance))
+        })
+    else {
+        return line_w;
+    };
+    glyph.glyph_id = glyph_id;
+    glyph.w = advance * glyph.font_size;
+    line_w.max(glyph.x + glyph.w)
+}
+
 fn get_atlas_rect(
     font_system: &mut FontSystem,
     scale_factor: f32,
@@ -589,3 +1346,554 @@ pub(crate) fn cache_glyph(
     let scale = glyph.font_size / unit_per_em * scale_factor;
     tess_commands.tess_glyph(stroke, scale, atlas, image, entry)
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::{AssetId, Assets},
+        color::{ColorToComponents, LinearRgba, Srgba},
+        ecs::{entity::Entity, world::World},
+        image::Image,
+        math::Vec2,
+        mesh::{Indices, Mesh, Mesh3d, MeshVertexAttribute, VertexAttributeValues},
+        tasks::{ComputeTaskPool, TaskPool},
+        time::Time,
+    };
+    use cosmic_text::{fontdb::Database, FontSystem};
+
+    use super::text_render;
+    use crate::{
+        misc::Weight, FetchedStyle, FetchedText, GlyphMeta, MeshExport, MeshExportEntry,
+        ParseBuilder, SegmentStyle, SpanEffect, TabStop, Text3d, Text3dBounds, Text3dDimensionOut,
+        Text3dPlugin, Text3dReveal, Text3dSegment, Text3dStyle, TextAtlas, TextRenderer,
+    };
+
+    /// A world that renders text with Roboto without loading system fonts.
+    fn world() -> World {
+        let mut world = World::new();
+        let mut db = Database::new();
+        db.load_font_data(include_bytes!("../assets/Roboto-Regular.ttf").to_vec());
+        world.insert_resource(TextRenderer::new(FontSystem::new_with_locale_and_db(
+            "en-US".into(),
+            db,
+        )));
+        world.insert_resource(Text3dPlugin::default());
+        world.insert_resource(Time::<()>::default());
+        world.init_resource::<Assets<Mesh>>();
+        let mut images = Assets::<Image>::default();
+        let _ = images.insert(&TextAtlas::DEFAULT_IMAGE, TextAtlas::empty_image(512, 512));
+        world.insert_resource(images);
+        let mut atlases = Assets::<TextAtlas>::default();
+        let _ = atlases.insert(AssetId::default(), TextAtlas::new(TextAtlas::DEFAULT_IMAGE));
+        world.insert_resource(atlases);
+        world
+    }
+
+    fn style() -> Text3dStyle {
+        Text3dStyle {
+            font: "Roboto".into(),
+            size: 10.0,
+            ..Default::default()
+        }
+    }
+
+    fn render(world: &mut World) {
+        let system = match world.get_resource::<RenderSystem>() {
+            Some(system) => system.0,
+            None => {
+                let system = world.register_system(text_render);
+                world.insert_resource(RenderSystem(system));
+                system
+            }
+        };
+        world.run_system(system).unwrap();
+    }
+
+    #[derive(bevy::ecs::resource::Resource)]
+    struct RenderSystem(bevy::ecs::system::SystemId);
+
+    fn mesh_of(world: &mut World, entity: Entity) -> &mut Mesh {
+        let id = world.get::<Mesh3d>(entity).unwrap().id();
+        world
+            .resource_mut::<Assets<Mesh>>()
+            .into_inner()
+            .get_mut(id)
+            .unwrap()
+            .into_inner()
+    }
+
+    fn indices(mesh: &Mesh) -> Vec<u16> {
+        match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reveal_only_rewrites_indices() {
+        let mut world = world();
+        let entity = world
+            .spawn((
+                Text3d::new("abc"),
+                style(),
+                Mesh3d::default(),
+                Text3dReveal::default(),
+            ))
+            .id();
+        render(&mut world);
+        let mesh = mesh_of(&mut world, entity);
+        assert_eq!(mesh.count_vertices(), 12);
+        assert_eq!(indices(mesh), [0, 0, 0]);
+        // Mark the colors, they are written again if the mesh is rebuilt.
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![[9.0f32; 4]; 12]);
+
+        world.get_mut::<Text3dReveal>(entity).unwrap().skip();
+        render(&mut world);
+        let mesh = mesh_of(&mut world, entity);
+        assert_eq!(indices(mesh).len(), 18);
+        assert!(matches!(
+            mesh.attribute(Mesh::ATTRIBUTE_COLOR),
+            Some(VertexAttributeValues::Float32x4(colors)) if colors.iter().all(|x| x[0] == 9.0)
+        ));
+    }
+
+    fn uv1(mesh: &Mesh) -> Vec<[f32; 2]> {
+        match mesh.attribute(Mesh::ATTRIBUTE_UV_1) {
+            Some(VertexAttributeValues::Float32x2(values)) => values.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn span_effects_are_exported() {
+        let mut world = world();
+        let text = Text3d::parse_raw("a{wave, shake-0.5: b}").unwrap();
+        let entity = world
+            .spawn((
+                text,
+                Text3dStyle {
+                    export: MeshExport::Uv1(
+                        GlyphMeta::EffectMask,
+                        GlyphMeta::EffectStrength(SpanEffect::Shake),
+                    ),
+                    ..style()
+                },
+                Mesh3d::default(),
+            ))
+            .id();
+        render(&mut world);
+        let uv1 = uv1(mesh_of(&mut world, entity));
+        assert_eq!(uv1.len(), 8);
+        assert!(uv1[..4].iter().all(|x| *x == [0.0, 0.0]));
+        let mask = (SpanEffect::Wave.bit() | SpanEffect::Shake.bit()) as f32;
+        assert!(uv1[4..].iter().all(|x| *x == [mask, 0.5]));
+    }
+
+    #[test]
+    fn char_index_is_offset_in_source() {
+        let mut world = world();
+        let text = Text3d::parse_raw("x{bold: yz}**w**").unwrap();
+        let entity = world
+            .spawn((
+                text,
+                Text3dStyle {
+                    export: MeshExport::Uv1(GlyphMeta::CharIndex, GlyphMeta::Index),
+                    ..style()
+                },
+                Mesh3d::default(),
+            ))
+            .id();
+        render(&mut world);
+        let uv1 = uv1(mesh_of(&mut world, entity));
+        let char_indices: Vec<f32> = uv1.chunks(4).map(|quad| quad[0][0]).collect();
+        assert_eq!(char_indices, [0.0, 8.0, 9.0, 13.0]);
+    }
+
+    #[test]
+    fn glyph_center_and_size_after_world_scale() {
+        let mut world = world();
+        let entry = MeshExportEntry::from_name(
+            "Vertex_GlyphBox",
+            7001,
+            &[
+                GlyphMeta::GlyphCenterX,
+                GlyphMeta::GlyphCenterY,
+                GlyphMeta::GlyphWidth,
+                GlyphMeta::GlyphHeight,
+            ],
+        );
+        let entity = world
+            .spawn((
+                Text3d::new("ab\ncd"),
+                Text3dStyle {
+                    export: MeshExport::Custom(vec![entry]),
+                    world_scale: Some(Vec2::new(2.0, 3.0)),
+                    ..style()
+                },
+                Mesh3d::default(),
+            ))
+            .id();
+        render(&mut world);
+        let mesh = mesh_of(&mut world, entity);
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("missing positions");
+        };
+        let Some(VertexAttributeValues::Float32x4(exports)) = mesh.attribute(entry.attribute())
+        else {
+            panic!("missing exports");
+        };
+        assert_eq!(positions.len(), 16);
+        for (quad, exports) in positions.chunks(4).zip(exports.chunks(4)) {
+            let [min_x, min_y] = [quad[0][0], quad[0][1]];
+            let [max_x, max_y] = [quad[3][0], quad[3][1]];
+            let expected = [
+                (min_x + max_x) / 2.0,
+                (min_y + max_y) / 2.0,
+                max_x - min_x,
+                max_y - min_y,
+            ];
+            for vertex in exports {
+                for (value, expected) in vertex.iter().zip(expected) {
+                    assert!((value - expected).abs() < 1e-4, "{vertex:?} {expected:?}");
+                }
+            }
+        }
+        let scaled = exports.clone();
+
+        // Glyphs are scaled from `size` to `world_scale`.
+        world.get_mut::<Text3dStyle>(entity).unwrap().world_scale = None;
+        render(&mut world);
+        let Some(VertexAttributeValues::Float32x4(exports)) =
+            mesh_of(&mut world, entity).attribute(entry.attribute())
+        else {
+            panic!("missing exports");
+        };
+        for (scaled, unscaled) in scaled.iter().zip(exports) {
+            let [x, y, w, h] = *unscaled;
+            let expected = [x * 0.2, y * 0.3, w * 0.2, h * 0.3];
+            for (value, expected) in scaled.iter().zip(expected) {
+                assert!((value - expected).abs() < 1e-4, "{scaled:?} {unscaled:?}");
+            }
+        }
+    }
+
+    /// Left and right edges of glyphs on each line, from top to bottom.
+    fn line_edges(world: &mut World, entity: Entity) -> Vec<(f32, f32)> {
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh_of(world, entity).attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("missing positions");
+        };
+        let mut lines: Vec<(f32, f32, f32)> = Vec::new();
+        for quad in positions.chunks(4) {
+            let (y, left, right) = (quad[0][1], quad[0][0], quad[3][0]);
+            match lines.iter_mut().find(|line| (line.0 - y).abs() < 0.01) {
+                Some(line) => {
+                    line.1 = line.1.min(left);
+                    line.2 = line.2.max(right);
+                }
+                None => lines.push((y, left, right)),
+            }
+        }
+        lines.sort_by(|a, b| b.0.total_cmp(&a.0));
+        lines.into_iter().map(|(_, l, r)| (l, r)).collect()
+    }
+
+    /// Render a paragraph of `aa` words wrapped at `width`.
+    fn paragraph(styles: &str, width: f32) -> Vec<(f32, f32)> {
+        let mut world = world();
+        let text = format!("{{{styles}: aa aa aa aa aa aa aa aa aa}}");
+        let entity = world
+            .spawn((
+                Text3d::parse_raw(&text).unwrap(),
+                style(),
+                Text3dBounds { width },
+                Mesh3d::default(),
+            ))
+            .id();
+        render(&mut world);
+        line_edges(&mut world, entity)
+    }
+
+    #[test]
+    fn first_line_indent() {
+        let lines = paragraph("indent-2", 50.0);
+        assert!(lines.len() > 2, "{lines:?}");
+        // `indent-2` is two times the font size.
+        assert!((lines[0].0 - lines[1].0 - 20.0).abs() < 0.01, "{lines:?}");
+        assert!(lines[1..].iter().all(|x| (x.0 - lines[1].0).abs() < 0.01));
+        // Following lines are wrapped narrower, so the first line fits.
+        let left = lines[1].0;
+        assert!(lines.iter().all(|x| x.1 - left <= 50.0), "{lines:?}");
+    }
+
+    #[test]
+    fn hanging_indent() {
+        let lines = paragraph("margin-3, indent--3", 50.0);
+        assert!(lines.len() > 2, "{lines:?}");
+        assert!((lines[1].0 - lines[0].0 - 30.0).abs() < 0.01, "{lines:?}");
+        assert!(lines[1..].iter().all(|x| (x.0 - lines[1].0).abs() < 0.01));
+        let left = lines[0].0;
+        assert!(lines.iter().all(|x| x.1 - left <= 50.0), "{lines:?}");
+    }
+
+    #[test]
+    fn right_margin() {
+        let plain = paragraph("bold", 60.0);
+        let lines = paragraph("margin-right-3", 60.0);
+        assert!(lines.len() > plain.len(), "{plain:?} {lines:?}");
+        let left = lines[0].0;
+        assert!(lines.iter().all(|x| (x.0 - left).abs() < 0.01));
+        assert!(lines.iter().all(|x| x.1 - left <= 30.0), "{lines:?}");
+    }
+
+    /// Left and right edges of the glyph at each byte offset of `text`, with tab stops at 10 and 20 em.
+    fn tabbed(text: &str, stop: fn(f32) -> TabStop) -> impl Fn(usize) -> (f32, f32) {
+        let mut world = world();
+        let entity = world
+            .spawn((
+                Text3d::new(text),
+                Text3dStyle {
+                    tab_stops: vec![stop(10.0), stop(20.0)],
+                    export: MeshExport::Uv1(GlyphMeta::CharIndex, GlyphMeta::Index),
+                    ..style()
+                },
+                Mesh3d::default(),
+            ))
+            .id();
+        render(&mut world);
+        let mesh = mesh_of(&mut world, entity);
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("missing positions");
+        };
+        let edges: Vec<_> = uv1(mesh)
+            .chunks(4)
+            .zip(positions.chunks(4))
+            .map(|(uv, quad)| (uv[0][0] as usize, (quad[0][0], quad[3][0])))
+            .collect();
+        move |offset| {
+            edges
+                .iter()
+                .find(|(i, _)| *i == offset)
+                .map(|(_, edges)| *edges)
+                .unwrap_or_else(|| panic!("no glyph at {offset}"))
+        }
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.01, "{a} != {b}");
+    }
+
+    #[test]
+    fn left_tab_stops() {
+        let text = "a\tbc\tx\naaa\tbc";
+        let glyph = tabbed(text, TabStop::left);
+        let a = glyph(0).0;
+        // Text starts at the tab stop, 10 em from the start of the line.
+        assert_close(glyph(2).0, glyph(11).0);
+        assert!(glyph(2).0 - a > 95.0 && glyph(2).0 - a < 105.0);
+        assert!(glyph(5).0 - a > 195.0 && glyph(5).0 - a < 205.0);
+    }
+
+    #[test]
+    fn right_tab_stops() {
+        let text = "a\tb\na\tbbb";
+        let glyph = tabbed(text, TabStop::right);
+        assert_close(glyph(2).1, glyph(8).1);
+        assert!(glyph(6).0 < glyph(2).0);
+    }
+
+    #[test]
+    fn center_tab_stops() {
+        let text = "a\tbb\na\tbbbb";
+        let glyph = tabbed(text, TabStop::center);
+        let center = |first: usize, last: usize| (glyph(first).0 + glyph(last).1) / 2.0;
+        assert_close(center(2, 3), center(7, 10));
+    }
+
+    #[test]
+    fn decimal_tab_stops() {
+        let rows = [("str", "1.5"), ("strength", "12.25"), ("x", "100")];
+        let text = rows
+            .iter()
+            .map(|(strength, value)| format!("{strength}\t{value}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let glyph = tabbed(&text, TabStop::decimal);
+        let dot = |row: usize| {
+            let start: usize = rows[..row].iter().map(|(s, v)| s.len() + v.len() + 2).sum();
+            let (strength, value) = rows[row];
+            let offset = start + strength.len() + 1;
+            match value.find('.') {
+                Some(dot) => glyph(offset + dot).0,
+                // Text without `.` ends at the tab stop.
+                None => glyph(offset + value.len() - 1).1,
+            }
+        };
+        assert_close(dot(0), dot(1));
+        assert!((dot(0) - dot(2)).abs() < 2.0, "{} {}", dot(0), dot(2));
+    }
+
+    fn attribute<const N: usize>(mesh: &Mesh, id: MeshVertexAttribute) -> Vec<[f32; N]> {
+        match mesh.attribute(id) {
+            Some(VertexAttributeValues::Float32x3(values)) if N == 3 => {
+                values.iter().map(|x| x[..N].try_into().unwrap()).collect()
+            }
+            Some(VertexAttributeValues::Float32x4(values)) if N == 4 => {
+                values.iter().map(|x| x[..N].try_into().unwrap()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn paint_only_style_change_keeps_layout() {
+        let mut world = world();
+        let dynamic = world.spawn(FetchedStyle::default()).id();
+        let text = Text3d::parse(
+            "{margin-2: a{@s: bc}}",
+            ParseBuilder::new().with_parse_value(move |_: &str| {
+                Ok((
+                    Text3dSegment::Style {
+                        style: dynamic,
+                        offset: 0,
+                    },
+                    SegmentStyle::default(),
+                ))
+            }),
+        )
+        .unwrap();
+        let entity = world.spawn((text, style(), Mesh3d::default())).id();
+        render(&mut world);
+        let mesh = mesh_of(&mut world, entity);
+        let positions: Vec<[f32; 3]> = attribute(mesh, Mesh::ATTRIBUTE_POSITION);
+        let colors: Vec<[f32; 4]> = attribute(mesh, Mesh::ATTRIBUTE_COLOR);
+        assert_eq!(positions.len(), 12);
+
+        let red = Srgba::RED;
+        world.get_mut::<FetchedStyle>(dynamic).unwrap().0.fill_color = Some(red);
+        render(&mut world);
+        assert_eq!(
+            world
+                .get::<Text3dDimensionOut>(entity)
+                .unwrap()
+                .laid_out_lines,
+            0
+        );
+        let mesh = mesh_of(&mut world, entity);
+        assert_eq!(attribute::<3>(mesh, Mesh::ATTRIBUTE_POSITION), positions);
+        let new_colors: Vec<[f32; 4]> = attribute(mesh, Mesh::ATTRIBUTE_COLOR);
+        assert_eq!(new_colors[..4], colors[..4]);
+        let red = LinearRgba::from(red).to_f32_array();
+        assert!(new_colors[4..].iter().all(|x| *x == red));
+
+        // Styles that change the layout lay out the line again.
+        world.get_mut::<FetchedStyle>(dynamic).unwrap().0.weight = Some(Weight::BOLD);
+        render(&mut world);
+        assert_ne!(
+            world
+                .get::<Text3dDimensionOut>(entity)
+                .unwrap()
+                .laid_out_lines,
+            0
+        );
+    }
+
+    #[test]
+    fn only_changed_lines_are_laid_out() {
+        let mut world = world();
+        let value = world.spawn(FetchedText("1".into())).id();
+        let text = || {
+            Text3d::parse(
+                "{margin-2: first}\nhp {hp}\nthird",
+                ParseBuilder::new().with_parse_value(move |_: &str| {
+                    Ok((Text3dSegment::Extract(value), SegmentStyle::default()))
+                }),
+            )
+            .unwrap()
+        };
+        let laid_out = |world: &World, entity| {
+            world
+                .get::<Text3dDimensionOut>(entity)
+                .unwrap()
+                .laid_out_lines
+        };
+        let entity = world.spawn((text(), style(), Mesh3d::default())).id();
+        render(&mut world);
+        // Lines with margins are laid out once with a narrower width.
+        assert_eq!(laid_out(&world, entity), 3);
+
+        world.get_mut::<FetchedText>(value).unwrap().0 = "20".into();
+        render(&mut world);
+        assert_eq!(laid_out(&world, entity), 1);
+        // The result is the same as a new layout.
+        let fresh = world.spawn((text(), style(), Mesh3d::default())).id();
+        render(&mut world);
+        let positions = |world: &mut World, entity| {
+            attribute::<3>(mesh_of(world, entity), Mesh::ATTRIBUTE_POSITION)
+        };
+        assert_eq!(positions(&mut world, entity), positions(&mut world, fresh));
+
+        world.get_mut::<Text3dBounds>(entity).unwrap().width = 500.0;
+        render(&mut world);
+        assert_eq!(laid_out(&world, entity), 3);
+    }
+
+    #[test]
+    fn parallel_layout_matches_serial() {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        let texts = [
+            "plain text",
+            "{margin-2, indent-1: a paragraph that wraps over more than one line}",
+            "**bold** and *italic*\nsecond line",
+            "a\tb\tc",
+            "{wave: effects} {s-2, red: stroke}",
+        ];
+        let meshes = |layout_threads: usize| {
+            let mut world = world();
+            world.resource_mut::<Text3dPlugin>().layout_threads = layout_threads;
+            let entities: Vec<Entity> = texts
+                .iter()
+                .map(|text| {
+                    let text = Text3d::parse_raw(text).unwrap();
+                    world
+                        .spawn((
+                            text,
+                            style(),
+                            Text3dBounds { width: 80.0 },
+                            Mesh3d::default(),
+                        ))
+                        .id()
+                })
+                .collect();
+            render(&mut world);
+            let shards = world
+                .resource::<TextRenderer>()
+                .0
+                .lock()
+                .unwrap()
+                .shards
+                .len();
+            assert_eq!(shards, layout_threads);
+            entities
+                .into_iter()
+                .map(|entity| {
+                    let mesh = mesh_of(&mut world, entity);
+                    (
+                        attribute::<3>(mesh, Mesh::ATTRIBUTE_POSITION),
+                        attribute::<4>(mesh, Mesh::ATTRIBUTE_COLOR),
+                        indices(mesh),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let serial = meshes(0);
+        assert!(serial.iter().all(|(positions, _, _)| !positions.is_empty()));
+        assert_eq!(meshes(2), serial);
+        assert_eq!(meshes(8), serial);
+    }
+}
@@ -0,0 +1,261 @@
+use std::borrow::Cow;
+
+#[cfg(feature = "reflect")]
+use bevy::{ecs::reflect::ReflectComponent, reflect::Reflect};
+use bevy::{
+    ecs::{
+        change_detection::DetectChangesMut,
+        component::Component,
+        entity::Entity,
+        message::{Message, MessageWriter},
+        system::{Query, Res},
+    },
+    time::Time,
+};
+
+use crate::{Text3d, Text3dSegment};
+
+/// An inline command in [`Text3d`] that controls [`Text3dReveal`].
+///
+/// Created by `{pause=0.5}` and `{speed=2}` in [`Text3d::parse`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RevealCommand {
+    /// Wait for some seconds before revealing the next glyph.
+    Pause(f32),
+    /// Multiply [`Text3dReveal::speed`] by a value until the next `Speed` command.
+    Speed(f32),
+}
+
+/// A glyph recorded by the renderer in display order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct RevealGlyph {
+    /// Index of the segment in [`Text3d::segments`].
+    pub segment: usize,
+    /// First character of the glyph's cluster.
+    pub char: char,
+}
+
+/// Reveals glyphs of a [`Text3d`] one by one, commonly used in dialogues.
+///
+/// Glyphs that are not revealed yet are not rendered.
+/// Revealing glyphs only rewrites the indices of the mesh, the text is not laid out again.
+///
+/// Modifying [`Text3d`] does not restart the reveal, call [`Text3dReveal::restart`] or
+/// insert a new [`Text3dReveal`] if desired.
+#[derive(Debug, Clone, Component)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct Text3dReveal {
+    /// Number of glyphs revealed per second.
+    pub speed: f32,
+    /// Additional seconds waited after revealing a glyph in `punctuations`.
+    pub punctuation_pause: f32,
+    /// Characters that trigger `punctuation_pause`.
+    pub punctuations: Cow<'static, str>,
+    /// If true, stops revealing new glyphs.
+    pub paused: bool,
+    revealed: usize,
+    timer: f32,
+    multiplier: f32,
+    cursor: usize,
+    finished: bool,
+    glyphs: Vec<RevealGlyph>,
+    glyphs_ready: bool,
+}
+
+impl Default for Text3dReveal {
+    fn default() -> Self {
+        Self {
+            speed: 30.0,
+            punctuation_pause: 0.25,
+            punctuations: Cow::Borrowed(".,!?;:。，！？；："),
+            paused: false,
+            revealed: 0,
+            timer: 0.0,
+            multiplier: 1.0,
+            cursor: 0,
+            finished: false,
+            glyphs: Vec::new(),
+            glyphs_ready: false,
+        }
+    }
+}
+
+impl Text3dReveal {
+    /// Create a [`Text3dReveal`] with a speed in glyphs per second.
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            ..Default::default()
+        }
+    }
+
+    /// Set `punctuation_pause`.
+    pub fn with_punctuation_pause(mut self, seconds: f32) -> Self {
+        self.punctuation_pause = seconds;
+        self
+    }
+
+    /// Number of glyphs revealed.
+    pub fn revealed(&self) -> usize {
+        self.revealed
+    }
+
+    /// Returns true if all glyphs are revealed.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Glyphs in the order they are revealed, available after the text is rendered.
+    pub fn glyphs(&self) -> &[RevealGlyph] {
+        &self.glyphs
+    }
+
+    /// Reveal all remaining glyphs immediately.
+    ///
+    /// Only [`Text3dRevealMessage::Finished`] is sent for skipped glyphs.
+    pub fn skip(&mut self) {
+        self.revealed = usize::MAX;
+    }
+
+    /// Hide all glyphs and reveal from the start.
+    pub fn restart(&mut self) {
+        self.revealed = 0;
+        self.timer = 0.0;
+        self.multiplier = 1.0;
+        self.cursor = 0;
+        self.finished = false;
+    }
+
+    pub(crate) fn set_glyphs(&mut self, glyphs: &mut Vec<RevealGlyph>) {
+        self.glyphs.clear();
+        self.glyphs.append(glyphs);
+        self.glyphs_ready = true;
+        // Glyphs added after finishing are revealed as usual.
+        if self.revealed < self.glyphs.len() {
+            self.finished = false;
+        }
+    }
+}
+
+/// Sent by [`Text3dReveal`] when glyphs are revealed.
+#[derive(Debug, Clone, Copy, PartialEq, Message)]
+pub enum Text3dRevealMessage {
+    /// A glyph is revealed, `index` is the order of the glyph.
+    Glyph {
+        entity: Entity,
+        index: usize,
+        glyph: RevealGlyph,
+    },
+    /// The first glyph of a segment is revealed.
+    Segment { entity: Entity, segment: usize },
+    /// All glyphs are revealed.
+    Finished { entity: Entity },
+}
+
+pub fn text_reveal(
+    time: Res<Time>,
+    mut query: Query<(Entity, &Text3d, &mut Text3dReveal)>,
+    mut messages: MessageWriter<Text3dRevealMessage>,
+) {
+    let delta = time.delta_secs();
+    for (entity, text, mut reveal) in &mut query {
+        if reveal.finished || !reveal.glyphs_ready {
+            continue;
+        }
+        if reveal.revealed >= reveal.glyphs.len() {
+            reveal.revealed = reveal.glyphs.len();
+            reveal.finished = true;
+            messages.write(Text3dRevealMessage::Finished { entity });
+            continue;
+        }
+        if reveal.paused {
+            continue;
+        }
+        let reveal_ref = reveal.bypass_change_detection();
+        let before = reveal_ref.revealed;
+        reveal_ref.timer += delta;
+        while let Some(glyph) = reveal_ref.glyphs.get(reveal_ref.revealed).copied() {
+            if reveal_ref.cursor <= glyph.segment {
+                for (segment, _) in text
+                    .segments
+                    .iter()
+                    .take(glyph.segment)
+                    .skip(reveal_ref.cursor)
+                {
+                    match segment {
+                        Text3dSegment::Reveal(RevealCommand::Pause(seconds)) => {
+                            reveal_ref.timer -= seconds
+                        }
+                        Text3dSegment::Reveal(RevealCommand::Speed(multiplier)) => {
+                            reveal_ref.multiplier = *multiplier
+                        }
+                        _ => (),
+                    }
+                }
+                reveal_ref.cursor = glyph.segment + 1;
+            }
+            let speed = reveal_ref.speed * reveal_ref.multiplier;
+            if speed <= 0.0 || reveal_ref.timer < 1.0 / speed {
+                break;
+            }
+            reveal_ref.timer -= 1.0 / speed;
+            let index = reveal_ref.revealed;
+            if index == 0 || reveal_ref.glyphs[index - 1].segment != glyph.segment {
+                messages.write(Text3dRevealMessage::Segment {
+                    entity,
+                    segment: glyph.segment,
+                });
+            }
+            messages.write(Text3dRevealMessage::Glyph {
+                entity,
+                index,
+                glyph,
+            });
+            reveal_ref.revealed += 1;
+            if reveal_ref.punctuations.contains(glyph.char) {
+                reveal_ref.timer -= reveal_ref.punctuation_pause;
+            }
+        }
+        if reveal_ref.revealed >= reveal_ref.glyphs.len() {
+            reveal_ref.finished = true;
+            messages.write(Text3dRevealMessage::Finished { entity });
+        }
+        if reveal_ref.revealed != before {
+            reveal.set_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RevealGlyph, Text3dReveal};
+
+    fn glyphs(len: usize) -> Vec<RevealGlyph> {
+        vec![
+            RevealGlyph {
+                segment: 0,
+                char: 'a',
+            };
+            len
+        ]
+    }
+
+    #[test]
+    fn growing_text_resumes_finished_reveal() {
+        let mut reveal = Text3dReveal::default();
+        reveal.set_glyphs(&mut glyphs(3));
+        reveal.revealed = 3;
+        reveal.finished = true;
+
+        reveal.set_glyphs(&mut glyphs(3));
+        assert!(reveal.is_finished());
+
+        reveal.set_glyphs(&mut glyphs(5));
+        assert!(!reveal.is_finished());
+        assert_eq!(reveal.revealed(), 3);
+    }
+}
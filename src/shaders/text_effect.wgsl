@@ -0,0 +1,69 @@
+#define_import_path bevy_rich_text3d::text_effect
+
+#import bevy_render::color_operations::hsv_to_rgb
+
+const TAU: f32 = 6.28318530718;
+
+const EFFECT_TYPEWRITER: u32 = 1u;
+const EFFECT_RAINBOW: u32 = 2u;
+const EFFECT_WAVE: u32 = 3u;
+const EFFECT_SHAKE: u32 = 4u;
+const EFFECT_DISSOLVE: u32 = 5u;
+const EFFECT_OUTLINE_GLOW: u32 = 6u;
+
+struct TextEffect {
+    color: vec4<f32>,
+    params: vec4<f32>,
+    kind: u32,
+}
+
+fn hash12(p: vec2<f32>) -> f32 {
+    var p3 = fract(vec3(p.xyx) * 0.1031);
+    p3 += dot(p3, p3.yzx + 33.33);
+    return fract((p3.x + p3.y) * p3.z);
+}
+
+// `data.x` is the advance of the glyph's center in `em`, `data.y` is a random value per glyph.
+fn effect_vertex_offset(effect: TextEffect, data: vec2<f32>, time: f32) -> vec2<f32> {
+    if effect.kind == EFFECT_WAVE {
+        let phase = (data.x * effect.params.y + time * effect.params.z) * TAU;
+        return vec2(0.0, sin(phase) * effect.params.x);
+    } else if effect.kind == EFFECT_SHAKE {
+        let tick = floor(time * effect.params.y);
+        let x = hash12(vec2(data.y * 1000.0, tick)) * 2.0 - 1.0;
+        let y = hash12(vec2(tick, data.y * 1000.0)) * 2.0 - 1.0;
+        return vec2(x, y) * effect.params.x;
+    }
+    return vec2(0.0);
+}
+
+// `noise` is a per pixel coordinate used for noise based effects.
+fn effect_color(
+    effect: TextEffect,
+    color: vec4<f32>,
+    data: vec2<f32>,
+    noise: vec2<f32>,
+    time: f32,
+) -> vec4<f32> {
+    var out = color;
+    if effect.kind == EFFECT_TYPEWRITER {
+        let softness = max(effect.params.z, 0.0001);
+        out.a *= clamp(((time - effect.params.x) * effect.params.y - data.x) / softness, 0.0, 1.0);
+    } else if effect.kind == EFFECT_RAINBOW {
+        let hue = fract(data.x * effect.params.y + time * effect.params.x) * TAU;
+        out = vec4(out.rgb * hsv_to_rgb(vec3(hue, effect.params.z, 1.0)), out.a);
+    } else if effect.kind == EFFECT_DISSOLVE {
+        let n = mix(hash12(floor(noise)), data.y, 0.25);
+        if n < effect.params.x {
+            out.a = 0.0;
+        } else if n < effect.params.x + effect.params.y {
+            out = vec4(effect.color.rgb, out.a * effect.color.a);
+        }
+    } else if effect.kind == EFFECT_OUTLINE_GLOW {
+        let edge = clamp(color.a * (1.0 - color.a) * 4.0, 0.0, 1.0);
+        let pulse = 0.5 + 0.5 * sin(time * effect.params.y * TAU);
+        let glow = edge * mix(1.0, pulse, step(0.0001, effect.params.y)) * effect.params.x;
+        out = vec4(mix(out.rgb, effect.color.rgb, clamp(glow, 0.0, 1.0)), max(out.a, glow * effect.color.a));
+    }
+    return out;
+}
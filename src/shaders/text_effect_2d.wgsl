@@ -0,0 +1,55 @@
+#import bevy_sprite::{
+    mesh2d_functions as mesh_functions,
+    mesh2d_view_bindings::{view, globals},
+}
+#import bevy_rich_text3d::text_effect::{TextEffect, effect_vertex_offset, effect_color}
+
+#ifdef TONEMAP_IN_SHADER
+#import bevy_core_pipeline::tonemapping
+#endif
+
+@group(#{MATERIAL_BIND_GROUP}) @binding(0) var<uniform> effect: TextEffect;
+@group(#{MATERIAL_BIND_GROUP}) @binding(1) var texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(2) var texture_sampler: sampler;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(4) color: vec4<f32>,
+    @location(5) uv_b: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(4) color: vec4<f32>,
+    @location(5) uv_b: vec2<f32>,
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    let position = vertex.position + vec3(effect_vertex_offset(effect, vertex.uv_b, globals.time), 0.0);
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    out.world_position = mesh_functions::mesh2d_position_local_to_world(
+        world_from_local,
+        vec4<f32>(position, 1.0)
+    );
+    out.position = mesh_functions::mesh2d_position_world_to_clip(out.world_position);
+    out.uv = vertex.uv;
+    out.color = vertex.color;
+    out.uv_b = vertex.uv_b;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = in.color * textureSample(texture, texture_sampler, in.uv);
+    color = effect_color(effect, color, in.uv_b, in.position.xy, globals.time);
+#ifdef TONEMAP_IN_SHADER
+    color = tonemapping::tone_mapping(color, view.color_grading);
+#endif
+    return color;
+}
@@ -0,0 +1,406 @@
+use std::{borrow::Cow, ops::Range};
+
+use bevy::{
+    asset::{io::Reader, Asset, AssetEvent, AssetId, AssetLoader, Assets, Handle, LoadContext},
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        message::MessageReader,
+        system::{Commands, Query, Res},
+        world::Ref,
+    },
+    log::{error, warn},
+    reflect::TypePath,
+};
+#[cfg(feature = "reflect")]
+use bevy::{ecs::reflect::ReflectComponent, reflect::Reflect};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    localize::blank_placeables,
+    parse_util::{ConditionOutput, ParseConditionFn, ParseStyleFn, ParseValueFn},
+    ParseBuilder, ParseError, SegmentStyle, SpannedParseError, Text3d, Text3dSegment,
+};
+
+/// Value of the matching kind for a name in [`Text3d::parse`], used to check strings without a world.
+pub(crate) fn placeholder_value(s: &str) -> Result<(Text3dSegment, SegmentStyle), ParseError> {
+    let segment = if s.starts_with('@') {
+        Text3dSegment::Style {
+            style: Entity::PLACEHOLDER,
+            offset: 0,
+        }
+    } else if s.starts_with("#each ") {
+        Text3dSegment::Each {
+            list: Entity::PLACEHOLDER,
+            offset: 0,
+        }
+    } else {
+        Text3dSegment::String(String::new())
+    };
+    Ok((segment, SegmentStyle::default()))
+}
+
+/// A rich text string in [`Text3dStrings`].
+#[derive(Debug, Clone)]
+pub struct Text3dTemplate {
+    /// Unparsed rich text.
+    pub source: String,
+    /// Line number of the key in the source file, starting from 1.
+    pub line: usize,
+    parsed: Option<(Vec<(Text3dSegment, SegmentStyle)>, Vec<usize>)>,
+}
+
+impl Text3dTemplate {
+    /// Returns the pre-parsed [`Text3d`] if the string only uses standard styles.
+    pub fn instantiate(&self) -> Option<Text3d> {
+        self.parsed
+            .as_ref()
+            .map(|(segments, source_offsets)| Text3d {
+                segments: segments.clone(),
+                source_offsets: source_offsets.clone(),
+            })
+    }
+
+    /// Parse the string with [`Text3d::parse`].
+    pub fn parse(
+        &self,
+        parser: ParseBuilder<impl ParseStyleFn, impl ParseValueFn, impl ParseConditionFn>,
+    ) -> Result<Text3d, ParseError> {
+        Text3d::parse(&self.source, parser)
+    }
+}
+
+/// An [`Asset`] of keyed rich text strings, loaded from `.rtext` files.
+///
+/// # Format
+///
+/// ```text
+/// # Comments start with `#`.
+/// greeting = Hello, **{blue:world}**!
+///
+/// # Indented lines continue the previous string, joined with a line break.
+/// intro =
+///     Once upon a time,
+///     there was a *very* long string.
+/// ```
+///
+/// Comments must start at the beginning of a line, so `#` can start an indented line in a string.
+/// The indentation of the first continued line is removed from all continued lines,
+/// additional indentation is kept.
+///
+/// Every string is checked for syntax errors when loaded, [`Text3dLocalized`](crate::Text3dLocalized)
+/// placeables like `{$count -> ...}` are ignored.
+/// Styles, values and conditions are not checked since they may be provided by [`Text3dTemplate::parse`].
+#[derive(Debug, Clone, Default, Asset, TypePath)]
+pub struct Text3dStrings {
+    path: String,
+    strings: FxHashMap<String, Text3dTemplate>,
+}
+
+/// Error emitted when loading [`Text3dStrings`].
+#[derive(Debug, thiserror::Error)]
+pub enum Text3dStringsError {
+    #[error("Could not read file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("File is not valid utf-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("{path}:{line}: Expected `key = value`.")]
+    Syntax { path: String, line: usize },
+    #[error("{path}:{line}: Duplicated key `{key}`.")]
+    DuplicatedKey {
+        path: String,
+        line: usize,
+        key: String,
+    },
+    #[error("{path}:{line}: In `{key}`: {error}")]
+    Parse {
+        path: String,
+        line: usize,
+        key: String,
+        error: Box<SpannedParseError>,
+    },
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+impl Text3dStrings {
+    /// Parse a `.rtext` file, `path` is only used in error messages.
+    pub fn from_source(path: impl Into<String>, source: &str) -> Result<Self, Text3dStringsError> {
+        let path = path.into();
+        // Key, line of the key, value and line of each line in value.
+        let mut entries: Vec<(String, usize, String, Vec<usize>)> = Vec::new();
+        // Indentation of the first continued line of the current entry.
+        let mut indent = None;
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                let Some((_, _, value, lines)) = entries.last_mut() else {
+                    return Err(Text3dStringsError::Syntax {
+                        path,
+                        line: line_number,
+                    });
+                };
+                let line = line.trim_end();
+                let content = line.trim_start();
+                let leading = &line[..line.len() - content.len()];
+                let indent = *indent.get_or_insert(leading.chars().count());
+                let kept = leading
+                    .chars()
+                    .skip(indent)
+                    .map(char::len_utf8)
+                    .sum::<usize>();
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(&line[leading.len() - kept..]);
+                lines.push(line_number);
+                continue;
+            }
+            indent = None;
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(Text3dStringsError::Syntax {
+                    path,
+                    line: line_number,
+                });
+            };
+            let key = key.trim();
+            if !is_valid_key(key) {
+                return Err(Text3dStringsError::Syntax {
+                    path,
+                    line: line_number,
+                });
+            }
+            let value = value.trim();
+            let lines = if value.is_empty() {
+                Vec::new()
+            } else {
+                vec![line_number]
+            };
+            entries.push((key.to_owned(), line_number, value.to_owned(), lines));
+        }
+        let mut strings = FxHashMap::default();
+        for (key, line, source, lines) in entries {
+            let line_of = |span: &Range<usize>| {
+                let index = source[..span.start.min(source.len())].matches('\n').count();
+                lines.get(index).copied().unwrap_or(line)
+            };
+            // Placeables of `Text3dLocalized` are resolved before parsing.
+            match Text3d::parse_with_warnings(
+                &blank_placeables(&source),
+                ParseBuilder::new()
+                    .with_parse_style(|_| Ok(SegmentStyle::default()))
+                    .with_parse_value(placeholder_value)
+                    .with_parse_condition(|_| Ok(ConditionOutput::Constant(true))),
+            ) {
+                Ok((_, warnings)) => {
+                    for warning in warnings {
+                        warn!("{path}:{}: In `{key}`: {warning}", line_of(&warning.span));
+                    }
+                }
+                Err(error) => {
+                    return Err(Text3dStringsError::Parse {
+                        line: line_of(&error.span),
+                        path,
+                        key,
+                        error: Box::new(error),
+                    });
+                }
+            }
+            let parsed = Text3d::parse_raw(&source)
+                .ok()
+                .map(|x| (x.segments, x.source_offsets));
+            if strings.contains_key(&key) {
+                return Err(Text3dStringsError::DuplicatedKey { path, line, key });
+            }
+            strings.insert(
+                key,
+                Text3dTemplate {
+                    source,
+                    line,
+                    parsed,
+                },
+            );
+        }
+        Ok(Text3dStrings { path, strings })
+    }
+
+    /// Path of the source file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Obtain a string by key.
+    pub fn get(&self, key: &str) -> Option<&Text3dTemplate> {
+        self.strings.get(key)
+    }
+
+    /// Iterate through all keys and strings.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Text3dTemplate)> {
+        self.strings.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+/// [`AssetLoader`] for [`Text3dStrings`], registered for the `.rtext` extension.
+#[derive(Debug, Default, TypePath)]
+pub struct Text3dStringsLoader;
+
+impl AssetLoader for Text3dStringsLoader {
+    type Asset = Text3dStrings;
+    type Settings = ();
+    type Error = Text3dStringsError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let source = std::str::from_utf8(&bytes)?;
+        Text3dStrings::from_source(load_context.path().to_string(), source)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["rtext"]
+    }
+}
+
+/// Sets [`Text3d`] to a string in [`Text3dStrings`], inserts [`Text3d`] if missing.
+///
+/// [`Text3d`] is updated when the key or the asset changes, this includes hot reloading.
+///
+/// Only standard styles are supported, call [`Text3dTemplate::parse`] manually for custom parsing.
+#[derive(Debug, Clone, Component)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct Text3dKey {
+    /// Handle of the strings asset.
+    pub strings: Handle<Text3dStrings>,
+    /// Key of the string.
+    pub key: Cow<'static, str>,
+}
+
+impl Text3dKey {
+    pub fn new(strings: Handle<Text3dStrings>, key: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            strings,
+            key: key.into(),
+        }
+    }
+}
+
+pub fn text_strings_system(
+    mut commands: Commands,
+    mut events: MessageReader<AssetEvent<Text3dStrings>>,
+    assets: Res<Assets<Text3dStrings>>,
+    mut query: Query<(Entity, Ref<Text3dKey>, Option<&mut Text3d>)>,
+) {
+    let changed: Vec<AssetId<Text3dStrings>> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    for (entity, key, text) in &mut query {
+        if !key.is_changed() && !changed.contains(&key.strings.id()) {
+            continue;
+        }
+        let Some(strings) = assets.get(&key.strings) else {
+            continue;
+        };
+        let Some(template) = strings.get(&key.key) else {
+            error!("{}: Key `{}` not found.", strings.path(), key.key);
+            continue;
+        };
+        let result = match template.instantiate() {
+            Some(parsed) => Ok(parsed),
+            None => Text3d::parse_raw(&template.source),
+        };
+        match result {
+            Ok(parsed) => match text {
+                Some(mut text) => *text = parsed,
+                None => {
+                    commands.entity(entity).insert(parsed);
+                }
+            },
+            Err(e) => error!(
+                "{}:{}: In `{}`: {}",
+                strings.path(),
+                template.line,
+                key.key,
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Text3dStrings, Text3dStringsError};
+
+    #[test]
+    fn comments_and_continuations() {
+        let source = "\
+# comment = not a string
+greeting = Hello!
+intro =
+    Once upon a time,
+# comment inside a string
+      there was
+    # a very long string.
+
+outro = The
+    end.
+";
+        let strings = Text3dStrings::from_source("test.rtext", source).unwrap();
+        assert_eq!(strings.iter().count(), 3);
+        assert!(strings.get("comment").is_none());
+        assert_eq!(strings.get("greeting").unwrap().source, "Hello!");
+        assert_eq!(strings.get("greeting").unwrap().line, 2);
+        let intro = strings.get("intro").unwrap();
+        assert_eq!(
+            intro.source,
+            "Once upon a time,\n  there was\n# a very long string."
+        );
+        assert_eq!(intro.line, 3);
+        assert_eq!(strings.get("outro").unwrap().source, "The\nend.");
+    }
+
+    #[test]
+    fn duplicated_key() {
+        let source = "a = one\nb = two\na = three\n";
+        match Text3dStrings::from_source("test.rtext", source) {
+            Err(Text3dStringsError::DuplicatedKey { path, line, key }) => {
+                assert_eq!(path, "test.rtext");
+                assert_eq!(line, 3);
+                assert_eq!(key, "a");
+            }
+            result => panic!("expected a duplicated key, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for (source, error_line) in [
+            ("  indented first\n", 1),
+            ("a = b\nno value\n", 2),
+            ("a b = c", 1),
+        ] {
+            match Text3dStrings::from_source("test.rtext", source) {
+                Err(Text3dStringsError::Syntax { line, .. }) => assert_eq!(line, error_line),
+                result => panic!("expected a syntax error, got {result:?}"),
+            }
+        }
+    }
+}
@@ -1,7 +1,14 @@
-use crate::{prepare::family, MeshExport, StrokeJoin, Style, TextAlign, TextAnchor, Weight};
+use crate::{
+    prepare::family, MeshExport, SpanEffects, StrokeJoin, Style, TabStop, TextAlign, TextAnchor,
+    TextWrap, ValueFormat, Weight,
+};
 use bevy::asset::AssetId;
 use bevy::image::Image;
-use bevy::{color::Srgba, ecs::component::Component, math::Vec2};
+use bevy::{
+    color::Srgba,
+    ecs::component::Component,
+    math::{Vec2, Vec4},
+};
 use cosmic_text::Metrics;
 use cosmic_text::{fontdb::ID, Attrs};
 use std::fmt::Debug;
@@ -9,11 +16,14 @@ use std::{num::NonZeroU32, sync::Arc};
 
 #[cfg(feature = "reflect")]
 use bevy::prelude::{Reflect, ReflectComponent, ReflectDefault};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 /// Default text style of a rich text component.
 #[derive(Debug, Component, Clone)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Component, Default))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Text3dStyle {
     /// Size of the font, corresponding to world space units.
     ///
@@ -59,14 +69,28 @@ pub struct Text3dStyle {
     pub layer_offset: f32,
     /// Determines what to extract as uv1.
     pub export: MeshExport,
+    /// Line breaking of text wider than [`Text3dBounds::width`](crate::Text3dBounds::width).
+    ///
+    /// Soft hyphens (`U+00AD`) are break opportunities rendered as `-` at the end of a line.
+    pub wrap: TextWrap,
+    /// If set, insert soft hyphens with the dictionary of this language in [`Text3dHyphenation`](crate::Text3dHyphenation).
+    pub hyphenate: Option<Arc<str>>,
     /// Tab in terms of spaces, default 4.
     pub tab_width: u16,
+    /// Positions of tabs in ascending order, tabs after the last stop use [`Text3dStyle::tab_width`].
+    ///
+    /// Tab stops move text after layout and do not affect wrapping.
+    /// Parsed text only keeps tabs with [`WhitespaceMode::PreserveTabs`](crate::WhitespaceMode::PreserveTabs)
+    /// or [`WhitespaceMode::Preserve`](crate::WhitespaceMode::Preserve).
+    pub tab_stops: Vec<TabStop>,
     /// If set, overwrite the size of `em` in the generated mesh.
     ///
     /// By default the mesh size is relative to [`Text3dStyle::size`], which is equivalent to `Some((size, size))`.
     pub world_scale: Option<Vec2>,
     /// If `Some`, render a text shadow.
     pub text_shadow: Option<(Srgba, Vec2)>,
+    /// Default layout of paragraphs, can be changed per paragraph with [`SegmentStyle::paragraph`].
+    pub paragraph: ParagraphStyle,
 }
 
 impl Default for Text3dStyle {
@@ -87,23 +111,115 @@ impl Default for Text3dStyle {
             stroke_in_front: false,
             stroke_join: StrokeJoin::Round,
             export: MeshExport::None,
+            wrap: TextWrap::WordOrGlyph,
+            hyphenate: None,
             tab_width: 4,
+            tab_stops: Vec::new(),
             world_scale: None,
             text_shadow: None,
+            paragraph: ParagraphStyle::default(),
         }
     }
 }
 
 /// Size of a segment.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SegmentSize {
     Flat(f32),
     Multiply(f32),
 }
 
+/// Layout of paragraphs, which are separated by line breaks.
+///
+/// Lengths are in multiples of [`Text3dStyle::size`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Default))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ParagraphStyle {
+    /// Space between the left of the text block and every line.
+    pub margin_left: f32,
+    /// Space between the right of the text block and every line, reduces the wrapping width.
+    pub margin_right: f32,
+    /// Extra indentation of the first line, negative for a hanging indent.
+    ///
+    /// A positive indent is also subtracted from the wrapping width of the other lines of the paragraph.
+    pub indent: f32,
+    /// Space added above the paragraph, except the first paragraph.
+    pub spacing_before: f32,
+    /// Space added below the paragraph, except the last paragraph.
+    pub spacing_after: f32,
+    /// If set, the first segment of the paragraph is a list marker placed before `margin_left`,
+    /// so that the text of the first line aligns with the following lines.
+    pub hanging_marker: bool,
+}
+
+/// Overrides fields of [`ParagraphStyle`], read from the style of the first segment in the paragraph.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SegmentParagraphStyle {
+    pub margin_left: Option<f32>,
+    pub margin_right: Option<f32>,
+    pub indent: Option<f32>,
+    pub spacing_before: Option<f32>,
+    pub spacing_after: Option<f32>,
+    pub hanging_marker: Option<bool>,
+}
+
+impl SegmentParagraphStyle {
+    pub fn join(&self, other: Self) -> Self {
+        SegmentParagraphStyle {
+            margin_left: other.margin_left.or(self.margin_left),
+            margin_right: other.margin_right.or(self.margin_right),
+            indent: other.indent.or(self.indent),
+            spacing_before: other.spacing_before.or(self.spacing_before),
+            spacing_after: other.spacing_after.or(self.spacing_after),
+            hanging_marker: other.hanging_marker.or(self.hanging_marker),
+        }
+    }
+
+    /// Fields of `self` that are different from `base`.
+    pub(crate) fn changes_from(&self, base: &Self) -> Self {
+        SegmentParagraphStyle {
+            margin_left: self
+                .margin_left
+                .filter(|_| self.margin_left != base.margin_left),
+            margin_right: self
+                .margin_right
+                .filter(|_| self.margin_right != base.margin_right),
+            indent: self.indent.filter(|_| self.indent != base.indent),
+            spacing_before: self
+                .spacing_before
+                .filter(|_| self.spacing_before != base.spacing_before),
+            spacing_after: self
+                .spacing_after
+                .filter(|_| self.spacing_after != base.spacing_after),
+            hanging_marker: self
+                .hanging_marker
+                .filter(|_| self.hanging_marker != base.hanging_marker),
+        }
+    }
+
+    /// Overwrite fields of `base`.
+    pub fn apply(&self, base: ParagraphStyle) -> ParagraphStyle {
+        ParagraphStyle {
+            margin_left: self.margin_left.unwrap_or(base.margin_left),
+            margin_right: self.margin_right.unwrap_or(base.margin_right),
+            indent: self.indent.unwrap_or(base.indent),
+            spacing_before: self.spacing_before.unwrap_or(base.spacing_before),
+            spacing_after: self.spacing_after.unwrap_or(base.spacing_after),
+            hanging_marker: self.hanging_marker.unwrap_or(base.hanging_marker),
+        }
+    }
+}
+
 /// Text style of a segment.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SegmentStyle {
     pub font: Option<Arc<str>>,
     pub size: Option<SegmentSize>,
@@ -117,6 +233,17 @@ pub struct SegmentStyle {
     pub strikethrough: Option<bool>,
     /// Can be referenced by [`GlyphMeta::MagicNumber`](crate::GlyphMeta::MagicNumber).
     pub magic_number: Option<f32>,
+    /// Can be referenced by [`GlyphMeta::ParamX`](crate::GlyphMeta::ParamX) to
+    /// [`GlyphMeta::ParamW`](crate::GlyphMeta::ParamW).
+    pub params: Option<Vec4>,
+    /// Can be referenced by [`GlyphMeta::NamedParam`](crate::GlyphMeta::NamedParam).
+    pub named_params: Vec<(Arc<str>, f32)>,
+    /// Animation effects like `{wave: text}`, nested effects are combined.
+    pub effects: SpanEffects,
+    /// Paragraph layout like margins, set by markdown lists and block quotes.
+    pub paragraph: SegmentParagraphStyle,
+    /// Format of a dynamic value like `{hp:0.0}`.
+    pub format: Option<ValueFormat>,
 }
 
 impl SegmentStyle {
@@ -139,6 +266,50 @@ impl SegmentStyle {
         .family(family)
     }
 
+    /// Obtain a value in `named_params`.
+    pub fn named_param(&self, key: &str) -> Option<f32> {
+        self.named_params
+            .iter()
+            .find(|(k, _)| k.as_ref() == key)
+            .map(|(_, v)| *v)
+    }
+
+    /// Fields of `self` that are different from `base`, i.e. set by a scope inside `base`.
+    pub(crate) fn changes_from(&self, base: &Self) -> Self {
+        macro_rules! changed {
+            ($field: ident) => {
+                self.$field.clone().filter(|_| self.$field != base.$field)
+            };
+        }
+        SegmentStyle {
+            font: changed!(font),
+            size: changed!(size),
+            fill_color: changed!(fill_color),
+            stroke_color: changed!(stroke_color),
+            fill: changed!(fill),
+            stroke: changed!(stroke),
+            weight: changed!(weight),
+            style: changed!(style),
+            underline: changed!(underline),
+            strikethrough: changed!(strikethrough),
+            magic_number: changed!(magic_number),
+            params: changed!(params),
+            named_params: self
+                .named_params
+                .iter()
+                .filter(|param| !base.named_params.contains(param))
+                .cloned()
+                .collect(),
+            effects: if self.effects == base.effects {
+                SpanEffects::NONE
+            } else {
+                self.effects
+            },
+            paragraph: self.paragraph.changes_from(&base.paragraph),
+            format: changed!(format),
+        }
+    }
+
     pub fn join(&self, other: Self) -> Self {
         SegmentStyle {
             font: other.font.or_else(|| self.font.clone()),
@@ -152,6 +323,22 @@ impl SegmentStyle {
             strikethrough: other.strikethrough.or(self.strikethrough),
             style: other.style.or(self.style),
             magic_number: other.magic_number.or(self.magic_number),
+            params: other.params.or(self.params),
+            named_params: if self.named_params.is_empty() {
+                other.named_params
+            } else {
+                let mut params = self.named_params.clone();
+                for (key, value) in other.named_params {
+                    match params.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, v)) => *v = value,
+                        None => params.push((key, value)),
+                    }
+                }
+                params
+            },
+            effects: self.effects.join(other.effects),
+            paragraph: self.paragraph.join(other.paragraph),
+            format: other.format.or(self.format),
         }
     }
 }
@@ -0,0 +1,506 @@
+use std::{num::NonZeroU32, str::FromStr};
+
+use crate::{
+    condition::parse_condition_expr,
+    misc::{Style, Weight},
+    parse::{parse_reveal_command, parse_style},
+    parse_util::{
+        ParseBuilder, ParseConditionFn, ParseError, ParseStyleFn, ParseValueFn, ParseWarning,
+        SpannedParseError, WhitespaceMode,
+    },
+    SegmentSize, SegmentStyle, Text3d, Text3dSegment,
+};
+
+/// Syntax of tag based rich text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TagSyntax {
+    /// BBCode like `[b]bold[/b]`.
+    BBCode,
+    /// Unity style rich text like `<b>bold</b>`.
+    Angle,
+}
+
+impl TagSyntax {
+    fn brackets(self) -> (char, char) {
+        match self {
+            TagSyntax::BBCode => ('[', ']'),
+            TagSyntax::Angle => ('<', '>'),
+        }
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+/// Name of a tag and whether it is a closing tag, if the content of brackets looks like a tag,
+/// i.e. `name`, `/name` or `name=value`.
+///
+/// Names cannot be empty, start with a digit or contain whitespaces and `/`.
+fn tag_name(content: &str) -> Option<(&str, bool)> {
+    let (content, closing) = match content.strip_prefix('/') {
+        Some(content) => (content, true),
+        None => (content, false),
+    };
+    let name = content
+        .split_once('=')
+        .map_or(content, |(name, _)| name)
+        .trim();
+    (!name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && !name.contains(|c: char| c.is_whitespace() || c == '/'))
+    .then_some((name, closing))
+}
+
+/// Parse built-in tags, returns `None` if not a built-in tag.
+fn parse_builtin_tag(name: &str, value: Option<&str>) -> Option<Result<SegmentStyle, ParseError>> {
+    let bad_value = || {
+        Err(ParseError::BadCommand(format!(
+            "{name}={}",
+            value.unwrap_or("")
+        )))
+    };
+    Some(match (name, value) {
+        ("b" | "strong", None) => Ok(SegmentStyle {
+            weight: Some(Weight::BOLD),
+            ..Default::default()
+        }),
+        ("i" | "em", None) => Ok(SegmentStyle {
+            style: Some(Style::Italic),
+            ..Default::default()
+        }),
+        ("u", None) | ("url", _) => Ok(SegmentStyle {
+            underline: Some(true),
+            ..Default::default()
+        }),
+        ("s" | "strike" | "del", None) => Ok(SegmentStyle {
+            strikethrough: Some(true),
+            ..Default::default()
+        }),
+        ("font" | "face", Some(font)) => Ok(SegmentStyle {
+            font: Some(font.into()),
+            ..Default::default()
+        }),
+        ("size", Some(size)) => match size.strip_suffix('%') {
+            Some(percent) => match f32::from_str(percent) {
+                Ok(percent) => Ok(SegmentStyle {
+                    size: Some(SegmentSize::Multiply(percent / 100.0)),
+                    ..Default::default()
+                }),
+                Err(_) => bad_value(),
+            },
+            None => match f32::from_str(size.trim_end_matches("px")) {
+                Ok(size) => Ok(SegmentStyle {
+                    size: Some(SegmentSize::Flat(size)),
+                    ..Default::default()
+                }),
+                Err(_) => bad_value(),
+            },
+        },
+        ("outline", Some(width)) => match u32::from_str(width) {
+            Ok(width) => Ok(SegmentStyle {
+                stroke: NonZeroU32::new(width),
+                ..Default::default()
+            }),
+            Err(_) => bad_value(),
+        },
+        _ => return None,
+    })
+}
+
+impl Text3d {
+    /// Parse BBCode like `[b]bold[/b] and [color=red]red[/color]`.
+    ///
+    /// See [`Text3d::parse_tagged`] for details.
+    pub fn parse_bbcode(
+        text: &str,
+        parser: ParseBuilder<impl ParseStyleFn, impl ParseValueFn, impl ParseConditionFn>,
+    ) -> Result<Self, ParseError> {
+        Text3d::parse_tagged(text, TagSyntax::BBCode, parser)
+            .map(|(text, _)| text)
+            .map_err(|e| e.error)
+    }
+
+    /// Parse Unity style rich text like `<b>bold</b> and <color=#ff0000>red</color>`.
+    ///
+    /// See [`Text3d::parse_tagged`] for details.
+    pub fn parse_angle_tags(
+        text: &str,
+        parser: ParseBuilder<impl ParseStyleFn, impl ParseValueFn, impl ParseConditionFn>,
+    ) -> Result<Self, ParseError> {
+        Text3d::parse_tagged(text, TagSyntax::Angle, parser)
+            .map(|(text, _)| text)
+            .map_err(|e| e.error)
+    }
+
+    /// Parse tag based rich text, the output is the same as the equivalent [`Text3d::parse`] markup.
+    ///
+    /// # Tags
+    ///
+    /// Shown in BBCode, replace `[]` with `<>` for [`TagSyntax::Angle`].
+    ///
+    /// * `[b]`, `[i]`, `[u]`, `[s]` Bold, italic, underline and strikethrough.
+    /// * `[color=red]`, `[color=#ff0000]` Fill color.
+    /// * `[size=20]`, `[size=150%]` Font size or a multiplier.
+    /// * `[font=Roboto]` Font family.
+    /// * `[outline=4]` Stroke size.
+    /// * `[url=...]` Underlined, the link is ignored.
+    /// * `[if=condition]`, `[if=!condition]` Conditions, closed by `[/if]`.
+    ///   Expressions like `[if="hp<10 && !shielded"]` are supported as in [`Text3d::parse`], but not else branches.
+    /// * `[pause=0.5]`, `[speed=2]` Reveal commands, not closed.
+    /// * `[pre]` Preserves whitespaces, see [`WhitespaceMode::Preserve`].
+    /// * `[nobr]` Spaces are non-breaking.
+    /// * `{value}` Dynamic values, `{hp:0.0}` formats a number, see [`ValueFormat`](crate::ValueFormat).
+    /// * `\[` escape character.
+    ///
+    /// Brackets that do not contain a tag, like `HP [100/100]` or `a < b and c > d`, are kept as text.
+    ///
+    /// Other tags like `[wave]`, `[red]` or `[my_style=2]` are parsed as styles in [`Text3d::parse`]
+    /// with `=` preserved, and are closed by their name, i.e. `[/my_style]`.
+    ///
+    /// Whitespaces follow [`ParseBuilder::with_whitespace`] but are not trimmed.
+    /// Unclosed and unexpected tags are [`ParseWarning`]s.
+    pub fn parse_tagged(
+        text: &str,
+        syntax: TagSyntax,
+        mut parser: ParseBuilder<impl ParseStyleFn, impl ParseValueFn, impl ParseConditionFn>,
+    ) -> Result<(Self, Vec<ParseWarning>), SpannedParseError> {
+        let (open, close) = syntax.brackets();
+        let mut buffer = String::new();
+        // Offset in `text` of the first byte of `buffer`.
+        let mut buffer_start = 0;
+        let mut segments = Vec::new();
+        let mut source_offsets = Vec::new();
+        let mut warnings = Vec::new();
+        // Tag name, style, position of `SkipIf` and position of the tag.
+        let mut stack: Vec<(String, SegmentStyle, Option<usize>, usize)> = Vec::new();
+
+        macro_rules! error {
+            ($error: expr, $span: expr) => {
+                SpannedParseError::new(text, $error, $span)
+            };
+        }
+        macro_rules! warn {
+            ($error: expr, $span: expr) => {
+                let warning = SpannedParseError::new(text, $error, $span);
+                if parser.strict {
+                    return Err(warning);
+                }
+                warnings.push(warning);
+            };
+        }
+        macro_rules! style {
+            () => {
+                stack.last().map(|x| x.1.clone()).unwrap_or_default()
+            };
+        }
+        macro_rules! push {
+            ($segment: expr, $offset: expr) => {
+                segments.push($segment);
+                source_offsets.push($offset);
+            };
+        }
+        macro_rules! push_seg {
+            () => {
+                if !buffer.is_empty() {
+                    push!(
+                        (
+                            Text3dSegment::String(core::mem::take(&mut buffer)),
+                            style!(),
+                        ),
+                        buffer_start
+                    );
+                }
+            };
+        }
+        macro_rules! pop {
+            () => {
+                if let Some((_, _, Some(r), _)) = stack.pop() {
+                    let l = segments.len().saturating_sub(1 + r);
+                    if let Text3dSegment::SkipIf { offset, .. }
+                    | Text3dSegment::SkipIfExpr { offset, .. } = &mut segments[r].0
+                    {
+                        *offset = l;
+                    }
+                }
+            };
+        }
+
+        let mut iter = text.char_indices().peekable();
+        while let Some((i, c)) = iter.next() {
+            if buffer.is_empty() {
+                buffer_start = i;
+            }
+            if c == open || c == '{' {
+                let end_char = if c == open { close } else { '}' };
+                let Some(len) = text[i..].find(end_char) else {
+                    warn!(ParseError::UnclosedBracket, i..i + 1);
+                    buffer.push(c);
+                    continue;
+                };
+                let end = i + len;
+                let span = i..end + 1;
+                let content = text[i + c.len_utf8()..end].trim();
+                if c == open && tag_name(content).is_none() {
+                    buffer.push(c);
+                    continue;
+                }
+                while iter.peek().is_some_and(|(j, _)| *j <= end) {
+                    iter.next();
+                }
+                push_seg!();
+                if c == '{' {
+                    let (name, format) = match content
+                        .split_once(':')
+                        .and_then(|(name, format)| Some((name, format.parse().ok()?)))
+                    {
+                        Some((name, format)) => (name.trim(), Some(format)),
+                        None => (content, None),
+                    };
+                    let (segment, style) = parser
+                        .parse_value
+                        .call(segments.len(), name)
+                        .map_err(|e| error!(e, span))?;
+                    let mut style = style!().join(style);
+                    style.format = style.format.or(format);
+                    push!((segment, style), i);
+                    continue;
+                }
+                if let Some(name) = content.strip_prefix('/') {
+                    let name = name.trim().to_ascii_lowercase();
+                    let Some(position) = stack.iter().rposition(|(n, ..)| *n == name) else {
+                        warn!(ParseError::UnexpectedBracket, span);
+                        continue;
+                    };
+                    while stack.len() > position + 1 {
+                        let start = stack.last().map(|x| x.3).unwrap_or(0);
+                        warn!(ParseError::UnclosedBracket, start..start + 1);
+                        pop!();
+                    }
+                    pop!();
+                    continue;
+                }
+                let (name, value) = match content.split_once('=') {
+                    Some((name, value)) => (name.trim().to_ascii_lowercase(), Some(unquote(value))),
+                    None => (content.to_ascii_lowercase(), None),
+                };
+                if let Some(command) =
+                    value.and_then(|v| parse_reveal_command(&format!("{name}={v}")))
+                {
+                    push!((Text3dSegment::Reveal(command), style!()), i);
+                    continue;
+                }
+                if name == "pre" || name == "nobr" {
+                    stack.push((name, style!(), None, i));
+                    continue;
+                }
+                if name == "if" {
+                    match parse_condition_expr(
+                        value.unwrap_or(""),
+                        segments.len(),
+                        &mut parser.parse_condition,
+                        &mut parser.parse_value,
+                    )
+                    .map_err(|e| error!(e, span.clone()))?
+                    {
+                        Err(true) => {
+                            stack.push((name, style!(), None, i));
+                        }
+                        Err(false) => {
+                            // skip all wrapped items.
+                            let mut depth = 1;
+                            let mut rest = &text[end + 1..];
+                            while depth > 0 {
+                                let Some(next) = rest.find(open) else {
+                                    warn!(ParseError::UnclosedBracket, i..i + 1);
+                                    rest = "";
+                                    break;
+                                };
+                                rest = &rest[next + open.len_utf8()..];
+                                let content = rest.find(close).map_or(rest, |x| &rest[..x]);
+                                match tag_name(content.trim()) {
+                                    Some((name, true)) if name.eq_ignore_ascii_case("if") => {
+                                        depth -= 1
+                                    }
+                                    Some((name, false)) if name.eq_ignore_ascii_case("if") => {
+                                        depth += 1
+                                    }
+                                    _ => (),
+                                }
+                            }
+                            let skip_to = match rest.find(close) {
+                                Some(x) => text.len() - rest.len() + x,
+                                None => text.len(),
+                            };
+                            while iter.peek().is_some_and(|(j, _)| *j <= skip_to) {
+                                iter.next();
+                            }
+                        }
+                        Ok(condition) => {
+                            let pos = segments.len();
+                            push!((condition.into_segment(false, 0), style!()), i);
+                            stack.push((name, style!(), Some(pos), i));
+                        }
+                    }
+                    continue;
+                }
+                let result = match parse_builtin_tag(&name, value) {
+                    Some(result) => result,
+                    None if name == "color" || name == "colour" => {
+                        parse_style(value.unwrap_or(""), &mut parser.parse_style)
+                    }
+                    None => parse_style(content, &mut parser.parse_style),
+                };
+                let style = style!().join(result.map_err(|e| error!(e, span))?);
+                stack.push((name, style, None, i));
+                continue;
+            }
+            match c {
+                '\\' => {
+                    if let Some((_, c)) = iter.next() {
+                        buffer.push(c);
+                    } else {
+                        buffer.push('\\');
+                    }
+                }
+                c if c.is_whitespace() => {
+                    let mut end = i + c.len_utf8();
+                    while let Some((j, c)) = iter.peek() {
+                        if !c.is_whitespace() {
+                            break;
+                        }
+                        end = j + c.len_utf8();
+                        iter.next();
+                    }
+                    let mode = match stack.iter().any(|(name, ..)| name == "pre") {
+                        true => WhitespaceMode::Preserve,
+                        false => parser.whitespace,
+                    };
+                    let start = buffer.len();
+                    mode.push_whitespace(&text[i..end], &mut buffer);
+                    if stack.iter().any(|(name, ..)| name == "nobr") {
+                        let nobr = buffer[start..].replace(' ', "\u{A0}");
+                        buffer.truncate(start);
+                        buffer.push_str(&nobr);
+                    }
+                }
+                c => buffer.push(c),
+            }
+        }
+        if !buffer.is_empty() {
+            push!((Text3dSegment::String(buffer), style!()), buffer_start);
+        }
+        while let Some(start) = stack.last().map(|x| x.3) {
+            warn!(ParseError::UnclosedBracket, start..start + 1);
+            pop!();
+        }
+        Ok((
+            Text3d {
+                segments,
+                source_offsets,
+            },
+            warnings,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::entity::Entity;
+
+    use crate::{
+        parse_util::{ConditionOutput, ParseConditionFn, ParseStyleFn, ParseValueFn},
+        ParseBuilder, ParseError, SegmentStyle, Text3d, Text3dSegment,
+    };
+
+    fn strings(text: &Text3d) -> Vec<&str> {
+        text.segments
+            .iter()
+            .filter_map(|(segment, _)| match segment {
+                Text3dSegment::String(s) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// `hp` is a dynamic value, `a` and `b` are dynamic conditions, `yes` and `no` are constants.
+    fn builder() -> ParseBuilder<impl ParseStyleFn, impl ParseValueFn, impl ParseConditionFn> {
+        ParseBuilder::new()
+            .with_parse_value(|s| match s {
+                "hp" => Ok((
+                    Text3dSegment::Extract(Entity::from_raw_u32(2).unwrap()),
+                    SegmentStyle::default(),
+                )),
+                _ => Err(ParseError::Custom(format!("Unknown value {s}."))),
+            })
+            .with_parse_condition(|s| match s {
+                "a" => Ok(ConditionOutput::Dynamic(Entity::from_raw_u32(0).unwrap())),
+                "b" => Ok(ConditionOutput::Dynamic(Entity::from_raw_u32(1).unwrap())),
+                "yes" => Ok(ConditionOutput::Constant(true)),
+                "no" => Ok(ConditionOutput::Constant(false)),
+                _ => Err(ParseError::Custom(format!("Unknown condition {s}."))),
+            })
+    }
+
+    fn assert_same(bbcode: &str, markup: &str) {
+        let expected = format!("{:?}", Text3d::parse(markup, builder()).unwrap().segments);
+        let text = Text3d::parse_bbcode(bbcode, builder()).unwrap();
+        assert_eq!(format!("{:?}", text.segments), expected, "{bbcode}");
+        let angle = bbcode.replace('[', "<").replace(']', ">");
+        let text = Text3d::parse_angle_tags(&angle, builder()).unwrap();
+        assert_eq!(format!("{:?}", text.segments), expected, "{angle}");
+    }
+
+    #[test]
+    fn brackets_without_tags_are_text() {
+        let text = Text3d::parse_bbcode("HP [100/100] [b]full[/b]", ParseBuilder::new()).unwrap();
+        assert_eq!(strings(&text), ["HP [100/100] ", "full"]);
+        let text = Text3d::parse_angle_tags("a < b and c > d", ParseBuilder::new()).unwrap();
+        assert_eq!(strings(&text), ["a < b and c > d"]);
+    }
+
+    #[test]
+    fn same_as_markup() {
+        assert_same("[b][color=red]x[/color][/b]", "{bold, red:x}");
+        assert_same("[b]a[i]b[/i]c[/b]", "{bold:a{italic:b}c}");
+        assert_same(
+            "[u]a[/u] [s]b[/s] [url=https://a.b]c[/url]",
+            "{underline:a} {strikethrough:b} {underline:c}",
+        );
+        assert_same("[size=20]a[/size][size=150%]b[/size]", "{$20:a}{*1.5:b}");
+        assert_same(
+            "[font=Roboto]a[/font][outline=4]b[/outline]",
+            "{f-Roboto:a}{s-4:b}",
+        );
+        assert_same(
+            "[wave]a[/wave][color=#ff0000]b[/color]",
+            "{wave:a}{#ff0000:b}",
+        );
+        assert_same("HP: [b]{hp:0.0}[/b] {hp}", "HP: {bold:{hp:0.0}} {hp}");
+        assert_same(
+            "Well[pause=0.5]... [speed=2]I guess.",
+            "Well{pause=0.5}... {speed=2}I guess.",
+        );
+    }
+
+    #[test]
+    fn conditions_same_as_markup() {
+        assert_same("[if=a]x[/if]y", "{?a:x}y");
+        assert_same("[if=!a]x[/if]y", "{?!a:x}y");
+        assert_same("[if=\"a && !b\"]x[b]y[/b][/if]", "{?a && !b:x{bold:y}}");
+        assert_same("[if=a][if=b]x[/if]y[/if]", "{?a:{?b:x}y}");
+        assert_same("[if=yes]x[/if]y", "{?yes:x}y");
+        assert_same("[if=no]x[/if]y", "{?no:x}y");
+        assert_same("[if=no][if=a]x[/if][/if]y", "y");
+        assert_same("[if=no]x[iframe]y[ifx][/if]z", "z");
+    }
+
+    #[test]
+    fn underline_has_no_value() {
+        assert!(Text3d::parse_bbcode("[u=foo]x[/u]", ParseBuilder::new()).is_err());
+        assert!(Text3d::parse_angle_tags("<u=foo>x</u>", ParseBuilder::new()).is_err());
+    }
+}
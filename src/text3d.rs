@@ -2,7 +2,7 @@ use bevy::{
     asset::Handle,
     ecs::{
         component::Component,
-        entity::Entity,
+        entity::{Entity, EntityMapper, MapEntities},
         lifecycle::HookContext,
         world::{DeferredWorld, Mut},
     },
@@ -12,26 +12,38 @@ use bevy::{
 use bevy::{ecs::reflect::ReflectComponent, reflect::Reflect};
 
 use crate::{
-    styling::SegmentStyle, SharedSegment, Text3dBounds, Text3dDimensionOut, Text3dStyle,
-    TextAtlasHandle,
+    styling::SegmentStyle, ConditionExpr, RevealCommand, SharedSegment, Text3dBounds,
+    Text3dDimensionOut, Text3dStyle, TextAtlasHandle,
 };
 
 /// A rich text component.
 ///
 /// Requires [`Text3dStyle`], [`Text3dBounds`], [`TextAtlasHandle`], [`Text3dDimensionOut`].
-#[derive(Debug, Component)]
+///
+/// # Scenes
+///
+/// With features `reflect` and `serde`, [`Text3d`] can be saved in a `DynamicWorld`, entities in segments are mapped on load.
+/// [`Text3dSegment::Image`] is saved by its asset path and loaded again by the deserializer's `LoadFromPath`,
+/// `Handle::Uuid` is saved as is. Handles from [`Assets::add`](bevy::asset::Assets::add) have no path,
+/// bevy logs a warning and saves them as the default handle.
+#[derive(Debug, Component, Default)]
 #[require(Text3dDimensionOut, Text3dBounds, TextAtlasHandle, Text3dStyle)]
-#[component(on_remove = text_3d_on_remove)]
+#[component(on_remove = text_3d_on_remove, map_entities)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Component))]
 pub struct Text3d {
     pub segments: Vec<(Text3dSegment, SegmentStyle)>,
+    /// Byte offset of each segment in the parsed source string,
+    /// used by [`GlyphMeta::CharIndex`](crate::GlyphMeta::CharIndex).
+    ///
+    /// Empty if the text is not parsed, ignored if the number of segments changes.
+    pub source_offsets: Vec<usize>,
 }
 
 /// A string segment in [`Text3d`].
 ///
 /// `Extract` reads data from an entity's [`FetchedTextSegment`](crate::FetchedTextSegment) component.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 pub enum Text3dSegment {
     /// A string segment.
@@ -56,6 +68,47 @@ pub enum Text3dSegment {
         skip_if: bool,
         offset: usize,
     },
+    /// Skips the next `offset` segments if a [`ConditionExpr`] equals `skip_if`.
+    SkipIfExpr {
+        condition: ConditionExpr,
+        skip_if: bool,
+        offset: usize,
+    },
+    /// Controls [`Text3dReveal`](crate::Text3dReveal), not rendered.
+    Reveal(RevealCommand),
+    /// [`FetchedStyle`](crate::FetchedStyle) on an entity, overriding the styles of the next `offset` segments.
+    Style { style: Entity, offset: usize },
+    /// Repeats the next `offset` segments for each item of a [`FetchedList`](crate::FetchedList) on an entity.
+    Each { list: Entity, offset: usize },
+    /// A field of the current item in [`Text3dSegment::Each`], read from its [`FetchedItem`](crate::FetchedItem).
+    Field(String),
+}
+
+impl MapEntities for Text3d {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        for (segment, _) in &mut self.segments {
+            segment.map_entities(entity_mapper);
+        }
+    }
+}
+
+impl MapEntities for Text3dSegment {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        match self {
+            Text3dSegment::Extract(entity) => *entity = entity_mapper.get_mapped(*entity),
+            Text3dSegment::SkipIf { condition, .. } => {
+                *condition = entity_mapper.get_mapped(*condition)
+            }
+            Text3dSegment::Style { style, .. } => *style = entity_mapper.get_mapped(*style),
+            Text3dSegment::Each { list, .. } => *list = entity_mapper.get_mapped(*list),
+            Text3dSegment::SkipIfExpr { condition, .. } => {
+                for entity in condition.entities_mut() {
+                    *entity = entity_mapper.get_mapped(*entity)
+                }
+            }
+            _ => (),
+        }
+    }
 }
 
 impl Text3dSegment {
@@ -63,9 +116,22 @@ impl Text3dSegment {
         match self {
             Text3dSegment::Extract(entity) => Some(*entity),
             Text3dSegment::SkipIf { condition, .. } => Some(*condition),
+            Text3dSegment::Style { style, .. } => Some(*style),
+            Text3dSegment::Each { list, .. } => Some(*list),
             _ => None,
         }
     }
+
+    /// All entities referenced by this segment, including those in a [`ConditionExpr`].
+    pub fn external_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        let expr = match self {
+            Text3dSegment::SkipIfExpr { condition, .. } => Some(condition.entities()),
+            _ => None,
+        };
+        self.get_external_segment()
+            .into_iter()
+            .chain(expr.into_iter().flatten())
+    }
 }
 
 fn text_3d_on_remove(mut world: DeferredWorld, cx: HookContext) {
@@ -78,7 +144,7 @@ fn text_3d_on_remove(mut world: DeferredWorld, cx: HookContext) {
     let to_be_dropped: Vec<_> = text
         .segments
         .iter()
-        .filter_map(|x| x.0.get_external_segment())
+        .flat_map(|x| x.0.external_entities())
         .filter(|entity| {
             world
                 .get_entity(*entity)
@@ -99,6 +165,7 @@ impl Text3d {
         let string = s.to_string();
         Self {
             segments: vec![(Text3dSegment::String(string), Default::default())],
+            source_offsets: Vec::new(),
         }
     }
 
@@ -106,6 +173,7 @@ impl Text3d {
     pub fn from_extract(entity: Entity) -> Self {
         Self {
             segments: vec![(Text3dSegment::Extract(entity), Default::default())],
+            source_offsets: Vec::new(),
         }
     }
 
@@ -152,3 +220,198 @@ impl Text3d {
             .filter_map_unchanged(|v| Self::get_segment_mut(v, index))
     }
 }
+
+#[cfg(all(test, feature = "reflect", feature = "serde"))]
+mod tests {
+    use std::any::TypeId;
+
+    use bevy::{
+        app::{App, TaskPoolPlugin},
+        asset::{
+            AssetApp, AssetPath, AssetPlugin, AssetServer, Handle, LoadFromPath, UntypedHandle,
+        },
+        ecs::{
+            entity::{Entity, EntityHashMap, MapEntities},
+            reflect::AppTypeRegistry,
+        },
+        image::Image,
+        world_serialization::{serde::WorldDeserializer, DynamicWorldBuilder},
+    };
+    use serde::de::DeserializeSeed;
+
+    use super::{Text3d, Text3dSegment};
+    use crate::{FetchedText, GlyphMeta, MeshExport, MeshExportEntry, SegmentStyle, Text3dStyle};
+
+    struct NoAssets;
+
+    impl LoadFromPath for NoAssets {
+        fn load_from_path_erased(&mut self, _: TypeId, _: AssetPath<'static>) -> UntypedHandle {
+            unreachable!()
+        }
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        crate::register_types(&mut app);
+        app
+    }
+
+    #[test]
+    fn dynamic_world_round_trip() {
+        let mut source = app();
+        let world = source.world_mut();
+        let value = world.spawn(FetchedText("12".into())).id();
+        let style = SegmentStyle {
+            font: Some("Roboto".into()),
+            named_params: vec![("glow".into(), 0.5)],
+            ..Default::default()
+        };
+        let export = MeshExport::Custom(vec![MeshExportEntry::from_name(
+            "Vertex_Glow",
+            9000,
+            &[GlyphMeta::NamedParam("glow".into()), GlyphMeta::Index],
+        )]);
+        let text = world
+            .spawn((
+                Text3d {
+                    segments: vec![
+                        (Text3dSegment::String("HP: ".into()), style.clone()),
+                        (Text3dSegment::Extract(value), SegmentStyle::default()),
+                    ],
+                    ..Default::default()
+                },
+                Text3dStyle {
+                    font: "Roboto".into(),
+                    hyphenate: Some("en".into()),
+                    export: export.clone(),
+                    ..Default::default()
+                },
+            ))
+            .id();
+
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let ron = DynamicWorldBuilder::from_world(world, &registry)
+            .deny_all()
+            .allow_component::<Text3d>()
+            .allow_component::<Text3dStyle>()
+            .allow_component::<FetchedText>()
+            .extract_entities([value, text].into_iter())
+            .build()
+            .serialize(&registry)
+            .unwrap();
+        let mut deserializer = ron::de::Deserializer::from_str(&ron).unwrap();
+        let loaded = WorldDeserializer {
+            type_registry: &registry,
+            load_from_path: &mut NoAssets,
+        }
+        .deserialize(&mut deserializer)
+        .unwrap();
+
+        let mut target = app();
+        let mut entities = EntityHashMap::default();
+        loaded
+            .write_to_world_with(target.world_mut(), &mut entities, &registry)
+            .unwrap();
+        let world = target.world();
+        let entity = world.entity(entities[&text]);
+        let segments = &entity.get::<Text3d>().unwrap().segments;
+        assert!(
+            matches!(&segments[0], (Text3dSegment::String(s), x) if s == "HP: " && x == &style)
+        );
+        assert!(matches!(&segments[1].0, Text3dSegment::Extract(e) if *e == entities[&value]));
+        let loaded_style = entity.get::<Text3dStyle>().unwrap();
+        assert_eq!(&*loaded_style.font, "Roboto");
+        assert_eq!(loaded_style.hyphenate.as_deref(), Some("en"));
+        assert_eq!(loaded_style.export, export);
+    }
+
+    #[test]
+    fn image_path_round_trip() {
+        let mut source = app();
+        source
+            .add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+            .init_asset::<Image>();
+        let image: Handle<Image> = source.world().resource::<AssetServer>().load("emoji.png");
+        let world = source.world_mut();
+        let text = world
+            .spawn(Text3d {
+                segments: vec![(
+                    Text3dSegment::Image { image, width: 1.5 },
+                    Default::default(),
+                )],
+                ..Default::default()
+            })
+            .id();
+
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let ron = DynamicWorldBuilder::from_world(world, &registry)
+            .deny_all()
+            .allow_component::<Text3d>()
+            .extract_entities([text].into_iter())
+            .build()
+            .serialize(&registry)
+            .unwrap();
+        assert!(ron.contains("emoji.png"));
+
+        let mut target = app();
+        target
+            .add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+            .init_asset::<Image>();
+        let mut server = target.world().resource::<AssetServer>().clone();
+        let mut deserializer = ron::de::Deserializer::from_str(&ron).unwrap();
+        let loaded = WorldDeserializer {
+            type_registry: &registry,
+            load_from_path: &mut server,
+        }
+        .deserialize(&mut deserializer)
+        .unwrap();
+        let mut entities = EntityHashMap::default();
+        loaded
+            .write_to_world_with(target.world_mut(), &mut entities, &registry)
+            .unwrap();
+        let segments = &target
+            .world()
+            .get::<Text3d>(entities[&text])
+            .unwrap()
+            .segments;
+        let Text3dSegment::Image { image, width } = &segments[0].0 else {
+            panic!("expected an image segment");
+        };
+        assert_eq!(image.path(), Some(&AssetPath::from("emoji.png")));
+        assert_eq!(*width, 1.5);
+    }
+
+    #[test]
+    fn map_entities_remaps_conditions_and_lists() {
+        let [condition, list, mapped_condition, mapped_list] =
+            [1, 2, 3, 4].map(|i| Entity::from_raw_u32(i).unwrap());
+        let mut text = Text3d {
+            segments: vec![
+                (
+                    Text3dSegment::SkipIf {
+                        condition,
+                        skip_if: true,
+                        offset: 1,
+                    },
+                    Default::default(),
+                ),
+                (Text3dSegment::Each { list, offset: 1 }, Default::default()),
+            ],
+            ..Default::default()
+        };
+        let mut mapper = EntityHashMap::default();
+        mapper.insert(condition, mapped_condition);
+        mapper.insert(list, mapped_list);
+        text.map_entities(&mut mapper);
+        assert!(matches!(
+            text.segments[0].0,
+            Text3dSegment::SkipIf { condition, skip_if: true, offset: 1 } if condition == mapped_condition
+        ));
+        assert!(matches!(
+            text.segments[1].0,
+            Text3dSegment::Each { list, offset: 1 } if list == mapped_list
+        ));
+    }
+}